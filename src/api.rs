@@ -1,10 +1,12 @@
-use actix_web::{get, post, web,  http::header::ContentType, HttpResponse, HttpServer, Responder};
+use actix_web::{get, post, delete, web,  http::header::{self, ContentType}, HttpRequest, HttpResponse, HttpServer, Responder};
 use bitcoin::{ Address, Amount, OutPoint, Transaction, Txid};
 use bitvm::bridge::{connectors::{connector_c, revealer}, transactions::{kick_off_1, peg_in_refund}, graphs::base::DUST_AMOUNT};
 use rusqlite::Connection;
-use serde::Serialize;
+use bitcoincore_rpc::Client;
+use serde::{Deserialize, Serialize};
 use log::{info, warn, error};
-use crate::{config, sql::{self, update_user_data, UserData}, transactions, utils};
+use tempfile::TempDir;
+use crate::{auth, classify, config, faucet, graph, health, metrics, scenario, setup, sql::{self, update_user_data, UserData}, rpc::{RpcClient, RpcPool}, steps, transactions, utils, workers};
 
 #[derive(Serialize)]
 struct TxInput {
@@ -20,13 +22,39 @@ struct TxOutput {
     value: Amount,
 }
 
+#[derive(Serialize)]
+struct VerifyResult {
+    result: String,
+    leaf_index: Option<usize>,
+    hint_script: Option<String>,
+}
+
+impl VerifyResult {
+    fn from_validation(res: Option<(usize, bitvm::treepp::Script)>) -> Self {
+        match res {
+            Some((leaf_index, hint_script)) => VerifyResult {
+                result: "invalid".to_string(),
+                leaf_index: Some(leaf_index),
+                hint_script: Some(hex::encode(hint_script.compile().as_bytes())),
+            },
+            _ => VerifyResult {
+                result: "valid".to_string(),
+                leaf_index: None,
+                hint_script: None,
+            },
+        }
+    }
+}
+
+// name the tx & label its inputs/outputs from their scripts, `tx_type` is only checked against the result
 #[get("/get-named-inputs-outputs/{tx_type}/{txid}")]
-async fn get_named_inputs_outputs(path: web::Path<(u8, String)>) -> impl Responder {
+async fn get_named_inputs_outputs(path: web::Path<(u8, String)>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
     #[derive(Serialize)]
     struct ResponseStruct {
         tx_name: String,
-        inputs: Vec<(String, Address, Amount)>,
-        outputs: Vec<(String, Address, Amount)>,
+        workflow_id: Option<i32>,
+        inputs: Vec<(String, Option<Address>, Amount)>,
+        outputs: Vec<(String, Option<Address>, Amount)>,
     }
 
     let (tx_type, txid) = path.into_inner();
@@ -38,257 +66,38 @@ async fn get_named_inputs_outputs(path: web::Path<(u8, String)>) -> impl Respond
             return HttpResponse::BadRequest().body(e.to_string())
         }
     };
-
-    let rpc = match utils::new_rpc_client().await {
-        Ok(v) => v,
-        Err(e) => { 
-            error!("/get-named-inputs-outputs/{tx_type}/{txid}: fail to connect bitcoind: {}", e);
-            return HttpResponse::InternalServerError().body(e.to_string())
-        }
-    };
-
-    let (inputs, outputs) = match tx_type {
-        2 => { // PegIn
-            match get_inputs_outputs(&rpc, tx_type, txid) {
-                Ok(v) => v,
-                Err(e) => return HttpResponse::InternalServerError().body(e.to_string())
-            }
-        },
-        3 => { // Kickoff_1
-            match get_inputs_outputs(&rpc, tx_type, txid) {
-                Ok(v) => v,
-                Err(e) => return HttpResponse::InternalServerError().body(e.to_string())
-            }
-        },
-        4 => { // Kickoff_2
-            match get_inputs_outputs(&rpc, tx_type, txid) {
-                Ok(v) => v,
-                Err(e) => return HttpResponse::InternalServerError().body(e.to_string())
-            }
-        },
-        5 => { // Challenge
-            match get_inputs_outputs(&rpc, tx_type, txid) {
-                Ok(v) => v,
-                Err(e) => return HttpResponse::InternalServerError().body(e.to_string())
-            }
-        },
-        6 => { // Take_1
-            match get_inputs_outputs(&rpc, tx_type, txid) {
-                Ok(v) => v,
-                Err(e) => return HttpResponse::InternalServerError().body(e.to_string())
-            }
-        },
-        7 => { // Assert
-            let revealers_num = transactions::REVEALERS_ADDRESS.len();
-            let r = transactions::REVEALERS_ADDRESS.clone()
-                .into_iter()
-                .zip(vec![Amount::from_sat(DUST_AMOUNT); revealers_num].into_iter())
-                // .zip(transactions::get_revealers_script_pubkey().clone())
-                // .map(|((x,y),z)| (x,y,z))
-                .collect();
-            let inputs = [
-                vec![(transactions::get_precomputed_connector_b_address(), transactions::get_connector_b_amount())],
-                r,
-            ].concat();
-            let outputs = vec![
-                (transactions::get_precomputed_connector_4_address(), transactions::get_connector_4_amount()),
-                (transactions::get_precomputed_connector_5_address(), transactions::get_connector_5_amount()), 
-                (transactions::get_precomputed_connector_c_address(), transactions::get_connector_c_amount()),
-            ];
-            (inputs, outputs)
-        },
-        8 => { // Take_2
-            let tx= match utils::get_raw_tx(&rpc, txid) {
-                Ok(v) => v,
-                Err(e) => { 
-                    error!("/get-named-inputs-outputs/{tx_type}/{txid}: fail to get tx:{txid} : {}", e);
-                    return HttpResponse::InternalServerError().body(e.to_string())
-                }
-            };
-            let inputs = vec![
-                (transactions::get_precomputed_connector_0_address(), transactions::get_connector_0_amount()), 
-                (transactions::get_precomputed_connector_4_address(), transactions::get_connector_4_amount()),
-                (transactions::get_precomputed_connector_5_address(), transactions::get_connector_5_amount()), 
-                (transactions::get_precomputed_connector_c_address(), transactions::get_connector_c_amount()),
-
-            ];
-            let mut outputs = vec![];
-            for i in 0..tx.output.len() {
-                let output_i_addr = match Address::from_script(&tx.output[i].script_pubkey, config::network()) {
-                    Ok(v) => v,
-                    Err(e) => { 
-                        error!("/get-named-inputs-outputs/{tx_type}/{txid}: fail to calc address: {}", e);
-                        return HttpResponse::InternalServerError().body(e.to_string())
-                    }
-                };
-                // let output_i_scrpub = hex::encode(tx.output[i].script_pubkey.clone().into_bytes());
-                let output_i_amount = tx.output[i].value;
-                outputs.push((output_i_addr, output_i_amount));
-            }
-            (inputs, outputs)
-        },  
-        9 => { // Disprove
-            let tx= match utils::get_raw_tx(&rpc, txid) {
-                Ok(v) => v,
-                Err(e) => { 
-                    error!("/get-named-inputs-outputs/{tx_type}/{txid}: fail to get tx:{txid} : {}", e);
-                    return HttpResponse::InternalServerError().body(e.to_string())
-                }
-            };
-            let inputs = vec![
-                (transactions::get_precomputed_connector_5_address(), transactions::get_connector_5_amount()), 
-                (transactions::get_precomputed_connector_c_address(), transactions::get_connector_c_amount()),
-            ];
-            let mut outputs = vec![];
-            for i in 0..tx.output.len() {
-                let output_i_addr = match Address::from_script(&tx.output[i].script_pubkey, config::network()) {
-                    Ok(v) => v,
-                    Err(e) => { 
-                        error!("/get-named-inputs-outputs/{tx_type}/{txid}: fail to calc address: {}", e);
-                        return HttpResponse::InternalServerError().body(e.to_string())
-                    }
-                };
-                // let output_i_scrpub = hex::encode(tx.output[i].script_pubkey.clone().into_bytes());
-                let output_i_amount = tx.output[i].value;
-                outputs.push((output_i_addr, output_i_amount));
-            }
-            (inputs, outputs)
-        },
-        _ => { // Unidentified
+    let expected_name = match classify::tx_type_name(tx_type) {
+        Some(v) => v,
+        _ => {
             error!("/get-named-inputs-outputs/{tx_type}/{txid}: Unidentified tx type");
             return HttpResponse::BadRequest().body("Unidentified tx type".to_string())
         }
     };
 
-    fn get_inputs_outputs(rpc: &bitcoincore_rpc::Client, tx_type: u8, txid: Txid) -> Result<(Vec<(Address, Amount)>, Vec<(Address, Amount)>), String> {
-        let tx= match utils::get_raw_tx(&rpc, txid) {
-            Ok(v) => v,
-            Err(e) => { 
-                error!("/get-named-inputs-outputs/{tx_type}/{txid}: fail to get tx:{txid} : {}", e);
-                return Err(e.to_string())
-            }
-        };
-        let mut inputs: Vec<(Address, Amount)> = vec![];
-        let mut outputs: Vec<(Address, Amount)> = vec![];
-        let mut prev_tx_cache: (Txid, Transaction) = (txid, tx.clone());
-        for i in 0..tx.input.len() {
-            let prev_txid = tx.input[i].previous_output.txid;
-            if prev_txid != prev_tx_cache.0 {
-                prev_tx_cache = match utils::get_raw_tx(&rpc, prev_txid) {
-                    Ok(v) => (prev_txid, v),
-                    Err(e) => { 
-                        error!("/get-named-inputs-outputs/{tx_type}/{txid}: fail to get tx:{prev_txid} : {}", e);
-                        return Err(e.to_string())
-                    }
-                };
-            };
-            let prev_vout = tx.input[i].previous_output.vout;
-            let prev_outpoint = match prev_tx_cache.1.output.get(prev_vout as usize) {
-                Some(v) => v,
-                _ => { 
-                    error!("/get-named-inputs-outputs/{tx_type}/{txid}: fail to get prev_txout");
-                    return Err("fail to get prev_txout".to_string())
-                },
-            };
-            let input_i_addr = match Address::from_script(&prev_outpoint.script_pubkey, config::network()) {
-                Ok(v) => v,
-                Err(e) => { 
-                    error!("/get-named-inputs-outputs/{tx_type}/{txid}: fail to calc address: {}", e);
-                    return Err(e.to_string())
-                }
-            };
-            // let input_i_scrpub = hex::encode(prev_outpoint.script_pubkey.clone().into_bytes());
-            let input_i_amount = prev_outpoint.value;
-            inputs.push((input_i_addr, input_i_amount));
+    let rpc = rpc_pool.get();
+    let named_tx = match workers::run_blocking(move || {
+        let db = sql::open_db()?;
+        classify::classify_tx(&rpc, &db, txid)
+    }).await {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/get-named-inputs-outputs/{tx_type}/{txid}: fail to classify tx: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
         }
-        for i in 0..tx.output.len() {
-            let output_i_addr = match Address::from_script(&tx.output[i].script_pubkey, config::network()) {
-                Ok(v) => v,
-                Err(e) => { 
-                    error!("/get-named-inputs-outputs/{tx_type}/{txid}: fail to calc address: {}", e);
-                    return Err(e.to_string())
-                }
-            };
-            // let output_i_scrpub = hex::encode(tx.output[i].script_pubkey.clone().into_bytes());
-            let output_i_amount = tx.output[i].value;
-            outputs.push((output_i_addr, output_i_amount));
-        }
-        Ok((inputs, outputs))
-    }
-
-    let revealers = vec!["revealer"; 59];
-    let (tx_name, input_names, output_names) = match (inputs.len(), outputs.len()) {
-        (1,1) => (
-            "Peg-In",
-            vec!["User"],
-            vec!["connector-0"],
-        ),
-        (1,3) => (
-            "Kickoff-1",
-            vec!["Operator"],
-            vec!["connector-a","connector-1","connector-2"],
-        ),
-        (1,61) => (
-            "Kickoff-2",
-            vec!["connector-1"],
-            [vec!["connector-3","connector-b"], revealers].concat(),
-        ),
-        (2,1) => (
-            "Challenge",
-            vec!["connector-a","Challenger"],
-            vec!["Operator"],
-        ),
-        (60,3) => (
-            "Assert",
-            [vec!["connector-b"], revealers].concat(),
-            vec!["connector-4","connector-5","connector-c"]
-        ),
-        (2,2) => (
-            "Disprove",
-            vec!["connector-5","connector-c"],
-            vec!["Challenger","Burn"],
-        ),
-        (4,1) => {
-            let input_3_addr = inputs[3].0.clone();
-            let connector_c_addr = transactions::get_precomputed_connector_c_address();
-            if input_3_addr != connector_c_addr {
-                (
-                    "Take-1",
-                    vec!["connector-0","connector-3","connector-a","connector-b"],
-                    vec!["Operator"],
-                )
-            } else {
-                (
-                    "Take-2",
-                    vec!["connector-0","connector-4","connector-5","connector-c"],
-                    vec!["Operator"],
-                )
-            }
-        },
-        (x,y) => (
-            "Unidentified",
-            vec![""; x],
-            vec![""; y],
-        )
     };
 
-    if tx_name == "Unidentified" {
+    if named_tx.tx_name == classify::UNIDENTIFIED {
         warn!("/get-named-inputs-outputs/{tx_type}/{txid}: unidentified tx");
+    } else if named_tx.tx_name != expected_name {
+        warn!("/get-named-inputs-outputs/{tx_type}/{txid}: requested as {expected_name}, identified as {}", named_tx.tx_name);
     };
 
-    let inputs = input_names.into_iter()
-        .zip(inputs.into_iter())
-        .map(|(x,(y,z))| (x.to_string(),y,z))
-        .collect();
-
-    let outputs = output_names.into_iter()
-        .zip(outputs.into_iter())
-        .map(|(x,(y,z))| (x.to_string(),y,z))
-        .collect();
-
-    let tx_name = tx_name.to_string();
-
-    let body = serde_json::to_string_pretty(&ResponseStruct{tx_name, inputs, outputs}).unwrap();
+    let body = serde_json::to_string_pretty(&ResponseStruct{
+        tx_name: named_tx.tx_name,
+        workflow_id: named_tx.workflow_id,
+        inputs: named_tx.inputs,
+        outputs: named_tx.outputs,
+    }).unwrap();
     info!("/get-named-inputs-outputs/{tx_type}/{txid}: ok");
     HttpResponse::Ok()
         .content_type(ContentType::json())
@@ -383,23 +192,63 @@ async fn get_tx_inputs_outputs(path: web::Path<String>) -> impl Responder {
 }
 */
 
-#[post("/get-user-workflow/{user_address}")]
-async fn get_user_workflow(path: web::Path<String>) -> impl Responder {
-    #[derive(Serialize)]
-    struct UserDataLite {
-        pub status: u8,
-        pub fake_index: Option<u32>,
-        pub faucet_1: Option<Txid>,
-        pub faucet_2: Option<Txid>,
-        pub pegin: Option<Txid>,
-        pub kickoff_1: Option<Txid>,
-        pub kickoff_2: Option<Txid>,
-        pub challenge: Option<Txid>,
-        pub assert: Option<Txid>,
-        pub disprove: Option<Txid>,
-        pub take_1: Option<Txid>,
-        pub take_2: Option<Txid>,
+// public view of a workflow, without the faucet vouts or stored proof path
+#[derive(Serialize)]
+struct UserDataLite {
+    status: u8,
+    fake_index: Option<u32>,
+    faucet_1: Option<Txid>,
+    faucet_2: Option<Txid>,
+    pegin_deposit: Option<Txid>,
+    pegin_refund: Option<Txid>,
+    pegin: Option<Txid>,
+    peg_out: Option<Txid>,
+    withdrawer_evm_address: Option<String>,
+    withdrawer_address: Option<String>,
+    kickoff_1: Option<Txid>,
+    start_time: Option<Txid>,
+    start_time_timeout: Option<Txid>,
+    kickoff_timeout: Option<Txid>,
+    kickoff_2: Option<Txid>,
+    challenge: Option<Txid>,
+    assert: Option<Txid>,
+    disprove: Option<Txid>,
+    take_1: Option<Txid>,
+    take_2: Option<Txid>,
+    vk_id: Option<String>,
+}
+
+impl From<UserData> for UserDataLite {
+    fn from(user_data: UserData) -> Self {
+        UserDataLite {
+            status: user_data.status,
+            fake_index: user_data.fake_index,
+            faucet_1: user_data.faucet_1.map(|(txid, _)| txid),
+            faucet_2: user_data.faucet_2.map(|(txid, _)| txid),
+            pegin_deposit: user_data.pegin_deposit,
+            pegin_refund: user_data.pegin_refund,
+            pegin: user_data.pegin,
+            peg_out: user_data.peg_out,
+            withdrawer_evm_address: user_data.withdrawer_evm_address,
+            withdrawer_address: user_data.withdrawer_address,
+            kickoff_1: user_data.kickoff_1,
+            start_time: user_data.start_time,
+            start_time_timeout: user_data.start_time_timeout,
+            kickoff_timeout: user_data.kickoff_timeout,
+            kickoff_2: user_data.kickoff_2,
+            challenge: user_data.challenge,
+            assert: user_data.assert,
+            disprove: user_data.disprove,
+            take_1: user_data.take_1,
+            take_2: user_data.take_2,
+            vk_id: user_data.vk_id,
+        }
     }
+}
+
+// legacy: resolves to the latest workflow of the address, see /users/{address}/workflows
+#[post("/get-user-workflow/{user_address}")]
+async fn get_user_workflow(req: HttpRequest, path: web::Path<String>) -> impl Responder {
     #[derive(Serialize)]
     struct ResponseStruct {
         workflow_id: i32,
@@ -422,20 +271,18 @@ async fn get_user_workflow(path: web::Path<String>) -> impl Responder {
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
+    // creates the workflow on first call
+    if let Err(resp) = check_auth(&format!("/get-user-workflow/{user_addr}"), &db, &req, &user_addr) {
+        return resp
+    };
     let workflow_id = match sql::get_user_id(&db, &user_addr) {
         Ok(id_option) => match id_option {
             Some(id) => id,
-            _ => {
-                if let Err(e) = sql::new_user(&db, &user_addr) {
+            _ => match sql::new_user(&db, &user_addr) {
+                Ok(id) => id,
+                Err(e) => {
                     error!("/get-user-workflow/{user_addr}: fail to new user workflow: {}",e);
                     return HttpResponse::InternalServerError().body(e.to_string())
-                };
-                match sql::get_user_id(&db, &user_addr) {
-                    Ok(id) => id.unwrap(),
-                    Err(e) => { 
-                        error!("/get-user-workflow/{user_addr}: fail to get user id after new_user: {}",e);
-                        return HttpResponse::InternalServerError().body(e.to_string())
-                    }
                 }
             }
         },
@@ -458,26 +305,7 @@ async fn get_user_workflow(path: web::Path<String>) -> impl Responder {
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
-    let workflow = UserDataLite {
-        status: workflow.status,
-        fake_index: workflow.fake_index,
-        faucet_1: match workflow.faucet_1 {
-            Some((txid, _)) => Some(txid),
-            _ => None,
-        },
-        faucet_2: match workflow.faucet_2 {
-            Some((txid, _)) => Some(txid),
-            _ => None,
-        },
-        pegin: workflow.pegin,
-        kickoff_1: workflow.kickoff_1,
-        kickoff_2: workflow.kickoff_2,
-        challenge: workflow.challenge,
-        assert: workflow.assert,
-        disprove: workflow.disprove,
-        take_1: workflow.take_1,
-        take_2: workflow.take_2,
-    };
+    let workflow = UserDataLite::from(workflow);
 
     let body = serde_json::to_string_pretty(&ResponseStruct{workflow_id,workflow}).unwrap();
     info!("/get-user-workflow/{user_addr}: ok");
@@ -488,21 +316,6 @@ async fn get_user_workflow(path: web::Path<String>) -> impl Responder {
 
 #[get("/get-workflow-info/{workflow_id}")]
 async fn get_workflow_info(path: web::Path<i32>) -> impl Responder {
-    #[derive(Serialize)]
-    struct ResponseStruct {
-        pub status: u8,
-        pub fake_index: Option<u32>,
-        pub faucet_1: Option<Txid>,
-        pub faucet_2: Option<Txid>,
-        pub pegin: Option<Txid>,
-        pub kickoff_1: Option<Txid>,
-        pub kickoff_2: Option<Txid>,
-        pub challenge: Option<Txid>,
-        pub assert: Option<Txid>,
-        pub disprove: Option<Txid>,
-        pub take_1: Option<Txid>,
-        pub take_2: Option<Txid>,
-    }
 
     let workflow_id = path.into_inner();
     info!("new REQUEST: /get-workflow-info/{workflow_id}");
@@ -529,44 +342,335 @@ async fn get_workflow_info(path: web::Path<i32>) -> impl Responder {
         }
     };
 
-    let workflow = ResponseStruct {
-        status: user_data.status,
-        fake_index: user_data.fake_index,
-        faucet_1: match user_data.faucet_1 {
-            Some((txid, _)) => Some(txid),
-            _ => None,
+    let workflow = UserDataLite::from(user_data);
+
+    let body = serde_json::to_string_pretty(&workflow).unwrap();
+    info!("/get-workflow-info/{workflow_id}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[derive(Deserialize)]
+struct GraphQuery {
+    format: Option<String>, // json (default), dot or mermaid
+}
+
+#[get("/workflow/{workflow_id}/graph")]
+async fn get_workflow_graph(path: web::Path<i32>, query: web::Query<GraphQuery>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    let workflow_id = path.into_inner();
+    let format = query.into_inner().format.unwrap_or_else(|| "json".to_string());
+    info!("new REQUEST: /workflow/{workflow_id}/graph?format={format}");
+    if !["json", "dot", "mermaid"].contains(&format.as_str()) {
+        error!("/workflow/{workflow_id}/graph: unknown format {format}");
+        return HttpResponse::BadRequest().body(format!("unknown format {format}, expected json, dot or mermaid"))
+    };
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/workflow/{workflow_id}/graph: fail to connect db: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/workflow/{workflow_id}/graph: workflow {workflow_id} does not exisit");
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => {
+            error!("/workflow/{workflow_id}/graph: fail to get user data: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let rpc = rpc_pool.get();
+    let graph = match workers::run_blocking(move || graph::build_graph(&rpc, &db, workflow_id, &user_data)).await {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/workflow/{workflow_id}/graph: fail to build graph: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    info!("/workflow/{workflow_id}/graph: ok");
+    match format.as_str() {
+        "dot" => HttpResponse::Ok()
+            .content_type("text/vnd.graphviz")
+            .body(graph::to_dot(&graph)),
+        "mermaid" => HttpResponse::Ok()
+            .content_type(ContentType::plaintext())
+            .body(graph::to_mermaid(&graph)),
+        _ => HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .body(serde_json::to_string_pretty(&graph).unwrap()),
+    }
+}
+
+// 503 with an ETA while setup & the transactions statics are still loading in the background
+fn check_statics_ready(route: &str) -> Result<(), HttpResponse> {
+    if health::is_statics_loaded() {
+        return Ok(())
+    };
+    let progress = health::get_load_progress();
+    warn!("{route}: statics not loaded yet, stage: {}", progress.stage);
+    let mut resp = HttpResponse::ServiceUnavailable();
+    if let Some(eta) = progress.eta_secs {
+        resp.insert_header((header::RETRY_AFTER, eta.to_string()));
+    };
+    Err(resp.content_type(ContentType::json()).body(serde_json::to_string_pretty(&progress).unwrap()))
+}
+
+// the forwarded headers are client-supplied, only trusted when the peer is config::TRUSTED_PROXY
+fn client_ip(req: &HttpRequest) -> String {
+    let peer_ip = match req.peer_addr() {
+        Some(addr) => addr.ip().to_string(),
+        _ => return "unknown".to_string(),
+    };
+    if config::TRUSTED_PROXY.is_empty() || peer_ip != config::TRUSTED_PROXY {
+        return peer_ip
+    };
+    match req.connection_info().realip_remote_addr() {
+        // may carry the client's port
+        Some(v) => v.parse::<std::net::SocketAddr>().map(|a| a.ip().to_string()).unwrap_or(v.to_string()),
+        _ => peer_ip,
+    }
+}
+
+// admin endpoints are disabled while config::ADMIN_TOKEN is empty
+fn is_admin(req: &HttpRequest) -> bool {
+    if config::ADMIN_TOKEN.is_empty() {
+        return false
+    };
+    match req.headers().get("X-Admin-Token") {
+        Some(v) => auth::constant_time_eq(v.as_bytes(), config::ADMIN_TOKEN.as_bytes()),
+        _ => false,
+    }
+}
+
+// session token sent as `Authorization: Bearer <token>`
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers().get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+// the caller must hold a session of `user_addr`
+fn check_auth(route: &str, db: &Connection, req: &HttpRequest, user_addr: &Address) -> Result<(), HttpResponse> {
+    if !config::AUTH_REQUIRED {
+        return Ok(())
+    };
+    let token = match bearer_token(req) {
+        Some(v) => v,
+        _ => {
+            error!("{route}: missing session token");
+            return Err(HttpResponse::Unauthorized().body("missing session token, see /auth/challenge"))
+        }
+    };
+    match auth::get_session_address(db, token) {
+        Ok(Some(addr)) if addr == user_addr.to_string() => Ok(()),
+        Ok(Some(_)) => {
+            error!("{route}: session does not belong to {user_addr}");
+            Err(HttpResponse::Forbidden().body(format!("session does not belong to {user_addr}")))
+        },
+        Ok(None) => {
+            error!("{route}: invalid or expired session token");
+            Err(HttpResponse::Unauthorized().body("invalid or expired session token"))
+        },
+        Err(e) => {
+            error!("{route}: fail to get session: {}", e);
+            Err(HttpResponse::InternalServerError().body(e.to_string()))
+        }
+    }
+}
+
+// the caller must hold a session of any address or the admin token, for endpoints not bound to a workflow
+fn check_session(route: &str, db: &Connection, req: &HttpRequest) -> Result<(), HttpResponse> {
+    if !config::AUTH_REQUIRED {
+        return Ok(())
+    };
+    require_session(route, db, req)
+}
+
+// same as check_session, even while config::AUTH_REQUIRED is off
+fn require_session(route: &str, db: &Connection, req: &HttpRequest) -> Result<(), HttpResponse> {
+    if is_admin(req) {
+        return Ok(())
+    };
+    let token = match bearer_token(req) {
+        Some(v) => v,
+        _ => {
+            error!("{route}: missing session token");
+            return Err(HttpResponse::Unauthorized().body("missing session token, see /auth/challenge"))
+        }
+    };
+    match auth::get_session_address(db, token) {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => {
+            error!("{route}: invalid or expired session token");
+            Err(HttpResponse::Unauthorized().body("invalid or expired session token"))
         },
-        faucet_2: match user_data.faucet_2 {
-            Some((txid, _)) => Some(txid),
-            _ => None,
+        Err(e) => {
+            error!("{route}: fail to get session: {}", e);
+            Err(HttpResponse::InternalServerError().body(e.to_string()))
+        }
+    }
+}
+
+// the caller must hold a session of the address owning `workflow_id`
+fn check_workflow_auth(route: &str, db: &Connection, req: &HttpRequest, workflow_id: i32) -> Result<(), HttpResponse> {
+    if !config::AUTH_REQUIRED {
+        return Ok(())
+    };
+    match sql::get_user_address(db, workflow_id) {
+        Ok(Some(user_addr)) => check_auth(route, db, req, &user_addr),
+        Ok(None) => {
+            error!("{route}: workflow {workflow_id} does not exisit");
+            Err(HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit")))
         },
-        pegin: user_data.pegin,
-        kickoff_1: user_data.kickoff_1,
-        kickoff_2: user_data.kickoff_2,
-        challenge: user_data.challenge,
-        assert: user_data.assert,
-        disprove: user_data.disprove,
-        take_1: user_data.take_1,
-        take_2: user_data.take_2,
+        Err(e) => {
+            error!("{route}: fail to get user address: {}", e);
+            Err(HttpResponse::InternalServerError().body(e.to_string()))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AuthChallengeQuery {
+    address: String,
+}
+
+#[get("/auth/challenge")]
+async fn auth_challenge(query: web::Query<AuthChallengeQuery>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        nonce: String,
+        message: String,
+        expires_at: u64,
+    }
+
+    let user_addr = query.into_inner().address;
+    info!("new REQUEST: /auth/challenge {user_addr}");
+    let user_addr = match utils::address_from_str(&user_addr) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/auth/challenge {user_addr}: fail to deocde address: {}",e);
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+    };
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/auth/challenge {user_addr}: fail to connect db: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let (nonce, message, expires_at) = match auth::new_challenge(&db, &user_addr) {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/auth/challenge {user_addr}: fail to issue challenge: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
     };
 
-    let body = serde_json::to_string_pretty(&workflow).unwrap();
-    info!("/get-workflow-info/{workflow_id}: ok");
+    let body = serde_json::to_string_pretty(&ResponseStruct{nonce, message, expires_at}).unwrap();
+    info!("/auth/challenge {user_addr}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[derive(Deserialize)]
+struct AuthSessionRequest {
+    address: String,
+    nonce: String,
+    signature: String, // base64, BIP-322 simple or legacy signmessage for p2pkh
+}
+
+#[post("/auth/session")]
+async fn auth_session(req: web::Json<AuthSessionRequest>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        token: String,
+        expires_at: u64,
+    }
+
+    let req = req.into_inner();
+    let user_addr = req.address;
+    info!("new REQUEST: /auth/session {user_addr}");
+    let user_addr = match utils::address_from_str(&user_addr) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/auth/session {user_addr}: fail to deocde address: {}",e);
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+    };
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/auth/session {user_addr}: fail to connect db: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let (token, expires_at) = match auth::new_session(&db, &user_addr, &req.nonce, &req.signature) {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/auth/session {user_addr}: {}",e);
+            return HttpResponse::Unauthorized().body(e)
+        }
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{token, expires_at}).unwrap();
+    info!("/auth/session {user_addr}: ok");
     HttpResponse::Ok()
         .content_type(ContentType::json())
         .body(body)
 }
 
+// checks proof-of-work & reserves the quota of a faucet request, returns the reservation to release if the payout fails
+fn check_faucet_limits(route: &str, db: &Connection, req: &HttpRequest, user_addr: &Address) -> Result<i64, HttpResponse> {
+    let ip = client_ip(req);
+    let whitelisted = match sql::is_faucet_whitelisted(db, user_addr) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("{route}: fail to get faucet whitelist: {}", e);
+            return Err(HttpResponse::InternalServerError().body(e.to_string()))
+        }
+    };
+    if !whitelisted {
+        let nonce = req.headers().get("X-Faucet-Pow").and_then(|v| v.to_str().ok());
+        if let Err(e) = faucet::check_pow(user_addr, nonce) {
+            error!("{route}: {ip}: {}", e);
+            metrics::inc_counter(metrics::FAUCET_REJECTED_TOTAL, &[("reason", "pow")], 1.0);
+            return Err(HttpResponse::Forbidden().body(e))
+        };
+    };
+    match faucet::reserve(db, user_addr, &ip, faucet::now()) {
+        Ok(faucet::Reservation::Reserved(id)) => Ok(id),
+        Ok(faucet::Reservation::Refused(reason, retry_after)) => {
+            warn!("{route}: {ip}: {reason}, retry after {retry_after}s");
+            metrics::inc_counter(metrics::FAUCET_REJECTED_TOTAL, &[("reason", "quota")], 1.0);
+            Err(HttpResponse::TooManyRequests()
+                .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                .body(format!("{reason}, retry after {retry_after}s")))
+        },
+        Err(e) => {
+            error!("{route}: fail to reserve faucet quota: {}", e);
+            Err(HttpResponse::InternalServerError().body(e.to_string()))
+        }
+    }
+}
+
+// legacy: funds the latest workflow of the address, starting a new one once it left EMPTY.
+// new clients create workflows explicitly and use /workflow/{workflow_id}/request-btc
 #[post("/request-btc/{user_address}")]
-async fn request_btc(path: web::Path<String>) -> impl Responder {
+async fn request_btc(req: HttpRequest, path: web::Path<String>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
     fn create_new_user(db: &Connection,user_addr: &Address) -> Result<(i32, UserData), String> {
-        if let Err(e) = sql::new_user(&db, &user_addr) {
-            return Err(e.to_string())
-        };
-        let workflow_id = match sql::get_user_id(&db, &user_addr) {
-            Ok(id) => id.unwrap(),
-            Err(e) => return Err(e.to_string()),
-        };
+        let workflow_id = sql::new_user(&db, &user_addr)?;
         let user_data = match sql::get_user_data(&db, workflow_id) {
             Ok(user_data_option) => { match user_data_option {
                     Some(data) => data,
@@ -602,6 +706,15 @@ async fn request_btc(path: web::Path<String>) -> impl Responder {
         }
     };
 
+    if let Err(resp) = check_auth(&format!("/request-btc/{user_addr}"), &db, &req, &user_addr) {
+        return resp
+    };
+
+    let reservation = match check_faucet_limits(&format!("/request-btc/{user_addr}"), &db, &req, &user_addr) {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
     let (workflow_id, mut user_data) = match sql::get_user_id(&db, &user_addr) {
         Ok(id_option) => {
             match id_option {
@@ -649,20 +762,19 @@ async fn request_btc(path: web::Path<String>) -> impl Responder {
         }
     };
 
-    let rpc = match utils::new_rpc_client().await {
+    let rpc = rpc_pool.get();
+    let task_addr = user_addr.clone();
+    let (faucet_outpoint_1, faucet_outpoint_2) = match workers::run_blocking(move || transactions::faucet(&rpc, &task_addr)).await {
         Ok(v) => v,
         Err(e) => { 
-            error!("/request-btc/{user_addr}: fail to connect bitcoind: {}", e);
-            return HttpResponse::InternalServerError().body(e.to_string())
-        }
-    };
-    let (faucet_outpoint_1, faucet_outpoint_2) = match transactions::faucet(&rpc, &user_addr) {
-        Ok(v) => v,
-        Err(e) => { 
-            error!("/request-btc/{user_addr}: fail to send faucet tx: {}", e);
+            error!("/request-btc/{user_addr}: fail to send faucet tx: {}", e);
+            if let Err(e) = faucet::release(&db, reservation) {
+                error!("/request-btc/{user_addr}: fail to release faucet quota: {}", e);
+            };
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
+    faucet::record_payout();
 
     user_data.status = sql::STATUS::FAUCET as u8;
     user_data.faucet_1 = Some((faucet_outpoint_1.txid, faucet_outpoint_1.vout));
@@ -681,273 +793,2502 @@ async fn request_btc(path: web::Path<String>) -> impl Responder {
         .body(body)
 }
 
-#[get("/get-unsigned-pegin-tx/{workflow_id}")]
-async fn get_unsigned_pegin_tx(path: web::Path<i32>) -> impl Responder {
+#[derive(Deserialize)]
+struct CreateWorkflowRequest {
+    address: String,
+}
+
+#[post("/workflows")]
+async fn create_workflow(http_req: HttpRequest, req: web::Json<CreateWorkflowRequest>) -> impl Responder {
     #[derive(Serialize)]
     struct ResponseStruct {
-        input: TxInput,
-        outputs: Vec<TxOutput>,
+        workflow_id: i32,
+        workflow: UserDataLite,
     }
 
-    let workflow_id = path.into_inner();
-    info!("new REQUEST: /get-unsigned-pegin-tx/{workflow_id}");
+    let user_addr = req.into_inner().address;
+    info!("new REQUEST: /workflows {user_addr}");
+    let user_addr = match utils::address_from_str(&user_addr) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/workflows {user_addr}: fail to deocde address: {}",e);
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+    };
     let db = match sql::open_db() {
         Ok(v) => v,
         Err(e) => { 
-            error!("/get-unsigned-pegin-tx/{workflow_id}: fail to connect db: {}",e);
+            error!("/workflows {user_addr}: fail to connect db: {}",e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
-
-    let user_data = match sql::get_user_data(&db, workflow_id) {
+    if let Err(resp) = check_auth(&format!("/workflows {user_addr}"), &db, &http_req, &user_addr) {
+        return resp
+    };
+    let workflow_id = match sql::new_user(&db, &user_addr) {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/workflows {user_addr}: fail to new user workflow: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let workflow = match sql::get_user_data(&db, workflow_id) {
         Ok(user_data_option) => { match user_data_option {
-                Some(data) => data,
-                _ => {
-                    error!("/get-unsigned-pegin-tx/{workflow_id}: workflow {workflow_id} does not exisit");
-                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                Some(data) => UserDataLite::from(data),
+                _ => { 
+                    error!("/workflows {user_addr}: workflow {workflow_id} does not exisit");
+                    return HttpResponse::InternalServerError().body(format!("workflow {workflow_id} does not exisit"))
                 }
             }
         },
         Err(e) => { 
-            error!("/get-unsigned-pegin-tx/{workflow_id}: fail to get user data: {}",e);
+            error!("/workflows {user_addr}: fail to get user data: {}",e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
 
-    if user_data.status != sql::STATUS::FAUCET as u8 {
-        error!("/get-unsigned-pegin-tx/{workflow_id}: workflow {workflow_id} not currently at faucet stage");
-        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at faucet stage"))
-    };
+    let body = serde_json::to_string_pretty(&ResponseStruct{workflow_id, workflow}).unwrap();
+    info!("/workflows {user_addr}: ok, workflow {workflow_id}");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
 
-    let (faucet_1_txid, faucet_1_vout) = match user_data.faucet_1 {
-        Some(v) => v,
-        _ => {
-            error!("/get-unsigned-pegin-tx/{workflow_id}: workflow {workflow_id} missing faucet_1_txid");
-            return HttpResponse::InternalServerError().body("workflow {workflow_id} missing faucet_1_txid".to_string())
+const WORKFLOW_PAGE_LIMIT: u32 = 20;
+const WORKFLOW_PAGE_LIMIT_MAX: u32 = 100;
+
+#[derive(Deserialize)]
+struct ListWorkflowsQuery {
+    status: Option<u8>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+#[get("/users/{user_address}/workflows")]
+async fn list_user_workflows(path: web::Path<String>, query: web::Query<ListWorkflowsQuery>) -> impl Responder {
+    #[derive(Serialize)]
+    struct WorkflowItem {
+        workflow_id: i32,
+        workflow: UserDataLite,
+    }
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        total: u32,
+        limit: u32,
+        offset: u32,
+        workflows: Vec<WorkflowItem>,
+    }
+
+    let user_addr = path.into_inner();
+    let query = query.into_inner();
+    let limit = query.limit.unwrap_or(WORKFLOW_PAGE_LIMIT).min(WORKFLOW_PAGE_LIMIT_MAX);
+    let offset = query.offset.unwrap_or(0);
+    info!("new REQUEST: /users/{user_addr}/workflows");
+    let user_addr = match utils::address_from_str(&user_addr) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/users/{user_addr}/workflows: fail to deocde address: {}",e);
+            return HttpResponse::BadRequest().body(e.to_string())
         }
     };
-
-    let rpc = match utils::new_rpc_client().await {
+    let db = match sql::open_db() {
         Ok(v) => v,
         Err(e) => { 
-            error!("/get-unsigned-pegin-tx/{workflow_id}: fail to connect bitcoind: {}",e);
+            error!("/users/{user_addr}/workflows: fail to connect db: {}",e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
-
-    let pegin_tx = match transactions::peg_in_prepare(&rpc, faucet_1_txid, faucet_1_vout) {
+    let total = match sql::count_user_workflows(&db, &user_addr, query.status) {
         Ok(v) => v,
         Err(e) => { 
-            error!("/get-unsigned-pegin-tx/{workflow_id}: fail to prepare pegin tx: {}",e);
+            error!("/users/{user_addr}/workflows: fail to count workflows: {}",e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
-
-    let txid = pegin_tx.input[0].previous_output.txid;
-    let vout = pegin_tx.input[0].previous_output.vout;
-    let (script_pubkey, value) = match utils::get_utxo_script_pubkey_value(&rpc, txid, vout) {
+    let workflows = match sql::list_user_workflows(&db, &user_addr, query.status, limit, offset) {
         Ok(v) => v,
         Err(e) => { 
-            error!("/get-unsigned-pegin-tx/{workflow_id}: fail to get_utxo_value: {}",e);
+            error!("/users/{user_addr}/workflows: fail to list workflows: {}",e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
-    let input = TxInput {
-        txid,
-        vout,
-        script_pubkey,
-        value,
-    };
-
-    let mut outputs = vec![];
-    for i in 0..pegin_tx.output.len() {
-        let testnet_address = Address::from_script(&pegin_tx.output[i].script_pubkey, bitcoin::Network::Testnet).unwrap();
-        let regtest_address = Address::from_script(&pegin_tx.output[i].script_pubkey, bitcoin::Network::Regtest).unwrap();
-        let value = pegin_tx.output[i].value;
-        let output_i = TxOutput {
-            testnet_address,
-            regtest_address,
-            value,
-        };
-        outputs.push(output_i)
-    };
+    let workflows = workflows.into_iter()
+        .map(|(workflow_id, data)| WorkflowItem { workflow_id, workflow: UserDataLite::from(data) })
+        .collect();
 
-    let body = serde_json::to_string_pretty(&ResponseStruct{input, outputs}).unwrap();
-    info!("/get-unsigned-pegin-tx/{workflow_id}: ok");
+    let body = serde_json::to_string_pretty(&ResponseStruct{total, limit, offset, workflows}).unwrap();
+    info!("/users/{user_addr}/workflows: ok");
     HttpResponse::Ok()
         .content_type(ContentType::json())
         .body(body)
 }
 
-#[post("/post-pegin-txid/{workflow_id}/{pegin_txid}")]
-async fn post_pegin_txid(path: web::Path<(i32, String)>) -> impl Responder {
+#[post("/workflow/{workflow_id}/request-btc")]
+async fn request_workflow_btc(req: HttpRequest, path: web::Path<i32>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
     #[derive(Serialize)]
     struct ResponseStruct {
-        success: bool,
+        workflow_id: i32,
+        faucet_txid: Txid,
     }
 
-    let (workflow_id, pegin_txid) = path.into_inner();
-    info!("new REQUEST: /post-pegin-txid/{workflow_id}/{pegin_txid}");
-    let pegin_txid = match utils::txid_from_str(&pegin_txid) {
-        Ok(v) => v,
-        Err(e) => { 
-            error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: fail to decode txid: {}", e);
-            return HttpResponse::BadRequest().body(e.to_string())
-        }
-    };
-
+    let workflow_id = path.into_inner();
+    info!("new REQUEST: /workflow/{workflow_id}/request-btc");
     let db = match sql::open_db() {
         Ok(v) => v,
         Err(e) => { 
-            error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: fail to connect db: {}", e);
+            error!("/workflow/{workflow_id}/request-btc: fail to connect db: {}", e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
 
+    if let Err(resp) = check_workflow_auth(&format!("/workflow/{workflow_id}/request-btc"), &db, &req, workflow_id) {
+        return resp
+    };
+
     match sql::is_workflow_locked(&db, workflow_id) {
         Ok(v) => {
             if v {
-                error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: workflow:{workflow_id} is processing a task, please wait");
+                error!("/workflow/{workflow_id}/request-btc: workflow:{workflow_id} is processing a task, please wait");
                 return HttpResponse::Locked().body("workflow:{workflow_id} is processing a task, please wait".to_string())
             };
         },
         Err(e) => {
-            error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: fail to get workflow lock: {}", e);
+            error!("/workflow/{workflow_id}/request-btc: fail to get workflow lock: {}", e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
 
-    if let Err(e) = sql::lock_workflow(&db, workflow_id) {
-        error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: fail to lock workflow: {}", e);
-        return HttpResponse::InternalServerError().body(e.to_string());
-    };
-
     let mut user_data = match sql::get_user_data(&db, workflow_id) {
         Ok(user_data_option) => { match user_data_option {
                 Some(data) => data,
                 _ => {
-                    error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: workflow {workflow_id} does not exisit");
+                    error!("/workflow/{workflow_id}/request-btc: workflow {workflow_id} does not exisit");
                     return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
                 }
             }
         },
         Err(e) => { 
-            error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: fail to get user data: {}", e);
+            error!("/workflow/{workflow_id}/request-btc: fail to get user data: {}", e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
 
-    if user_data.status != sql::STATUS::FAUCET as u8 {
-        error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: workflow {workflow_id} not currently at faucet stage");
-        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at faucet stage"))
+    if user_data.status != sql::STATUS::EMPTY as u8 {
+        error!("/workflow/{workflow_id}/request-btc: workflow {workflow_id} has already been funded");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} has already been funded"))
     };
 
-    user_data.status = sql::STATUS::PEGIN as u8;
-    user_data.pegin = Some(pegin_txid);
+    let user_addr = match sql::get_user_address(&db, workflow_id) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            error!("/workflow/{workflow_id}/request-btc: workflow {workflow_id} does not exisit");
+            return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+        },
+        Err(e) => { 
+            error!("/workflow/{workflow_id}/request-btc: fail to get user address: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let reservation = match check_faucet_limits(&format!("/workflow/{workflow_id}/request-btc"), &db, &req, &user_addr) {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    if let Err(e) = sql::lock_workflow(&db, workflow_id) {
+        error!("/workflow/{workflow_id}/request-btc: fail to lock workflow: {}", e);
+        if let Err(e) = faucet::release(&db, reservation) {
+            error!("/workflow/{workflow_id}/request-btc: fail to release faucet quota: {}", e);
+        };
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    let rpc = rpc_pool.get();
+    let task_addr = user_addr.clone();
+    let (faucet_outpoint_1, faucet_outpoint_2) = match workers::run_blocking(move || transactions::faucet(&rpc, &task_addr)).await {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/workflow/{workflow_id}/request-btc: fail to send faucet tx: {}", e);
+            if let Err(e) = faucet::release(&db, reservation) {
+                error!("/workflow/{workflow_id}/request-btc: fail to release faucet quota: {}", e);
+            };
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/workflow/{workflow_id}/request-btc: fail to unlock workflow: {}", e);
+            };
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    faucet::record_payout();
+
+    user_data.status = sql::STATUS::FAUCET as u8;
+    user_data.faucet_1 = Some((faucet_outpoint_1.txid, faucet_outpoint_1.vout));
+    user_data.faucet_2 = Some((faucet_outpoint_2.txid, faucet_outpoint_2.vout));
 
     if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
-        error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: fail to update user data: {}", e);
+        error!("/workflow/{workflow_id}/request-btc: fail to update user data: {}", e);
         return HttpResponse::InternalServerError().body(e.to_string())
     }
 
     if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
-        error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: fail to unlock workflow: {}", e);
+        error!("/workflow/{workflow_id}/request-btc: fail to unlock workflow: {}", e);
         return HttpResponse::InternalServerError().body(e.to_string())
     };
 
-    let body = serde_json::to_string_pretty(&ResponseStruct{success: true}).unwrap();
-    info!("/post-pegin-txid/{workflow_id}/{pegin_txid}: ok");
+    let faucet_txid = faucet_outpoint_1.txid;
+    let body = serde_json::to_string_pretty(&ResponseStruct{workflow_id, faucet_txid}).unwrap();
+    info!("/workflow/{workflow_id}/request-btc: ok");
     HttpResponse::Ok()
         .content_type(ContentType::json())
         .body(body)
 }
 
-#[post("/post-fake-index/{workflow_id}/{fake_index}")]
-async fn post_fake_index(path: web::Path<(i32, u32)>) -> impl Responder {
+#[derive(Deserialize)]
+struct ScenarioRequest {
+    scenario: String, // see scenario::SCENARIOS
+    fake_index: Option<u32>, // the assertion the operator fakes, required by disprove
+}
+
+#[post("/scenarios")]
+async fn start_scenario(http_req: HttpRequest, req: web::Json<ScenarioRequest>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        scenario_id: i32,
+        workflow_id: i32,
+        steps: Vec<&'static str>,
+    }
+
+    let req = req.into_inner();
+    let scenario_name = req.scenario;
+    info!("new REQUEST: /scenarios {scenario_name}");
+    if let Err(resp) = check_statics_ready(&format!("/scenarios {scenario_name}")) {
+        return resp
+    };
+    let steps = match scenario::steps(&scenario_name) {
+        Some(v) => v,
+        _ => {
+            error!("/scenarios {scenario_name}: unknown scenario");
+            return HttpResponse::BadRequest().body(format!("unknown scenario {scenario_name}, expected one of {}", scenario::SCENARIOS.join(", ")))
+        }
+    };
+    let fake_index = match (scenario_name.as_str(), req.fake_index) {
+        ("disprove", Some(i)) if (i as usize) < config::N_ASSERTIONS => Some(i),
+        ("disprove", _) => {
+            error!("/scenarios {scenario_name}: missing or out of range fake_index");
+            return HttpResponse::BadRequest().body(format!("disprove needs a fake_index below {}", config::N_ASSERTIONS))
+        },
+        _ => None,
+    };
+
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/scenarios {scenario_name}: fail to connect db: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    // the faucet funds a fresh address every run, the caller must be known whatever AUTH_REQUIRED
+    if let Err(resp) = require_session(&format!("/scenarios {scenario_name}"), &db, &http_req) {
+        return resp
+    };
+
+    let user = scenario::DemoUser::generate();
+    let ip = client_ip(&http_req);
+    // reserved for the whole run, a failed faucet step doesn't give it back
+    let reservation_id = match faucet::reserve(&db, &user.address, &ip, faucet::now()) {
+        Ok(faucet::Reservation::Reserved(id)) => id,
+        Ok(faucet::Reservation::Refused(reason, retry_after)) => {
+            warn!("/scenarios {scenario_name}: {ip}: {reason}, retry after {retry_after}s");
+            metrics::inc_counter(metrics::FAUCET_REJECTED_TOTAL, &[("reason", "quota")], 1.0);
+            return HttpResponse::TooManyRequests()
+                .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                .body(format!("{reason}, retry after {retry_after}s"))
+        },
+        Err(e) => {
+            error!("/scenarios {scenario_name}: fail to reserve faucet quota: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let now = faucet::now();
+    let mut data = sql::ScenarioData {
+        scenario: scenario_name.clone(),
+        fake_index,
+        workflow_id: None,
+        status: sql::SCENARIO_RUNNING.to_string(),
+        steps: vec![],
+        error: None,
+        created_at: now,
+        updated_at: now,
+    };
+    let (scenario_id, workflow_id) = match sql::new_scenario_workflow(&db, &user.address, &mut data, config::SCENARIO_MAX_RUNNING) {
+        Ok(Some(v)) => v,
+        res => {
+            if let Err(e) = faucet::release(&db, reservation_id) {
+                error!("/scenarios {scenario_name}: fail to release faucet quota: {}", e);
+            };
+            return match res {
+                Err(e) => {
+                    error!("/scenarios {scenario_name}: fail to create scenario: {}", e);
+                    HttpResponse::InternalServerError().body(e.to_string())
+                },
+                _ => {
+                    error!("/scenarios {scenario_name}: {} scenarios already running", config::SCENARIO_MAX_RUNNING);
+                    HttpResponse::TooManyRequests().body(format!("{} scenarios already running, please retry later", config::SCENARIO_MAX_RUNNING))
+                },
+            }
+        }
+    };
+
+    let rpc = rpc_pool.get();
+    workers::spawn(scenario::run(scenario_id, workflow_id, user, rpc));
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{scenario_id, workflow_id, steps}).unwrap();
+    info!("/scenarios {scenario_name}: ok, scenario {scenario_id} on workflow {workflow_id}");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[get("/scenarios/{scenario_id}")]
+async fn get_scenario(path: web::Path<i32>) -> impl Responder {
+    let scenario_id = path.into_inner();
+    info!("new REQUEST: /scenarios/{scenario_id}");
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/scenarios/{scenario_id}: fail to connect db: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let data = match sql::get_scenario(&db, scenario_id) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            error!("/scenarios/{scenario_id}: scenario {scenario_id} does not exist");
+            return HttpResponse::NotFound().body(format!("scenario {scenario_id} does not exist"))
+        },
+        Err(e) => {
+            error!("/scenarios/{scenario_id}: fail to get scenario: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let body = serde_json::to_string_pretty(&data).unwrap();
+    info!("/scenarios/{scenario_id}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[post("/admin/faucet/whitelist/{user_address}")]
+async fn add_faucet_whitelist(req: HttpRequest, path: web::Path<String>) -> impl Responder {
+    let user_addr = path.into_inner();
+    let resp = update_faucet_whitelist(&req, user_addr.clone(), true);
+    audit(&req, "whitelist-add", &user_addr, &resp, "");
+    resp
+}
+
+#[delete("/admin/faucet/whitelist/{user_address}")]
+async fn remove_faucet_whitelist(req: HttpRequest, path: web::Path<String>) -> impl Responder {
+    let user_addr = path.into_inner();
+    let resp = update_faucet_whitelist(&req, user_addr.clone(), false);
+    audit(&req, "whitelist-remove", &user_addr, &resp, "");
+    resp
+}
+
+fn update_faucet_whitelist(req: &HttpRequest, user_addr: String, add: bool) -> HttpResponse {
     #[derive(Serialize)]
     struct ResponseStruct {
         success: bool,
     }
 
-    let (workflow_id, fake_index) = path.into_inner();
-    info!("new REQUEST: /post-fake-index/{workflow_id}/{fake_index}");
+    info!("new REQUEST: /admin/faucet/whitelist/{user_addr}");
+    if !is_admin(req) {
+        error!("/admin/faucet/whitelist/{user_addr}: unauthorized");
+        return HttpResponse::Unauthorized().body("invalid admin token")
+    };
+    let user_addr = match utils::address_from_str(&user_addr) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/admin/faucet/whitelist/{user_addr}: fail to deocde address: {}",e);
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+    };
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/admin/faucet/whitelist/{user_addr}: fail to connect db: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let res = match add {
+        true => sql::add_faucet_whitelist(&db, &user_addr),
+        false => sql::remove_faucet_whitelist(&db, &user_addr),
+    };
+    if let Err(e) = res {
+        error!("/admin/faucet/whitelist/{user_addr}: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string())
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{success: true}).unwrap();
+    info!("/admin/faucet/whitelist/{user_addr}: ok, whitelisted: {add}");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+// record every admin attempt with the status it was answered with, unauthorized & failed ones too,
+// failures are only logged so they don't undo the action
+fn audit(req: &HttpRequest, action: &str, target: &str, resp: &HttpResponse, detail: &str) {
+    let res = sql::open_db()
+        .and_then(|db| sql::add_admin_audit(&db, faucet::now(), &client_ip(req), action, target, resp.status().as_u16(), detail));
+    if let Err(e) = res {
+        error!("admin audit {action} {target}: {}", e);
+    };
+}
+
+#[derive(Deserialize)]
+struct AdminWorkflowsQuery {
+    address: Option<String>,
+    status: Option<u8>,
+    locked: Option<bool>,
+    txid: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+#[get("/admin/workflows")]
+async fn admin_list_workflows(req: HttpRequest, query: web::Query<AdminWorkflowsQuery>) -> impl Responder {
+    #[derive(Serialize)]
+    struct WorkflowItem {
+        workflow_id: i32,
+        address: String,
+        locked: bool,
+        workflow: UserDataLite,
+    }
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        limit: u32,
+        offset: u32,
+        workflows: Vec<WorkflowItem>,
+    }
+
+    info!("new REQUEST: /admin/workflows");
+    if !is_admin(&req) {
+        error!("/admin/workflows: unauthorized");
+        let resp = HttpResponse::Unauthorized().body("invalid admin token");
+        audit(&req, "list-workflows", "", &resp, "");
+        return resp
+    };
+    let query = query.into_inner();
+    let limit = query.limit.unwrap_or(WORKFLOW_PAGE_LIMIT).min(WORKFLOW_PAGE_LIMIT_MAX);
+    let offset = query.offset.unwrap_or(0);
+    let user_addr = match query.address {
+        Some(addr) => match utils::address_from_str(&addr) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                error!("/admin/workflows: fail to deocde address: {}",e);
+                return HttpResponse::BadRequest().body(e.to_string())
+            }
+        },
+        _ => None,
+    };
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/admin/workflows: fail to connect db: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let workflows = match sql::search_workflows(&db, user_addr.as_ref(), query.status, query.locked, query.txid.as_deref(), limit, offset) {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/admin/workflows: fail to search workflows: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let workflows = workflows.into_iter()
+        .map(|row| WorkflowItem { workflow_id: row.id, address: row.addr, locked: row.locked, workflow: UserDataLite::from(row.data) })
+        .collect();
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{limit, offset, workflows}).unwrap();
+    info!("/admin/workflows: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[post("/admin/workflow/{workflow_id}/status/{status}")]
+async fn admin_set_status(req: HttpRequest, path: web::Path<(i32, u8)>) -> impl Responder {
+    let (workflow_id, status) = path.into_inner();
+    let mut detail = status.to_string();
+    let resp = set_workflow_status(&req, workflow_id, status, &mut detail);
+    audit(&req, "set-status", &workflow_id.to_string(), &resp, &detail);
+    resp
+}
+
+// `detail`: the status change, for the audit
+fn set_workflow_status(req: &HttpRequest, workflow_id: i32, status: u8, detail: &mut String) -> HttpResponse {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        success: bool,
+    }
+
+    info!("new REQUEST: /admin/workflow/{workflow_id}/status/{status}");
+    if !is_admin(req) {
+        error!("/admin/workflow/{workflow_id}/status/{status}: unauthorized");
+        return HttpResponse::Unauthorized().body("invalid admin token")
+    };
+    if status > sql::STATUS::PEGOUT as u8 {
+        error!("/admin/workflow/{workflow_id}/status/{status}: unknown status");
+        return HttpResponse::BadRequest().body(format!("unknown status {status}"))
+    };
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/admin/workflow/{workflow_id}/status/{status}: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let mut user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/admin/workflow/{workflow_id}/status/{status}: workflow {workflow_id} does not exisit");
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => { 
+            error!("/admin/workflow/{workflow_id}/status/{status}: fail to get user data: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let old_status = user_data.status;
+    user_data.status = status;
+    if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
+        error!("/admin/workflow/{workflow_id}/status/{status}: fail to update user data: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string())
+    }
+    *detail = format!("{old_status} -> {status}");
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{success: true}).unwrap();
+    info!("/admin/workflow/{workflow_id}/status/{status}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[post("/admin/workflow/{workflow_id}/unlock")]
+async fn admin_unlock_workflow(req: HttpRequest, path: web::Path<i32>) -> impl Responder {
+    let workflow_id = path.into_inner();
+    let resp = unlock_workflow(&req, workflow_id);
+    audit(&req, "unlock", &workflow_id.to_string(), &resp, "");
+    resp
+}
+
+fn unlock_workflow(req: &HttpRequest, workflow_id: i32) -> HttpResponse {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        success: bool,
+    }
+
+    info!("new REQUEST: /admin/workflow/{workflow_id}/unlock");
+    if !is_admin(req) {
+        error!("/admin/workflow/{workflow_id}/unlock: unauthorized");
+        return HttpResponse::Unauthorized().body("invalid admin token")
+    };
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/admin/workflow/{workflow_id}/unlock: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+        error!("/admin/workflow/{workflow_id}/unlock: fail to unlock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string())
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{success: true}).unwrap();
+    info!("/admin/workflow/{workflow_id}/unlock: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+// 200 once broadcast, 202 while the tx waits in the scheduler
+fn broadcast_response<T: Serialize>(res: transactions::Broadcast, body: &T) -> HttpResponse {
+    let body = serde_json::to_string_pretty(body).unwrap();
+    let mut resp = match res {
+        transactions::Broadcast::Sent(..) => HttpResponse::Ok(),
+        transactions::Broadcast::Scheduled(..) => HttpResponse::Accepted(),
+    };
+    resp.content_type(ContentType::json()).body(body)
+}
+
+// rebuild & broadcast a presigned tx of the workflow, e.g. after a failed broadcast
+#[post("/admin/workflow/{workflow_id}/rerun/{tx_kind}")]
+async fn admin_rerun_step(req: HttpRequest, path: web::Path<(i32, String)>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    let (workflow_id, tx_kind) = path.into_inner();
+    let mut detail = tx_kind.clone();
+    let resp = rerun_workflow_tx(&req, workflow_id, &tx_kind, rpc_pool, &mut detail).await;
+    audit(&req, "rerun", &workflow_id.to_string(), &resp, &detail);
+    resp
+}
+
+// `detail`: the tx kind & its txid once broadcast, for the audit
+async fn rerun_workflow_tx(req: &HttpRequest, workflow_id: i32, tx_kind: &str, rpc_pool: web::Data<RpcPool>, detail: &mut String) -> HttpResponse {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        txid: Txid,
+        scheduled_height: Option<u64>,
+        fee_info: transactions::FeeInfo,
+    }
+
+    info!("new REQUEST: /admin/workflow/{workflow_id}/rerun/{tx_kind}");
+    if let Err(resp) = check_statics_ready(&format!("/admin/workflow/{workflow_id}/rerun/{tx_kind}")) {
+        return resp
+    };
+    if !is_admin(req) {
+        error!("/admin/workflow/{workflow_id}/rerun/{tx_kind}: unauthorized");
+        return HttpResponse::Unauthorized().body("invalid admin token")
+    };
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/admin/workflow/{workflow_id}/rerun/{tx_kind}: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    match sql::is_workflow_locked(&db, workflow_id) {
+        Ok(v) => {
+            if v {
+                error!("/admin/workflow/{workflow_id}/rerun/{tx_kind}: workflow:{workflow_id} is processing a task, please wait");
+                return HttpResponse::Locked().body("workflow:{workflow_id} is processing a task, please wait".to_string())
+            };
+        },
+        Err(e) => {
+            error!("/admin/workflow/{workflow_id}/rerun/{tx_kind}: fail to get workflow lock: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let mut user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/admin/workflow/{workflow_id}/rerun/{tx_kind}: workflow {workflow_id} does not exisit");
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => { 
+            error!("/admin/workflow/{workflow_id}/rerun/{tx_kind}: fail to get user data: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let user_addr = match sql::get_user_address(&db, workflow_id) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            error!("/admin/workflow/{workflow_id}/rerun/{tx_kind}: workflow {workflow_id} does not exisit");
+            return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+        },
+        Err(e) => { 
+            error!("/admin/workflow/{workflow_id}/rerun/{tx_kind}: fail to get user address: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(e) = sql::lock_workflow(&db, workflow_id) {
+        error!("/admin/workflow/{workflow_id}/rerun/{tx_kind}: fail to lock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    let rpc = rpc_pool.get();
+    let task_rpc = rpc.clone();
+    let task_data = user_data.clone();
+    let task_kind = tx_kind.to_string();
+    let tx = workers::run_blocking(move || prepare_workflow_tx(&task_rpc, user_addr, &task_data, &task_kind)).await;
+    let res = match tx {
+        Ok(tx) => {
+            let task_kind = tx_kind.to_string();
+            match workers::run(async move { transactions::broadcast_after_timelock(&rpc, &tx, &task_kind).await }).await {
+                Ok(v) => steps::record_broadcast(&mut user_data, tx_kind, v)
+                    .and_then(|_| sql::update_user_data(&db, workflow_id, &user_data))
+                    .map(|_| v),
+                Err(e) => Err(format!("fail to broadcast tx: {}", e)),
+            }
+        },
+        Err(e) => Err(format!("fail to prepare tx: {}", e)),
+    };
+    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+        error!("/admin/workflow/{workflow_id}/rerun/{tx_kind}: fail to unlock workflow: {}", e);
+    };
+    let res = match res {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/admin/workflow/{workflow_id}/rerun/{tx_kind}: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let txid = res.txid();
+    *detail = format!("{tx_kind} {txid}");
+
+    info!("/admin/workflow/{workflow_id}/rerun/{tx_kind}: ok");
+    broadcast_response(res, &ResponseStruct{txid, scheduled_height: res.scheduled_height(), fee_info: res.fee_info()})
+}
+
+#[post("/admin/workflow/{workflow_id}/archive")]
+async fn admin_archive_workflow(req: HttpRequest, path: web::Path<i32>) -> impl Responder {
+    let workflow_id = path.into_inner();
+    let resp = remove_workflow(&req, workflow_id, true);
+    audit(&req, "archive", &workflow_id.to_string(), &resp, "");
+    resp
+}
+
+#[delete("/admin/workflow/{workflow_id}")]
+async fn admin_delete_workflow(req: HttpRequest, path: web::Path<i32>) -> impl Responder {
+    let workflow_id = path.into_inner();
+    let resp = remove_workflow(&req, workflow_id, false);
+    audit(&req, "delete", &workflow_id.to_string(), &resp, "");
+    resp
+}
+
+fn remove_workflow(req: &HttpRequest, workflow_id: i32, archive: bool) -> HttpResponse {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        success: bool,
+    }
+
+    let action = if archive { "archive" } else { "delete" };
+    info!("new REQUEST: /admin/workflow/{workflow_id} {action}");
+    if !is_admin(req) {
+        error!("/admin/workflow/{workflow_id} {action}: unauthorized");
+        return HttpResponse::Unauthorized().body("invalid admin token")
+    };
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/admin/workflow/{workflow_id} {action}: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let res = match archive {
+        true => sql::archive_workflow(&db, workflow_id, faucet::now()),
+        false => sql::delete_workflow(&db, workflow_id),
+    };
+    match res {
+        Ok(true) => {},
+        Ok(false) => {
+            error!("/admin/workflow/{workflow_id} {action}: workflow {workflow_id} does not exisit");
+            return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+        },
+        Err(e) => {
+            error!("/admin/workflow/{workflow_id} {action}: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{success: true}).unwrap();
+    info!("/admin/workflow/{workflow_id} {action}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[derive(Deserialize)]
+struct DrainFaucetRequest {
+    address: String,
+    amount: Option<u64>, // in sats, default: the whole balance
+}
+
+#[post("/admin/faucet/drain")]
+async fn admin_drain_faucet(http_req: HttpRequest, req: web::Json<DrainFaucetRequest>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    let req = req.into_inner();
+    let target = req.address.clone();
+    let mut detail = req.amount.map_or("all".to_string(), |v| v.to_string());
+    let resp = drain_faucet(&http_req, req, rpc_pool, &mut detail).await;
+    audit(&http_req, "drain-faucet", &target, &resp, &detail);
+    resp
+}
+
+// `detail`: the amount & the drain txid once sent, for the audit
+async fn drain_faucet(http_req: &HttpRequest, req: DrainFaucetRequest, rpc_pool: web::Data<RpcPool>, detail: &mut String) -> HttpResponse {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        txid: Txid,
+    }
+
+    info!("new REQUEST: /admin/faucet/drain {}", req.address);
+    if !is_admin(http_req) {
+        error!("/admin/faucet/drain: unauthorized");
+        return HttpResponse::Unauthorized().body("invalid admin token")
+    };
+    let address = match utils::address_from_str(&req.address) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/admin/faucet/drain: fail to deocde address: {}",e);
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+    };
+    let rpc = rpc_pool.get();
+    let amount = req.amount.map(Amount::from_sat);
+    let txid = match workers::run_blocking(move || utils::drain_wallet(&rpc, &address, amount)).await {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/admin/faucet/drain: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    *detail = format!("{detail} {txid}");
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{txid}).unwrap();
+    info!("/admin/faucet/drain: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[derive(Deserialize)]
+struct AdminAuditQuery {
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+#[get("/admin/audit")]
+async fn admin_get_audit(req: HttpRequest, query: web::Query<AdminAuditQuery>) -> impl Responder {
+    #[derive(Serialize)]
+    struct AuditEntry {
+        time: u64,
+        ip: String,
+        action: String,
+        target: String,
+        outcome: u16,
+        detail: String,
+    }
+
+    info!("new REQUEST: /admin/audit");
+    if !is_admin(&req) {
+        error!("/admin/audit: unauthorized");
+        let resp = HttpResponse::Unauthorized().body("invalid admin token");
+        audit(&req, "get-audit", "", &resp, "");
+        return resp
+    };
+    let query = query.into_inner();
+    let limit = query.limit.unwrap_or(WORKFLOW_PAGE_LIMIT).min(WORKFLOW_PAGE_LIMIT_MAX);
+    let offset = query.offset.unwrap_or(0);
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/admin/audit: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let entries: Vec<AuditEntry> = match sql::get_admin_audit(&db, limit, offset) {
+        Ok(v) => v.into_iter().map(|(time, ip, action, target, outcome, detail)| AuditEntry { time, ip, action, target, outcome, detail }).collect(),
+        Err(e) => { 
+            error!("/admin/audit: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let body = serde_json::to_string_pretty(&entries).unwrap();
+    info!("/admin/audit: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[get("/get-unsigned-pegin-tx/{workflow_id}")]
+async fn get_unsigned_pegin_tx(path: web::Path<i32>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        input: TxInput,
+        outputs: Vec<TxOutput>,
+        fee_info: transactions::FeeInfo,
+    }
+
+    let workflow_id = path.into_inner();
+    info!("new REQUEST: /get-unsigned-pegin-tx/{workflow_id}");
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/get-unsigned-pegin-tx/{workflow_id}: fail to connect db: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/get-unsigned-pegin-tx/{workflow_id}: workflow {workflow_id} does not exisit");
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => { 
+            error!("/get-unsigned-pegin-tx/{workflow_id}: fail to get user data: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if user_data.status != sql::STATUS::FAUCET as u8 {
+        error!("/get-unsigned-pegin-tx/{workflow_id}: workflow {workflow_id} not currently at faucet stage");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at faucet stage"))
+    };
+
+    let (faucet_1_txid, faucet_1_vout) = match user_data.faucet_1 {
+        Some(v) => v,
+        _ => {
+            error!("/get-unsigned-pegin-tx/{workflow_id}: workflow {workflow_id} missing faucet_1_txid");
+            return HttpResponse::InternalServerError().body("workflow {workflow_id} missing faucet_1_txid".to_string())
+        }
+    };
+
+    let rpc = rpc_pool.get();
+    let res = workers::run_blocking(move || {
+        let pegin_tx = transactions::peg_in_prepare(&rpc, faucet_1_txid, faucet_1_vout)?;
+        let prevout = pegin_tx.input[0].previous_output;
+        let (script_pubkey, value) = utils::get_utxo_script_pubkey_value(&rpc, prevout.txid, prevout.vout)?;
+        let fee_info = transactions::get_fee_info(&rpc, &pegin_tx)?;
+        Ok((pegin_tx, prevout, script_pubkey, value, fee_info))
+    }).await;
+    let (pegin_tx, prevout, script_pubkey, value, fee_info) = match res {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/get-unsigned-pegin-tx/{workflow_id}: fail to prepare pegin tx: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let (txid, vout) = (prevout.txid, prevout.vout);
+    let input = TxInput {
+        txid,
+        vout,
+        script_pubkey,
+        value,
+    };
+
+    let mut outputs = vec![];
+    for i in 0..pegin_tx.output.len() {
+        let testnet_address = Address::from_script(&pegin_tx.output[i].script_pubkey, bitcoin::Network::Testnet).unwrap();
+        let regtest_address = Address::from_script(&pegin_tx.output[i].script_pubkey, bitcoin::Network::Regtest).unwrap();
+        let value = pegin_tx.output[i].value;
+        let output_i = TxOutput {
+            testnet_address,
+            regtest_address,
+            value,
+        };
+        outputs.push(output_i)
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{input, outputs, fee_info}).unwrap();
+    info!("/get-unsigned-pegin-tx/{workflow_id}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[post("/post-pegin-txid/{workflow_id}/{pegin_txid}")]
+async fn post_pegin_txid(req: HttpRequest, path: web::Path<(i32, String)>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        success: bool,
+    }
+
+    let (workflow_id, pegin_txid) = path.into_inner();
+    info!("new REQUEST: /post-pegin-txid/{workflow_id}/{pegin_txid}");
+    let pegin_txid = match utils::txid_from_str(&pegin_txid) {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: fail to decode txid: {}", e);
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+    };
+
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(resp) = check_workflow_auth(&format!("/post-pegin-txid/{workflow_id}/{pegin_txid}"), &db, &req, workflow_id) {
+        return resp
+    };
+
+    match sql::is_workflow_locked(&db, workflow_id) {
+        Ok(v) => {
+            if v {
+                error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: workflow:{workflow_id} is processing a task, please wait");
+                return HttpResponse::Locked().body("workflow:{workflow_id} is processing a task, please wait".to_string())
+            };
+        },
+        Err(e) => {
+            error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: fail to get workflow lock: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(e) = sql::lock_workflow(&db, workflow_id) {
+        error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: fail to lock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    let mut user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: workflow {workflow_id} does not exisit");
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => { 
+            error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: fail to get user data: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if user_data.status != sql::STATUS::FAUCET as u8 {
+        error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: workflow {workflow_id} not currently at faucet stage");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at faucet stage"))
+    };
+
+    user_data.status = sql::STATUS::PEGIN as u8;
+    user_data.pegin = Some(pegin_txid);
+
+    if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
+        error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: fail to update user data: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string())
+    }
+
+    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+        error!("/post-pegin-txid/{workflow_id}/{pegin_txid}: fail to unlock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string())
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{success: true}).unwrap();
+    info!("/post-pegin-txid/{workflow_id}/{pegin_txid}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[get("/get-unsigned-pegin-deposit-tx/{workflow_id}")]
+async fn get_unsigned_pegin_deposit_tx(path: web::Path<i32>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        input: TxInput,
+        outputs: Vec<TxOutput>,
+        fee_info: transactions::FeeInfo,
+    }
+
+    let workflow_id = path.into_inner();
+    info!("new REQUEST: /get-unsigned-pegin-deposit-tx/{workflow_id}");
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/get-unsigned-pegin-deposit-tx/{workflow_id}: fail to connect db: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/get-unsigned-pegin-deposit-tx/{workflow_id}: workflow {workflow_id} does not exisit");
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => { 
+            error!("/get-unsigned-pegin-deposit-tx/{workflow_id}: fail to get user data: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if user_data.status != sql::STATUS::FAUCET as u8 {
+        error!("/get-unsigned-pegin-deposit-tx/{workflow_id}: workflow {workflow_id} not currently at faucet stage");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at faucet stage"))
+    };
+
+    let (faucet_1_txid, faucet_1_vout) = match user_data.faucet_1 {
+        Some(v) => v,
+        _ => {
+            error!("/get-unsigned-pegin-deposit-tx/{workflow_id}: workflow {workflow_id} missing faucet_1_txid");
+            return HttpResponse::InternalServerError().body(format!("workflow {workflow_id} missing faucet_1_txid"))
+        }
+    };
+
+    let rpc = rpc_pool.get();
+    let res = workers::run_blocking(move || {
+        let deposit_tx = transactions::peg_in_deposit_prepare(&rpc, faucet_1_txid, faucet_1_vout)?;
+        let prevout = deposit_tx.input[0].previous_output;
+        let (script_pubkey, value) = utils::get_utxo_script_pubkey_value(&rpc, prevout.txid, prevout.vout)?;
+        let fee_info = transactions::get_fee_info(&rpc, &deposit_tx)?;
+        Ok((deposit_tx, prevout, script_pubkey, value, fee_info))
+    }).await;
+    let (deposit_tx, prevout, script_pubkey, value, fee_info) = match res {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/get-unsigned-pegin-deposit-tx/{workflow_id}: fail to prepare pegin deposit tx: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let (txid, vout) = (prevout.txid, prevout.vout);
+    let input = TxInput {
+        txid,
+        vout,
+        script_pubkey,
+        value,
+    };
+
+    let mut outputs = vec![];
+    for i in 0..deposit_tx.output.len() {
+        let testnet_address = Address::from_script(&deposit_tx.output[i].script_pubkey, bitcoin::Network::Testnet).unwrap();
+        let regtest_address = Address::from_script(&deposit_tx.output[i].script_pubkey, bitcoin::Network::Regtest).unwrap();
+        let value = deposit_tx.output[i].value;
+        let output_i = TxOutput {
+            testnet_address,
+            regtest_address,
+            value,
+        };
+        outputs.push(output_i)
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{input, outputs, fee_info}).unwrap();
+    info!("/get-unsigned-pegin-deposit-tx/{workflow_id}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[post("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}")]
+async fn post_pegin_deposit_txid(req: HttpRequest, path: web::Path<(i32, String)>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        success: bool,
+    }
+
+    let (workflow_id, deposit_txid) = path.into_inner();
+    info!("new REQUEST: /post-pegin-deposit-txid/{workflow_id}/{deposit_txid}");
+    let deposit_txid = match utils::txid_from_str(&deposit_txid) {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: fail to decode txid: {}", e);
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+    };
+
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(resp) = check_workflow_auth(&format!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}"), &db, &req, workflow_id) {
+        return resp
+    };
+
+    match sql::is_workflow_locked(&db, workflow_id) {
+        Ok(v) => {
+            if v {
+                error!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: workflow:{workflow_id} is processing a task, please wait");
+                return HttpResponse::Locked().body(format!("workflow:{workflow_id} is processing a task, please wait"))
+            };
+        },
+        Err(e) => {
+            error!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: fail to get workflow lock: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(e) = sql::lock_workflow(&db, workflow_id) {
+        error!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: fail to lock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    let mut user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: workflow {workflow_id} does not exisit");
+                    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                        error!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: fail to unlock workflow: {}", e);
+                    };
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => { 
+            error!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: fail to get user data: {}", e);
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: fail to unlock workflow: {}", e);
+            };
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if user_data.status != sql::STATUS::FAUCET as u8 {
+        error!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: workflow {workflow_id} not currently at faucet stage");
+        if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+            error!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: fail to unlock workflow: {}", e);
+        };
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at faucet stage"))
+    };
+
+    user_data.status = sql::STATUS::PEGIN_DEPOSIT as u8;
+    user_data.pegin_deposit = Some(deposit_txid);
+
+    if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
+        error!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: fail to update user data: {}", e);
+        if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+            error!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: fail to unlock workflow: {}", e);
+        };
+        return HttpResponse::InternalServerError().body(e.to_string())
+    }
+
+    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+        error!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: fail to unlock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string())
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{success: true}).unwrap();
+    info!("/post-pegin-deposit-txid/{workflow_id}/{deposit_txid}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+// kind: "confirm" (pre-signed by the committee) or "refund" (after connector_z's timelock)
+#[get("/get-pegin-{kind}-tx/{workflow_id}")]
+async fn get_pegin_confirm_refund_tx(path: web::Path<(String, i32)>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        txid: Txid,
+        tx_hex: String,
+        timelock_blocks: u16,
+        deposit_confirmations: u32,
+        fee_info: transactions::FeeInfo,
+    }
+
+    let (kind, workflow_id) = path.into_inner();
+    info!("new REQUEST: /get-pegin-{kind}-tx/{workflow_id}");
+    if kind != "confirm" && kind != "refund" {
+        error!("/get-pegin-{kind}-tx/{workflow_id}: unknown peg-in tx kind");
+        return HttpResponse::NotFound().body(format!("unknown peg-in tx kind: {kind}"))
+    };
+
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/get-pegin-{kind}-tx/{workflow_id}: fail to connect db: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/get-pegin-{kind}-tx/{workflow_id}: workflow {workflow_id} does not exisit");
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => { 
+            error!("/get-pegin-{kind}-tx/{workflow_id}: fail to get user data: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if user_data.status != sql::STATUS::PEGIN_DEPOSIT as u8 {
+        error!("/get-pegin-{kind}-tx/{workflow_id}: workflow {workflow_id} not currently at pegin deposit stage");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at pegin deposit stage"))
+    };
+
+    let deposit_txid = match user_data.pegin_deposit {
+        Some(txid) => txid,
+        _ => {
+            error!("/get-pegin-{kind}-tx/{workflow_id}: workflow {workflow_id} missing pegin_deposit_txid");
+            return HttpResponse::InternalServerError().body(format!("workflow {workflow_id} missing pegin_deposit_txid"))
+        }
+    };
+
+    let user_address = match sql::get_user_address(&db, workflow_id) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            error!("/get-pegin-{kind}-tx/{workflow_id}: workflow {workflow_id} does not exisit");
+            return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+        },
+        Err(e) => {
+            error!("/get-pegin-{kind}-tx/{workflow_id}: fail to get user address: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let rpc = rpc_pool.get();
+    let task_kind = kind.clone();
+    let res = workers::run_blocking(move || {
+        // the refund goes back to the workflow's user
+        let tx = match task_kind.as_str() {
+            "confirm" => transactions::peg_in_confirm_prepare(&rpc, deposit_txid)?,
+            _ => transactions::peg_in_refund_prepare(&rpc, deposit_txid, &user_address)?,
+        };
+        let deposit_confirmations = utils::get_utxo_confirmations(&rpc, deposit_txid, 0)?;
+        let fee_info = transactions::get_fee_info(&rpc, &tx)?;
+        Ok((tx, deposit_confirmations, fee_info))
+    }).await;
+    let (tx, deposit_confirmations, fee_info) = match res {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/get-pegin-{kind}-tx/{workflow_id}: fail to prepare pegin {kind} tx: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{
+        txid: tx.compute_txid(),
+        tx_hex: utils::tx_wrapper(&tx),
+        timelock_blocks: transactions::get_relative_timelock_blocks(&tx, 0),
+        deposit_confirmations,
+        fee_info,
+    }).unwrap();
+    info!("/get-pegin-{kind}-tx/{workflow_id}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+// kind: "confirm" -> PEGIN, "refund" -> PEGIN_REFUND
+#[post("/send-pegin-{kind}/{workflow_id}")]
+async fn send_pegin_confirm_refund(req: HttpRequest, path: web::Path<(String, i32)>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        txid: Txid,
+        scheduled_height: Option<u64>,
+        fee_info: transactions::FeeInfo,
+    }
+
+    let (kind, workflow_id) = path.into_inner();
+    info!("new REQUEST: /send-pegin-{kind}/{workflow_id}");
+    if kind != "confirm" && kind != "refund" {
+        error!("/send-pegin-{kind}/{workflow_id}: unknown peg-in tx kind");
+        return HttpResponse::NotFound().body(format!("unknown peg-in tx kind: {kind}"))
+    };
+
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/send-pegin-{kind}/{workflow_id}: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(resp) = check_workflow_auth(&format!("/send-pegin-{kind}/{workflow_id}"), &db, &req, workflow_id) {
+        return resp
+    };
+
+    match sql::is_workflow_locked(&db, workflow_id) {
+        Ok(v) => {
+            if v {
+                error!("/send-pegin-{kind}/{workflow_id}: workflow:{workflow_id} is processing a task, please wait");
+                return HttpResponse::Locked().body(format!("workflow:{workflow_id} is processing a task, please wait"))
+            };
+        },
+        Err(e) => {
+            error!("/send-pegin-{kind}/{workflow_id}: fail to get workflow lock: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(e) = sql::lock_workflow(&db, workflow_id) {
+        error!("/send-pegin-{kind}/{workflow_id}: fail to lock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    let mut user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/send-pegin-{kind}/{workflow_id}: workflow {workflow_id} does not exisit");
+                    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                        error!("/send-pegin-{kind}/{workflow_id}: fail to unlock workflow: {}", e);
+                    };
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => { 
+            error!("/send-pegin-{kind}/{workflow_id}: fail to get user data: {}", e);
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/send-pegin-{kind}/{workflow_id}: fail to unlock workflow: {}", e);
+            };
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if user_data.status != sql::STATUS::PEGIN_DEPOSIT as u8 {
+        error!("/send-pegin-{kind}/{workflow_id}: workflow {workflow_id} not currently at pegin deposit stage");
+        if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+            error!("/send-pegin-{kind}/{workflow_id}: fail to unlock workflow: {}", e);
+        };
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at pegin deposit stage"))
+    };
+
+    let deposit_txid = match user_data.pegin_deposit {
+        Some(txid) => txid,
+        _ => {
+            error!("/send-pegin-{kind}/{workflow_id}: workflow {workflow_id} missing pegin_deposit_txid");
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/send-pegin-{kind}/{workflow_id}: fail to unlock workflow: {}", e);
+            };
+            return HttpResponse::InternalServerError().body(format!("workflow {workflow_id} missing pegin_deposit_txid"))
+        }
+    };
+
+    let user_address = match sql::get_user_address(&db, workflow_id) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            error!("/send-pegin-{kind}/{workflow_id}: workflow {workflow_id} does not exisit");
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/send-pegin-{kind}/{workflow_id}: fail to unlock workflow: {}", e);
+            };
+            return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+        },
+        Err(e) => {
+            error!("/send-pegin-{kind}/{workflow_id}: fail to get user address: {}", e);
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/send-pegin-{kind}/{workflow_id}: fail to unlock workflow: {}", e);
+            };
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let rpc = rpc_pool.get();
+
+    let task_kind = kind.clone();
+    let res = workers::run(async move {
+        match task_kind.as_str() {
+            "confirm" => transactions::peg_in_confirm(&rpc, deposit_txid).await,
+            _ => transactions::peg_in_refund(&rpc, deposit_txid, &user_address).await,
+        }
+    }).await;
+    let res = match res {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/send-pegin-{kind}/{workflow_id}: fail to send pegin {kind} tx: {}", e);
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/send-pegin-{kind}/{workflow_id}: fail to unlock workflow: {}", e);
+            };
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let txid = res.txid();
+
+    if kind == "confirm" {
+        user_data.status = sql::STATUS::PEGIN as u8;
+        user_data.pegin = Some(txid);
+    } else if let Err(e) = steps::record_broadcast(&mut user_data, "pegin-refund", res) {
+        error!("/send-pegin-{kind}/{workflow_id}: {}", e);
+        if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+            error!("/send-pegin-{kind}/{workflow_id}: fail to unlock workflow: {}", e);
+        };
+        return HttpResponse::InternalServerError().body(e.to_string())
+    };
+
+    if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
+        error!("/send-pegin-{kind}/{workflow_id}: fail to update user data: {}", e);
+        if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+            error!("/send-pegin-{kind}/{workflow_id}: fail to unlock workflow: {}", e);
+        };
+        return HttpResponse::InternalServerError().body(e.to_string())
+    }
+
+    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+        error!("/send-pegin-{kind}/{workflow_id}: fail to unlock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    info!("/send-pegin-{kind}/{workflow_id}: ok");
+    broadcast_response(res, &ResponseStruct{txid, scheduled_height: res.scheduled_height(), fee_info: res.fee_info()})
+}
+
+fn required_txid(txid: Option<Txid>, name: &str) -> Result<Txid, String> {
+    match txid {
+        Some(v) => Ok(v),
+        _ => Err(format!("workflow missing {name}_txid")),
+    }
+}
+
+// rebuild (without broadcasting) the given tx of the workflow from its recorded txids
+// tx_kind: pegin-refund, kickoff2, start-time-timeout, kickoff-timeout, take1, take2, disprove
+fn prepare_workflow_tx(rpc: &Client, user_address: Address, user_data: &UserData, tx_kind: &str) -> Result<Transaction, String> {
+    match tx_kind {
+        "pegin-refund" => {
+            let deposit_txid = required_txid(user_data.pegin_deposit, "pegin_deposit")?;
+            transactions::peg_in_refund_prepare(rpc, deposit_txid, &user_address)
+        },
+        "kickoff2" => {
+            let kick_off_1_txid = required_txid(user_data.kickoff_1, "kickoff_1")?;
+            let key_set = transactions::get_workflow_key_set(kick_off_1_txid, user_data);
+            let bitcom_lock_scripts = transactions::get_cached_bitcom_lock_scripts(&key_set);
+            transactions::kick_off_2_prepare(rpc, kick_off_1_txid, &bitcom_lock_scripts)
+        },
+        "start-time-timeout" => {
+            let kick_off_1_txid = required_txid(user_data.kickoff_1, "kickoff_1")?;
+            transactions::start_time_timeout_prepare(rpc, kick_off_1_txid, None)
+        },
+        "kickoff-timeout" => {
+            let kick_off_1_txid = required_txid(user_data.kickoff_1, "kickoff_1")?;
+            transactions::kick_off_timeout_prepare(rpc, kick_off_1_txid, None)
+        },
+        "take1" => {
+            let peg_in_txid = required_txid(user_data.pegin, "pegin")?;
+            let kick_off_1_txid = required_txid(user_data.kickoff_1, "kickoff_1")?;
+            let kick_off_2_txid = required_txid(user_data.kickoff_2, "kickoff_2")?;
+            transactions::take_1_prepare(rpc, peg_in_txid, kick_off_1_txid, kick_off_2_txid, steps::get_take_receive_address(user_data, user_address))
+        },
+        "take2" => {
+            let peg_in_txid = required_txid(user_data.pegin, "pegin")?;
+            let kick_off_1_txid = required_txid(user_data.kickoff_1, "kickoff_1")?;
+            let assert_txid = required_txid(user_data.assert, "assert")?;
+            let key_set = transactions::get_workflow_key_set(kick_off_1_txid, user_data);
+            let connector_c = transactions::get_cached_connector_c(&key_set);
+            let connector_c_addr = Some(connector_c.address.clone());
+            transactions::take_2_prepare(rpc, peg_in_txid, assert_txid, &connector_c.tapscripts, connector_c_addr, &connector_c.spend_info, steps::get_take_receive_address(user_data, user_address))
+        },
+        "disprove" => {
+            let kick_off_1_txid = required_txid(user_data.kickoff_1, "kickoff_1")?;
+            let assert_txid = required_txid(user_data.assert, "assert")?;
+            let key_set = transactions::get_workflow_key_set(kick_off_1_txid, user_data);
+            let connector_c = transactions::get_cached_connector_c(&key_set);
+            let connector_c_addr = Some(connector_c.address.clone());
+            transactions::disprove_prepare(rpc, assert_txid, &connector_c.tapscripts, connector_c_addr, &connector_c.spend_info, &key_set, None)
+        },
+        _ => Err(format!("unknown tx kind: {tx_kind}")),
+    }
+}
+
+#[get("/get-spendable-height/{workflow_id}/{tx_kind}")]
+async fn get_spendable_height(path: web::Path<(i32, String)>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        current_height: u64,
+        spendable_height: u64,
+        blocks_remaining: u64,
+        scheduled_txid: Option<Txid>,
+        target_fee_rate: u64,
+    }
+
+    let (workflow_id, tx_kind) = path.into_inner();
+    info!("new REQUEST: /get-spendable-height/{workflow_id}/{tx_kind}");
+    if let Err(resp) = check_statics_ready(&format!("/get-spendable-height/{workflow_id}/{tx_kind}")) {
+        return resp
+    };
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/get-spendable-height/{workflow_id}/{tx_kind}: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/get-spendable-height/{workflow_id}/{tx_kind}: workflow {workflow_id} does not exisit");
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => { 
+            error!("/get-spendable-height/{workflow_id}/{tx_kind}: fail to get user data: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let rpc = rpc_pool.get();
+
+    match transactions::get_timelock_parent(&user_data, &tx_kind) {
+        Ok(Some(_)) => {},
+        Ok(None) => {
+            error!("/get-spendable-height/{workflow_id}/{tx_kind}: the tx {tx_kind} spends is not sent yet");
+            return HttpResponse::BadRequest().body(format!("the tx {tx_kind} spends is not sent yet"))
+        },
+        Err(e) => { 
+            error!("/get-spendable-height/{workflow_id}/{tx_kind}: {}", e);
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+    };
+    let user_addr = match sql::get_user_address(&db, workflow_id) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            error!("/get-spendable-height/{workflow_id}/{tx_kind}: workflow {workflow_id} does not exisit");
+            return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+        },
+        Err(e) => { 
+            error!("/get-spendable-height/{workflow_id}/{tx_kind}: fail to get user address: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let scheduled = user_data.scheduled.clone().filter(|v| v.kind == tx_kind);
+    let scheduled_txid = scheduled.as_ref().map(|v| v.txid);
+
+    // a scheduled tx already knows its height, else read the CSV off the tx itself so it matches the connectors
+    let task_kind = tx_kind.clone();
+    let res = workers::run_blocking(move || {
+        let current_height = utils::get_block_count(&rpc)?;
+        let spendable_height = match scheduled {
+            Some(v) => v.height,
+            None => {
+                let tx = prepare_workflow_tx(&rpc, user_addr, &user_data, &task_kind)?;
+                transactions::get_spendable_height(&rpc, &tx)?
+            },
+        };
+        Ok((current_height, spendable_height, utils::get_fee_rate(&rpc)))
+    }).await;
+    let (current_height, spendable_height, target_fee_rate) = match res {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/get-spendable-height/{workflow_id}/{tx_kind}: fail to get spendable height: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{
+        current_height,
+        spendable_height,
+        blocks_remaining: spendable_height.saturating_sub(current_height + 1),
+        scheduled_txid,
+        target_fee_rate,
+    }).unwrap();
+    info!("/get-spendable-height/{workflow_id}/{tx_kind}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+fn get_workflow_txid(user_data: &UserData, tx_kind: &str) -> Result<Option<Txid>, String> {
+    match tx_kind {
+        "pegin" => Ok(user_data.pegin),
+        "pegout" => Ok(user_data.peg_out),
+        "pegin-refund" => Ok(user_data.pegin_refund),
+        "kickoff2" => Ok(user_data.kickoff_2),
+        "start-time" => Ok(user_data.start_time),
+        "start-time-timeout" => Ok(user_data.start_time_timeout),
+        "kickoff-timeout" => Ok(user_data.kickoff_timeout),
+        "challenge" => Ok(user_data.challenge),
+        "take1" => Ok(user_data.take_1),
+        "assert" => Ok(user_data.assert),
+        "take2" => Ok(user_data.take_2),
+        "disprove" => Ok(user_data.disprove),
+        _ => Err(format!("unknown tx kind: {tx_kind}")),
+    }
+}
+
+#[post("/workflow/{workflow_id}/bump/{tx_kind}")]
+async fn bump_workflow_tx(req: HttpRequest, path: web::Path<(i32, String)>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        txid: Txid,
+        cpfp_txid: Txid,
+        target_fee_rate: u64,
+    }
+
+    let (workflow_id, tx_kind) = path.into_inner();
+    info!("new REQUEST: /workflow/{workflow_id}/bump/{tx_kind}");
+    if let Err(resp) = check_statics_ready(&format!("/workflow/{workflow_id}/bump/{tx_kind}")) {
+        return resp
+    };
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/workflow/{workflow_id}/bump/{tx_kind}: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(resp) = check_workflow_auth(&format!("/workflow/{workflow_id}/bump/{tx_kind}"), &db, &req, workflow_id) {
+        return resp
+    };
+
+    match sql::is_workflow_locked(&db, workflow_id) {
+        Ok(v) => {
+            if v {
+                error!("/workflow/{workflow_id}/bump/{tx_kind}: workflow:{workflow_id} is processing a task, please wait");
+                return HttpResponse::Locked().body("workflow:{workflow_id} is processing a task, please wait".to_string())
+            };
+        },
+        Err(e) => {
+            error!("/workflow/{workflow_id}/bump/{tx_kind}: fail to get workflow lock: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/workflow/{workflow_id}/bump/{tx_kind}: workflow {workflow_id} does not exisit");
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => { 
+            error!("/workflow/{workflow_id}/bump/{tx_kind}: fail to get user data: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let txid = match get_workflow_txid(&user_data, &tx_kind) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            error!("/workflow/{workflow_id}/bump/{tx_kind}: workflow {workflow_id} has no {tx_kind} tx");
+            return HttpResponse::BadRequest().body(format!("workflow {workflow_id} has no {tx_kind} tx"))
+        },
+        Err(e) => {
+            error!("/workflow/{workflow_id}/bump/{tx_kind}: {}", e);
+            return HttpResponse::NotFound().body(e)
+        }
+    };
+
+    let rpc = rpc_pool.get();
+    let task_rpc = rpc.clone();
+    let res = workers::run_blocking(move || {
+        let confirmations = utils::get_tx_confirmations(&task_rpc, txid)?;
+        let tx = utils::get_raw_tx(&task_rpc, txid)?;
+        Ok((confirmations, tx, utils::get_fee_rate(&task_rpc)))
+    }).await;
+    let (tx, target_fee_rate) = match res {
+        Ok((0, tx, target_fee_rate)) => (tx, target_fee_rate),
+        Ok(_) => {
+            error!("/workflow/{workflow_id}/bump/{tx_kind}: tx {txid} already confirmed");
+            return HttpResponse::BadRequest().body(format!("tx {txid} already confirmed"))
+        },
+        Err(e) => { 
+            error!("/workflow/{workflow_id}/bump/{tx_kind}: fail to get tx {txid}: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(e) = sql::lock_workflow(&db, workflow_id) {
+        error!("/workflow/{workflow_id}/bump/{tx_kind}: fail to lock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string())
+    };
+    let task_kind = tx_kind.clone();
+    let res = workers::run(async move { transactions::bump_fee(&rpc, &tx, &task_kind).await }).await;
+    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+        error!("/workflow/{workflow_id}/bump/{tx_kind}: fail to unlock workflow: {}", e);
+    };
+    let cpfp_txid = match res {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/workflow/{workflow_id}/bump/{tx_kind}: fail to bump fee: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{txid, cpfp_txid, target_fee_rate}).unwrap();
+    info!("/workflow/{workflow_id}/bump/{tx_kind}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[derive(Deserialize)]
+struct PegOutRequest {
+    evm_address: Option<String>, // burn reference on the EVM side, default WITHDRAWER_EVM_ADDRESS
+    btc_address: Option<String>, // payout address, must be the workflow's user address
+}
+
+#[post("/request-pegout/{workflow_id}")]
+async fn request_pegout(http_req: HttpRequest, path: web::Path<i32>, req: web::Json<PegOutRequest>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        peg_out_txid: Txid,
+        scheduled_height: Option<u64>,
+        fee_info: transactions::FeeInfo,
+    }
+
+    let workflow_id = path.into_inner();
+    info!("new REQUEST: /request-pegout/{workflow_id}");
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/request-pegout/{workflow_id}: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(resp) = check_workflow_auth(&format!("/request-pegout/{workflow_id}"), &db, &http_req, workflow_id) {
+        return resp
+    };
+
+    match sql::is_workflow_locked(&db, workflow_id) {
+        Ok(v) => {
+            if v {
+                error!("/request-pegout/{workflow_id}: workflow:{workflow_id} is processing a task, please wait");
+                return HttpResponse::Locked().body(format!("workflow:{workflow_id} is processing a task, please wait"))
+            };
+        },
+        Err(e) => {
+            error!("/request-pegout/{workflow_id}: fail to get workflow lock: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let mut user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/request-pegout/{workflow_id}: workflow {workflow_id} does not exisit");
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => { 
+            error!("/request-pegout/{workflow_id}: fail to get user data: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if user_data.status != sql::STATUS::PEGIN as u8 {
+        error!("/request-pegout/{workflow_id}: workflow {workflow_id} not currently at pegin stage");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at pegin stage"))
+    };
+
+    // one peg-out per peg-in, a scheduled one keeps the status at pegin
+    if user_data.peg_out.is_some() || user_data.scheduled.as_ref().is_some_and(|v| v.kind == "pegout") {
+        error!("/request-pegout/{workflow_id}: workflow {workflow_id} already requested a peg-out");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} already requested a peg-out"))
+    };
+
+    let peg_in_txid = match user_data.pegin {
+        Some(txid) => txid,
+        _ => {
+            error!("/request-pegout/{workflow_id}: workflow {workflow_id} missing pegin_txid");
+            return HttpResponse::InternalServerError().body(format!("workflow {workflow_id} missing pegin_txid"))
+        }
+    };
+    // the peg-in must be this workflow's own, not one re-recorded by a later workflow
+    match sql::get_workflow_id_by_txid(&db, "pegin", &peg_in_txid) {
+        Ok(Some(id)) if id == workflow_id => {},
+        Ok(_) => {
+            error!("/request-pegout/{workflow_id}: pegin {peg_in_txid} belongs to another workflow");
+            return HttpResponse::BadRequest().body(format!("pegin {peg_in_txid} belongs to another workflow"))
+        },
+        Err(e) => { 
+            error!("/request-pegout/{workflow_id}: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    // the operator fronts the peg-out, only ever to the depositor's own address
+    let withdrawer_address = match sql::get_user_address(&db, workflow_id) {
+        Ok(Some(addr)) => addr,
+        Ok(None) => {
+            error!("/request-pegout/{workflow_id}: workflow {workflow_id} does not exisit");
+            return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+        },
+        Err(e) => { 
+            error!("/request-pegout/{workflow_id}: fail to get user address: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    if let Some(addr) = &req.btc_address {
+        match utils::address_from_str(addr) {
+            Ok(v) if v == withdrawer_address => {},
+            Ok(_) => {
+                error!("/request-pegout/{workflow_id}: btc address {addr} is not the workflow's address");
+                return HttpResponse::BadRequest().body(format!("btc address {addr} is not the workflow's address"))
+            },
+            Err(e) => { 
+                error!("/request-pegout/{workflow_id}: invalid btc address: {}", e);
+                return HttpResponse::BadRequest().body(e.to_string())
+            }
+        };
+    };
+    let withdrawer_evm_address = req.evm_address.clone().unwrap_or(config::WITHDRAWER_EVM_ADDRESS.to_string());
+
+    if let Err(e) = sql::lock_workflow(&db, workflow_id) {
+        error!("/request-pegout/{workflow_id}: fail to lock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    let rpc = rpc_pool.get();
+    let task_address = withdrawer_address.clone();
+    let res = match workers::run(async move { transactions::peg_out(&rpc, peg_in_txid, &task_address).await }).await {
+        Ok(res) => {
+            user_data.withdrawer_evm_address = Some(withdrawer_evm_address);
+            user_data.withdrawer_address = Some(withdrawer_address.to_string());
+            match steps::record_broadcast(&mut user_data, "pegout", res).and_then(|_| sql::update_user_data(&db, workflow_id, &user_data)) {
+                Ok(_) => Ok(res),
+                Err(e) => Err(format!("fail to update user data: {}", e)),
+            }
+        },
+        Err(e) => Err(format!("fail to send peg-out tx: {}", e)),
+    };
+
+    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+        error!("/request-pegout/{workflow_id}: fail to unlock workflow: {}", e);
+    };
+
+    match res {
+        Ok(res) => {
+            info!("/request-pegout/{workflow_id}: ok");
+            broadcast_response(res, &ResponseStruct{peg_out_txid: res.txid(), scheduled_height: res.scheduled_height(), fee_info: res.fee_info()})
+        },
+        Err(e) => {
+            error!("/request-pegout/{workflow_id}: {}", e);
+            HttpResponse::InternalServerError().body(e)
+        }
+    }
+}
+
+// the workflow that reimburses a peg-out: its kickoff & take txs
+#[get("/get-pegout/{peg_out_txid}")]
+async fn get_pegout(path: web::Path<String>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        workflow_id: i32,
+        peg_out: Txid,
+        withdrawer_evm_address: Option<String>,
+        withdrawer_address: Option<String>,
+        pegin: Option<Txid>,
+        kickoff_1: Option<Txid>,
+        kickoff_2: Option<Txid>,
+        take_1: Option<Txid>,
+        take_2: Option<Txid>,
+        reimbursed: bool,
+    }
+
+    let peg_out_txid = path.into_inner();
+    info!("new REQUEST: /get-pegout/{peg_out_txid}");
+    let peg_out = match utils::txid_from_str(&peg_out_txid) {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/get-pegout/{peg_out_txid}: fail to decode txid: {}", e);
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+    };
+
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/get-pegout/{peg_out_txid}: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let workflow_id = match sql::get_workflow_id_by_txid(&db, "peg_out", &peg_out) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            error!("/get-pegout/{peg_out_txid}: unknown peg-out");
+            return HttpResponse::NotFound().body(format!("unknown peg-out {peg_out_txid}"))
+        },
+        Err(e) => { 
+            error!("/get-pegout/{peg_out_txid}: fail to find workflow: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(Some(data)) => data,
+        Ok(None) => {
+            error!("/get-pegout/{peg_out_txid}: workflow {workflow_id} does not exisit");
+            return HttpResponse::NotFound().body(format!("workflow {workflow_id} does not exisit"))
+        },
+        Err(e) => { 
+            error!("/get-pegout/{peg_out_txid}: fail to get user data: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{
+        workflow_id,
+        peg_out,
+        withdrawer_evm_address: user_data.withdrawer_evm_address,
+        withdrawer_address: user_data.withdrawer_address,
+        pegin: user_data.pegin,
+        kickoff_1: user_data.kickoff_1,
+        kickoff_2: user_data.kickoff_2,
+        take_1: user_data.take_1,
+        take_2: user_data.take_2,
+        reimbursed: user_data.take_1.is_some() || user_data.take_2.is_some(),
+    }).unwrap();
+    info!("/get-pegout/{peg_out_txid}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[post("/post-fake-index/{workflow_id}/{fake_index}")]
+async fn post_fake_index(req: HttpRequest, path: web::Path<(i32, u32)>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        success: bool,
+    }
+
+    let (workflow_id, fake_index) = path.into_inner();
+    info!("new REQUEST: /post-fake-index/{workflow_id}/{fake_index}");
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/post-fake-index/{workflow_id}/{fake_index}: fail to connect db: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(resp) = check_workflow_auth(&format!("/post-fake-index/{workflow_id}/{fake_index}"), &db, &req, workflow_id) {
+        return resp
+    };
+
+    match sql::is_workflow_locked(&db, workflow_id) {
+        Ok(v) => {
+            if v {
+                error!("/post-fake-index/{workflow_id}/{fake_index}: workflow:{workflow_id} is processing a task, please wait");
+                return HttpResponse::Locked().body("workflow:{workflow_id} is processing a task, please wait".to_string())
+            };
+        },
+        Err(e) => {
+            error!("/post-fake-index/{workflow_id}/{fake_index}: fail to get workflow lock: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(e) = sql::lock_workflow(&db, workflow_id) {
+        error!("/post-fake-index/{workflow_id}/{fake_index}: fail to lock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    let mut user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/post-fake-index/{workflow_id}/{fake_index}: workflow {workflow_id} does not exisit");
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => {
+            error!("/post-fake-index/{workflow_id}/{fake_index}: fail to get user data: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if !sql::is_pegged_in(user_data.status) {
+        error!("/post-fake-index/{workflow_id}/{fake_index}: workflow {workflow_id} not currently at pegin stage");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at pegin stage"))
+    };
+
+    let fake_index = if fake_index > 614 { 
+        warn!("/post-fake-index/{workflow_id}/{fake_index}: index out of range");
+        1 
+    } else {
+        fake_index 
+    };
+    user_data.fake_index = Some(fake_index);
+
+    if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
+        error!("/post-fake-index/{workflow_id}/{fake_index}: fail to update user data: {}",e);
+        return HttpResponse::InternalServerError().body(e.to_string())
+    }
+
+    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+        error!("/post-fake-index/{workflow_id}/{fake_index}: fail to unlock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{success:true}).unwrap();
+    info!("/post-fake-index/{workflow_id}/{fake_index}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[post("/register-vk")]
+async fn register_vk(req: HttpRequest, vk_json: web::Json<serde_json::Value>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        vk_id: String,
+        compiled: bool,
+    }
+
+    info!("new REQUEST: /register-vk");
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/register-vk: fail to connect db: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    if let Err(resp) = check_session("/register-vk", &db, &req) {
+        return resp
+    };
+    let vk_id = match setup::register_vk(&vk_json) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/register-vk: fail to register vk: {}", e);
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+    };
+
+    let compiled = setup::is_vk_compiled(&vk_id);
+    if !compiled {
+        // compile in background, progress is reported by /get-vk-info/{vk_id}
+        let task_vk_id = vk_id.clone();
+        tokio::task::spawn_blocking(move || {
+            setup::setup_vk(&task_vk_id);
+        });
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{vk_id: vk_id.clone(), compiled}).unwrap();
+    info!("/register-vk: ok, vk_id: {vk_id}");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[get("/get-vk-info/{vk_id}")]
+async fn get_vk_info(path: web::Path<String>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        vk_id: String,
+        compiled: bool,
+    }
+
+    let vk_id = path.into_inner();
+    info!("new REQUEST: /get-vk-info/{vk_id}");
+    if !setup::is_vk_registered(&vk_id) {
+        error!("/get-vk-info/{vk_id}: vk {vk_id} does not exisit");
+        return HttpResponse::BadRequest().body(format!("vk {vk_id} does not exisit"))
+    };
+
+    let compiled = setup::is_vk_compiled(&vk_id);
+    let body = serde_json::to_string_pretty(&ResponseStruct{vk_id: vk_id.clone(), compiled}).unwrap();
+    info!("/get-vk-info/{vk_id}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+#[post("/post-proof/{workflow_id}/{vk_id}")]
+async fn post_proof(req: HttpRequest, path: web::Path<(i32, String)>, proof_json: web::Json<serde_json::Value>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        success: bool,
+    }
+
+    let (workflow_id, vk_id) = path.into_inner();
+    info!("new REQUEST: /post-proof/{workflow_id}/{vk_id}");
+    if !setup::is_vk_registered(&vk_id) {
+        error!("/post-proof/{workflow_id}/{vk_id}: vk {vk_id} does not exisit");
+        return HttpResponse::BadRequest().body(format!("vk {vk_id} does not exisit"))
+    };
+    if !setup::is_vk_compiled(&vk_id) {
+        error!("/post-proof/{workflow_id}/{vk_id}: vk {vk_id} is still compiling");
+        return HttpResponse::BadRequest().body(format!("vk {vk_id} is still compiling, check /get-vk-info/{vk_id}"))
+    };
+
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/post-proof/{workflow_id}/{vk_id}: fail to connect db: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(resp) = check_workflow_auth(&format!("/post-proof/{workflow_id}/{vk_id}"), &db, &req, workflow_id) {
+        return resp
+    };
+
+    match sql::is_workflow_locked(&db, workflow_id) {
+        Ok(v) => {
+            if v {
+                error!("/post-proof/{workflow_id}/{vk_id}: workflow:{workflow_id} is processing a task, please wait");
+                return HttpResponse::Locked().body("workflow:{workflow_id} is processing a task, please wait".to_string())
+            };
+        },
+        Err(e) => {
+            error!("/post-proof/{workflow_id}/{vk_id}: fail to get workflow lock: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(e) = sql::lock_workflow(&db, workflow_id) {
+        error!("/post-proof/{workflow_id}/{vk_id}: fail to lock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    let mut user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/post-proof/{workflow_id}/{vk_id}: workflow {workflow_id} does not exisit");
+                    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                        error!("/post-proof/{workflow_id}/{vk_id}: fail to unlock workflow: {}", e);
+                    };
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => {
+            error!("/post-proof/{workflow_id}/{vk_id}: fail to get user data: {}",e);
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/post-proof/{workflow_id}/{vk_id}: fail to unlock workflow: {}", e);
+            };
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    // the workflow's tapscripts & signed assertions are generated from the proof after kickoff
+    if !sql::is_pegged_in(user_data.status) {
+        error!("/post-proof/{workflow_id}/{vk_id}: workflow {workflow_id} not currently at pegin stage");
+        if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+            error!("/post-proof/{workflow_id}/{vk_id}: fail to unlock workflow: {}", e);
+        };
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at pegin stage"))
+    };
+
+    let proof_path = match setup::store_workflow_proof(workflow_id, &vk_id, &proof_json) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/post-proof/{workflow_id}/{vk_id}: fail to store proof: {}", e);
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/post-proof/{workflow_id}/{vk_id}: fail to unlock workflow: {}", e);
+            };
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+    };
+    user_data.vk_id = Some(vk_id.clone());
+    user_data.proof_path = Some(proof_path);
+
+    if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
+        error!("/post-proof/{workflow_id}/{vk_id}: fail to update user data: {}",e);
+        if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+            error!("/post-proof/{workflow_id}/{vk_id}: fail to unlock workflow: {}", e);
+        };
+        return HttpResponse::InternalServerError().body(e.to_string())
+    }
+
+    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+        error!("/post-proof/{workflow_id}/{vk_id}: fail to unlock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{success:true}).unwrap();
+    info!("/post-proof/{workflow_id}/{vk_id}: ok");
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body)
+}
+
+// validations are cpu bound & queued, a full queue is a transient condition
+fn validation_queue_full(e: String) -> HttpResponse {
+    HttpResponse::ServiceUnavailable()
+        .insert_header((header::RETRY_AFTER, config::VALIDATION_RETRY_AFTER.to_string()))
+        .body(e)
+}
+
+// per-request scratch space of /verify/*, removed when dropped
+fn verify_scratch_dir() -> Result<TempDir, String> {
+    if let Err(e) = std::fs::create_dir_all(config::VERIFY_PATH) {
+        return Err(format!("fail to create {}: {}", config::VERIFY_PATH, e))
+    };
+    match tempfile::Builder::new().prefix("verify_").tempdir_in(config::VERIFY_PATH) {
+        Ok(v) => Ok(v),
+        Err(e) => Err(format!("fail to create scratch dir: {}", e)),
+    }
+}
+
+#[derive(Deserialize)]
+struct VerifyProofRequest {
+    vk_id: Option<String>,
+    proof: serde_json::Value, // {"1": proof, "2": public_inputs}
+}
+
+#[post("/verify/proof")]
+async fn verify_proof(http_req: HttpRequest, req: web::Json<VerifyProofRequest>) -> impl Responder {
+    info!("new REQUEST: /verify/proof");
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/verify/proof: fail to connect db: {}",e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    if let Err(resp) = check_session("/verify/proof", &db, &http_req) {
+        return resp
+    };
+    let req = req.into_inner();
+    if let Some(vk_id) = &req.vk_id {
+        if !setup::is_vk_registered(vk_id) {
+            error!("/verify/proof: vk {vk_id} does not exisit");
+            return HttpResponse::BadRequest().body(format!("vk {vk_id} does not exisit"))
+        };
+    };
+
+    let scratch_dir = match verify_scratch_dir() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/verify/proof: {}", e);
+            return HttpResponse::InternalServerError().body(e)
+        }
+    };
+    let proof_path = scratch_dir.path().join("proof.json").to_string_lossy().to_string();
+    if let Err(e) = setup::write_proof_file(&proof_path, req.vk_id.as_deref(), &req.proof) {
+        error!("/verify/proof: fail to store proof: {}", e);
+        return HttpResponse::BadRequest().body(e.to_string())
+    };
+
+    // the scratch dir is dropped (and removed) with the task, also if the validation panics
+    let task = match workers::spawn_validation(move || {
+        let sigs_dir = scratch_dir.path().to_string_lossy().to_string();
+        transactions::verify_proof(&proof_path, &sigs_dir)
+    }) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/verify/proof: {}", e);
+            return validation_queue_full(e)
+        }
+    };
+    let res = task.await;
+
+    match res {
+        Ok(res) => match res {
+            Ok(v) => {
+                let body = serde_json::to_string_pretty(&VerifyResult::from_validation(v)).unwrap();
+                info!("/verify/proof: ok");
+                HttpResponse::Ok()
+                    .content_type(ContentType::json())
+                    .body(body)
+            },
+            Err(e) => {
+                error!("/verify/proof: fail to verify proof: {}", e);
+                HttpResponse::InternalServerError().body(e.to_string())
+            }
+        },
+        Err(e) => {
+            error!("/verify/proof: fail while run verify task (malformed proof?): {}", e);
+            HttpResponse::BadRequest().body(e.to_string())
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct VerifyAssertionsRequest {
+    workflow_id: Option<i32>, // verify against this workflow's wots key & proof, default key & dummy proof otherwise
+    signed_assertions: Option<Vec<serde_json::Value>>, // contents of signed_assertion_{i}.json
+    fake_index: Option<usize>, // without signed_assertions: the stored assertions corrupted at fake_index
+}
+
+#[post("/verify/assertions")]
+async fn verify_assertions(http_req: HttpRequest, req: web::Json<VerifyAssertionsRequest>) -> impl Responder {
+    info!("new REQUEST: /verify/assertions");
+    if let Err(resp) = check_statics_ready("/verify/assertions") {
+        return resp
+    };
+    let req = req.into_inner();
     let db = match sql::open_db() {
         Ok(v) => v,
         Err(e) => {
-            error!("/post-fake-index/{workflow_id}/{fake_index}: fail to connect db: {}",e);
+            error!("/verify/assertions: fail to connect db: {}",e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
-
-    match sql::is_workflow_locked(&db, workflow_id) {
-        Ok(v) => {
-            if v {
-                error!("/post-fake-index/{workflow_id}/{fake_index}: workflow:{workflow_id} is processing a task, please wait");
-                return HttpResponse::Locked().body("workflow:{workflow_id} is processing a task, please wait".to_string())
-            };
-        },
-        Err(e) => {
-            error!("/post-fake-index/{workflow_id}/{fake_index}: fail to get workflow lock: {}", e);
-            return HttpResponse::InternalServerError().body(e.to_string())
-        }
+    // a workflow's key set only for its owner
+    let auth_res = match req.workflow_id {
+        Some(workflow_id) if !is_admin(&http_req) => check_workflow_auth("/verify/assertions", &db, &http_req, workflow_id),
+        _ => check_session("/verify/assertions", &db, &http_req),
     };
-
-    if let Err(e) = sql::lock_workflow(&db, workflow_id) {
-        error!("/post-fake-index/{workflow_id}/{fake_index}: fail to lock workflow: {}", e);
-        return HttpResponse::InternalServerError().body(e.to_string());
+    if let Err(resp) = auth_res {
+        return resp
     };
-
-    let mut user_data = match sql::get_user_data(&db, workflow_id) {
-        Ok(user_data_option) => { match user_data_option {
-                Some(data) => data,
+    let key_set = match req.workflow_id {
+        Some(workflow_id) => {
+            let user_data = match sql::get_user_data(&db, workflow_id) {
+                Ok(user_data_option) => { match user_data_option {
+                        Some(data) => data,
+                        _ => {
+                            error!("/verify/assertions: workflow {workflow_id} does not exisit");
+                            return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                        }
+                    }
+                },
+                Err(e) => {
+                    error!("/verify/assertions: fail to get user data: {}",e);
+                    return HttpResponse::InternalServerError().body(e.to_string())
+                }
+            };
+            match user_data.kickoff_1 {
+                Some(kick_off_1_txid) => transactions::get_workflow_key_set(kick_off_1_txid, &user_data),
                 _ => {
-                    error!("/post-fake-index/{workflow_id}/{fake_index}: workflow {workflow_id} does not exisit");
-                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                    error!("/verify/assertions: workflow {workflow_id} has no wots key before kickoff_1");
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} has no wots key before kickoff_1"))
                 }
             }
         },
-        Err(e) => {
-            error!("/post-fake-index/{workflow_id}/{fake_index}: fail to get user data: {}",e);
-            return HttpResponse::InternalServerError().body(e.to_string())
-        }
+        _ => transactions::WotsKeySet::default_set(),
     };
 
-    if user_data.status != sql::STATUS::PEGIN as u8 {
-        error!("/post-fake-index/{workflow_id}/{fake_index}: workflow {workflow_id} not currently at pegin stage");
-        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at pegin stage"))
+    let scratch_dir = match verify_scratch_dir() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/verify/assertions: {}", e);
+            return HttpResponse::InternalServerError().body(e)
+        }
     };
-
-    let fake_index = if fake_index > 614 { 
-        warn!("/post-fake-index/{workflow_id}/{fake_index}: index out of range");
-        1 
-    } else {
-        fake_index 
+    let task = match workers::spawn_validation(move || {
+        let sigs_dir = scratch_dir.path().to_string_lossy().to_string();
+        match req.signed_assertions {
+            Some(files) => transactions::verify_signed_assertion_files(&key_set, &sigs_dir, &files),
+            _ => transactions::verify_stored_assertions(&key_set, &sigs_dir, req.fake_index),
+        }
+    }) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/verify/assertions: {}", e);
+            return validation_queue_full(e)
+        }
     };
-    user_data.fake_index = Some(fake_index);
 
-    if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
-        error!("/post-fake-index/{workflow_id}/{fake_index}: fail to update user data: {}",e);
-        return HttpResponse::InternalServerError().body(e.to_string())
+    match task.await {
+        Ok(res) => match res {
+            Ok(v) => {
+                let body = serde_json::to_string_pretty(&VerifyResult::from_validation(v)).unwrap();
+                info!("/verify/assertions: ok");
+                HttpResponse::Ok()
+                    .content_type(ContentType::json())
+                    .body(body)
+            },
+            Err(e) => {
+                error!("/verify/assertions: fail to verify assertions: {}", e);
+                HttpResponse::BadRequest().body(e.to_string())
+            }
+        },
+        Err(e) => {
+            error!("/verify/assertions: fail while run verify task (malformed assertions?): {}", e);
+            HttpResponse::BadRequest().body(e.to_string())
+        }
     }
-
-    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
-        error!("/post-fake-index/{workflow_id}/{fake_index}: fail to unlock workflow: {}", e);
-        return HttpResponse::InternalServerError().body(e.to_string());
-    };
-
-    let body = serde_json::to_string_pretty(&ResponseStruct{success:true}).unwrap();
-    info!("/post-fake-index/{workflow_id}/{fake_index}: ok");
-    HttpResponse::Ok()
-        .content_type(ContentType::json())
-        .body(body)
 }
 
 #[get("/get-unsigned-kickoff1-tx/{workflow_id}")]
-async fn get_unsigned_kickoff1_tx(path: web::Path<i32>) -> impl Responder {
+async fn get_unsigned_kickoff1_tx(path: web::Path<i32>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
     #[derive(Serialize)]
     struct ResponseStruct {
         input: TxInput,
         outputs: Vec<TxOutput>,
+        fee_info: transactions::FeeInfo,
     }
 
     let workflow_id = path.into_inner();
@@ -972,7 +3313,7 @@ async fn get_unsigned_kickoff1_tx(path: web::Path<i32>) -> impl Responder {
         Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
     };
 
-    if user_data.status != sql::STATUS::PEGIN as u8 {
+    if !sql::is_pegged_in(user_data.status) {
         error!("/get-unsigned-kickoff1-tx/{workflow_id}: workflow {workflow_id} not currently at pegin stage");
         return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at pegin stage"))
     };
@@ -985,31 +3326,22 @@ async fn get_unsigned_kickoff1_tx(path: web::Path<i32>) -> impl Responder {
         }
     };
 
-    let rpc = match utils::new_rpc_client().await {
-        Ok(v) => v,
-        Err(e) => { 
-            error!("/get-unsigned-kickoff1-tx/{workflow_id}: fail to connect bitcoind: {}",e);
-            return HttpResponse::InternalServerError().body(e.to_string())
-        }
-    };
-
-    let kickoff_1_tx = match transactions::kickoff_1_prepare(&rpc, faucet_2_txid, faucet_2_vout) {
+    let rpc = rpc_pool.get();
+    let res = workers::run_blocking(move || {
+        let kickoff_1_tx = transactions::kickoff_1_prepare(&rpc, faucet_2_txid, faucet_2_vout)?;
+        let prevout = kickoff_1_tx.input[0].previous_output;
+        let (script_pubkey, value) = utils::get_utxo_script_pubkey_value(&rpc, prevout.txid, prevout.vout)?;
+        let fee_info = transactions::get_fee_info(&rpc, &kickoff_1_tx)?;
+        Ok((kickoff_1_tx, prevout, script_pubkey, value, fee_info))
+    }).await;
+    let (kickoff_1_tx, prevout, script_pubkey, value, fee_info) = match res {
         Ok(v) => v,
         Err(e) => { 
             error!("/get-unsigned-kickoff1-tx/{workflow_id}: fail to prepare kickoff1 tx: {}",e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
-
-    let txid = kickoff_1_tx.input[0].previous_output.txid;
-    let vout = kickoff_1_tx.input[0].previous_output.vout;
-    let (script_pubkey, value) = match utils::get_utxo_script_pubkey_value(&rpc, txid, vout) {
-        Ok(v) => v,
-        Err(e) => { 
-            error!("/get-unsigned-kickoff1-tx/{workflow_id}: fail to get_utxo_value: {}",e);
-            return HttpResponse::InternalServerError().body(e.to_string())
-        }
-    };
+    let (txid, vout) = (prevout.txid, prevout.vout);
     let input = TxInput {
         txid,
         vout,
@@ -1031,7 +3363,7 @@ async fn get_unsigned_kickoff1_tx(path: web::Path<i32>) -> impl Responder {
         outputs.push(output_i)
     };
 
-    let body = serde_json::to_string_pretty(&ResponseStruct{input, outputs}).unwrap();
+    let body = serde_json::to_string_pretty(&ResponseStruct{input, outputs, fee_info}).unwrap();
     info!("/get-unsigned-kickoff1-tx/{workflow_id}: ok");
     HttpResponse::Ok()
         .content_type(ContentType::json())
@@ -1039,14 +3371,19 @@ async fn get_unsigned_kickoff1_tx(path: web::Path<i32>) -> impl Responder {
 }
 
 #[post("/send-kickoff2/{workflow_id}/{kickoff_1_txid}")]
-async fn send_kickoff_2(path: web::Path<(i32, String)>) -> impl Responder {
+async fn send_kickoff_2(req: HttpRequest, path: web::Path<(i32, String)>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
     #[derive(Serialize)]
     struct ResponseStruct {
         kick_off_2_txid: Txid,
+        scheduled_height: Option<u64>,
+        fee_info: transactions::FeeInfo,
     }
 
     let (workflow_id, kickoff_1_txid) = path.into_inner();
     info!("new REQUEST: /send-kickoff2/{workflow_id}/{kickoff_1_txid}");
+    if let Err(resp) = check_statics_ready(&format!("/send-kickoff2/{workflow_id}/{kickoff_1_txid}")) {
+        return resp
+    };
     let kick_off_1_txid = match utils::txid_from_str(&kickoff_1_txid) {
         Ok(v) => v,
         Err(e) => { 
@@ -1063,6 +3400,10 @@ async fn send_kickoff_2(path: web::Path<(i32, String)>) -> impl Responder {
         }
     };
 
+    if let Err(resp) = check_workflow_auth(&format!("/send-kickoff2/{workflow_id}/{kickoff_1_txid}"), &db, &req, workflow_id) {
+        return resp
+    };
+
     match sql::is_workflow_locked(&db, workflow_id) {
         Ok(v) => {
             if v {
@@ -1096,11 +3437,12 @@ async fn send_kickoff_2(path: web::Path<(i32, String)>) -> impl Responder {
         }
     };
 
-    if user_data.status == sql::STATUS::PEGIN as u8 {
+    if let Err(e) = steps::check_stage("kickoff-2", user_data.status) {
+        error!("/send-kickoff2/{workflow_id}/{kickoff_1_txid}: workflow {workflow_id} {e}");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} {e}"))
+    };
+    if sql::is_pegged_in(user_data.status) {
         user_data.status = sql::STATUS::KICKOFF1 as u8;
-    } else if user_data.status != sql::STATUS::KICKOFF1 as u8 {
-        error!("/send-kickoff2/{workflow_id}/{kickoff_1_txid}: workflow {workflow_id} not currently at pegin/kickoff1 stage");
-        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at pegin/kickoff1 stage"))
     };
 
     user_data.kickoff_1 = Some(kick_off_1_txid);
@@ -1110,55 +3452,339 @@ async fn send_kickoff_2(path: web::Path<(i32, String)>) -> impl Responder {
         return HttpResponse::InternalServerError().body(e.to_string())
     }
 
-    let rpc = match utils::new_rpc_client().await {
+    let res = match steps::kick_off_2(rpc_pool.get(), &mut user_data).await {
+        Ok(v) => v,
+        Err(e) => {
+            error!("/send-kickoff2/{workflow_id}/{kickoff_1_txid}: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let kick_off_2_txid = res.txid();
+
+    if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
+        error!("/send-kickoff2/{workflow_id}/{kickoff_1_txid}: fail to update user data: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string())
+    }
+
+    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+        error!("/send-kickoff2/{workflow_id}/{kickoff_1_txid}: fail to unlock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    info!("/send-kickoff2/{workflow_id}/{kickoff_1_txid}: ok");
+    broadcast_response(res, &ResponseStruct{kick_off_2_txid, scheduled_height: res.scheduled_height(), fee_info: res.fee_info()})
+}
+
+#[derive(Clone, Copy)]
+enum KickOff1Branch {
+    StartTime,
+    StartTimeTimeout,
+    KickOffTimeout,
+}
+
+impl KickOff1Branch {
+    fn name(&self) -> &'static str {
+        match self {
+            KickOff1Branch::StartTime => "start-time",
+            KickOff1Branch::StartTimeTimeout => "start-time-timeout",
+            KickOff1Branch::KickOffTimeout => "kickoff-timeout",
+        }
+    }
+
+    // statuses the branch can be taken from, kickoff_1 is recorded on the way if at pegin/pegout
+    fn allowed_status(&self) -> Vec<u8> {
+        match self {
+            KickOff1Branch::StartTime => vec![sql::STATUS::PEGIN as u8, sql::STATUS::PEGOUT as u8, sql::STATUS::KICKOFF1 as u8],
+            KickOff1Branch::StartTimeTimeout => vec![sql::STATUS::PEGIN as u8, sql::STATUS::PEGOUT as u8, sql::STATUS::KICKOFF1 as u8],
+            KickOff1Branch::KickOffTimeout => vec![sql::STATUS::PEGIN as u8, sql::STATUS::PEGOUT as u8, sql::STATUS::KICKOFF1 as u8, sql::STATUS::START_TIME as u8],
+        }
+    }
+}
+
+#[post("/send-start-time/{workflow_id}/{kickoff_1_txid}")]
+async fn send_start_time(req: HttpRequest, path: web::Path<(i32, String)>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    let (workflow_id, kickoff_1_txid) = path.into_inner();
+    send_kick_off_1_branch(req, KickOff1Branch::StartTime, workflow_id, kickoff_1_txid, rpc_pool).await
+}
+
+#[post("/send-start-time-timeout/{workflow_id}/{kickoff_1_txid}")]
+async fn send_start_time_timeout(req: HttpRequest, path: web::Path<(i32, String)>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    let (workflow_id, kickoff_1_txid) = path.into_inner();
+    send_kick_off_1_branch(req, KickOff1Branch::StartTimeTimeout, workflow_id, kickoff_1_txid, rpc_pool).await
+}
+
+#[post("/send-kickoff-timeout/{workflow_id}/{kickoff_1_txid}")]
+async fn send_kickoff_timeout(req: HttpRequest, path: web::Path<(i32, String)>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    let (workflow_id, kickoff_1_txid) = path.into_inner();
+    send_kick_off_1_branch(req, KickOff1Branch::KickOffTimeout, workflow_id, kickoff_1_txid, rpc_pool).await
+}
+
+async fn send_kick_off_1_branch(req: HttpRequest, branch: KickOff1Branch, workflow_id: i32, kickoff_1_txid: String, rpc_pool: web::Data<RpcPool>) -> HttpResponse {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        txid: Txid,
+        scheduled_height: Option<u64>,
+        fee_info: transactions::FeeInfo,
+    }
+
+    let name = branch.name();
+    info!("new REQUEST: /send-{name}/{workflow_id}/{kickoff_1_txid}");
+    let kick_off_1_txid = match utils::txid_from_str(&kickoff_1_txid) {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/send-{name}/{workflow_id}/{kickoff_1_txid}: fail to decode txid: {}", e);
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+    };
+
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/send-{name}/{workflow_id}/{kickoff_1_txid}: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(resp) = check_workflow_auth(&format!("/send-{name}/{workflow_id}/{kickoff_1_txid}"), &db, &req, workflow_id) {
+        return resp
+    };
+
+    match sql::is_workflow_locked(&db, workflow_id) {
+        Ok(v) => {
+            if v {
+                error!("/send-{name}/{workflow_id}/{kickoff_1_txid}: workflow:{workflow_id} is processing a task, please wait");
+                return HttpResponse::Locked().body(format!("workflow:{workflow_id} is processing a task, please wait"))
+            };
+        },
+        Err(e) => {
+            error!("/send-{name}/{workflow_id}/{kickoff_1_txid}: fail to get workflow lock: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(e) = sql::lock_workflow(&db, workflow_id) {
+        error!("/send-{name}/{workflow_id}/{kickoff_1_txid}: fail to lock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    let mut user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/send-{name}/{workflow_id}/{kickoff_1_txid}: workflow {workflow_id} does not exisit");
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => { 
+            error!("/send-{name}/{workflow_id}/{kickoff_1_txid}: fail to get user data: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if !branch.allowed_status().contains(&user_data.status) {
+        error!("/send-{name}/{workflow_id}/{kickoff_1_txid}: workflow {workflow_id} can not send {name} at status {}", user_data.status);
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} can not send {name} at status {}", user_data.status))
+    };
+
+    if let Some(txid) = user_data.kickoff_1 {
+        if txid != kick_off_1_txid {
+            error!("/send-{name}/{workflow_id}/{kickoff_1_txid}: workflow {workflow_id} already kicked off by {txid}");
+            return HttpResponse::BadRequest().body(format!("workflow {workflow_id} already kicked off by {txid}"))
+        };
+    };
+
+    let rpc = rpc_pool.get();
+
+    let res = workers::run(async move {
+        match branch {
+            KickOff1Branch::StartTime => transactions::start_time(&rpc, kick_off_1_txid).await,
+            KickOff1Branch::StartTimeTimeout => transactions::start_time_timeout(&rpc, kick_off_1_txid, None).await,
+            KickOff1Branch::KickOffTimeout => transactions::kick_off_timeout(&rpc, kick_off_1_txid, None).await,
+        }
+    }).await;
+    let res = match res {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/send-{name}/{workflow_id}/{kickoff_1_txid}: fail to send {name} tx: {}", e);
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/send-{name}/{workflow_id}/{kickoff_1_txid}: fail to unlock workflow: {}", e);
+            };
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+    let txid = res.txid();
+
+    // kickoff_1 is only recorded once a tx spending it made it out (or into the scheduler)
+    user_data.kickoff_1 = Some(kick_off_1_txid);
+    if let Err(e) = steps::record_broadcast(&mut user_data, name, res) {
+        error!("/send-{name}/{workflow_id}/{kickoff_1_txid}: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string())
+    };
+
+    if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
+        error!("/send-{name}/{workflow_id}/{kickoff_1_txid}: fail to update user data: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string())
+    }
+
+    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+        error!("/send-{name}/{workflow_id}/{kickoff_1_txid}: fail to unlock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    info!("/send-{name}/{workflow_id}/{kickoff_1_txid}: ok");
+    broadcast_response(res, &ResponseStruct{txid, scheduled_height: res.scheduled_height(), fee_info: res.fee_info()})
+}
+
+#[post("/send-challenge/{workflow_id}")]
+async fn send_challenge(req: HttpRequest, path: web::Path<i32>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        challenge_txid: Txid,
+        scheduled_height: Option<u64>,
+        fee_info: transactions::FeeInfo,
+    }
+
+    let workflow_id = path.into_inner();
+    info!("new REQUEST: /send-challenge/{workflow_id}");
+    let db = match sql::open_db() {
         Ok(v) => v,
         Err(e) => { 
-            error!("/send-kickoff2/{workflow_id}/{kickoff_1_txid}: fail to connect bitcoind: {}", e);
+            error!("/send-challenge/{workflow_id}: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(resp) = check_workflow_auth(&format!("/send-challenge/{workflow_id}"), &db, &req, workflow_id) {
+        return resp
+    };
+
+    match sql::is_workflow_locked(&db, workflow_id) {
+        Ok(v) => {
+            if v {
+                error!("/send-challenge/{workflow_id}: workflow:{workflow_id} is processing a task, please wait");
+                return HttpResponse::Locked().body("workflow:{workflow_id} is processing a task, please wait".to_string())
+            };
+        },
+        Err(e) => {
+            error!("/send-challenge/{workflow_id}: fail to get workflow lock: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(e) = sql::lock_workflow(&db, workflow_id) {
+        error!("/send-challenge/{workflow_id}: fail to lock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    let mut user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(user_data_option) => { match user_data_option {
+                Some(data) => data,
+                _ => {
+                    error!("/send-challenge/{workflow_id}: workflow {workflow_id} does not exisit");
+                    return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+                }
+            }
+        },
+        Err(e) => { 
+            error!("/send-challenge/{workflow_id}: fail to get user data: {}", e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
-    let bitcom_lock_scripts = transactions::borrow_bitcom_lock_scripts();
-    let kick_off_2_txid = match transactions::kick_off_2(&rpc, kick_off_1_txid, bitcom_lock_scripts).await {
+
+    if let Err(e) = steps::check_stage("challenge", user_data.status) {
+        error!("/send-challenge/{workflow_id}: workflow {workflow_id} {e}");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} {e}"))
+    };
+
+    let res = match steps::challenge(rpc_pool.get(), &mut user_data).await {
         Ok(v) => v,
-        Err(e) => { 
-            error!("/send-kickoff2/{workflow_id}/{kickoff_1_txid}: fail to send kickoff2 tx: {}", e);
+        Err(e) => {
+            error!("/send-challenge/{workflow_id}: {}", e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
-
-    user_data.status = sql::STATUS::KICKOFF2 as u8;
-    user_data.kickoff_2 = Some(kick_off_2_txid);
+    let challenge_txid = res.txid();
 
     if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
-        error!("/send-kickoff2/{workflow_id}/{kickoff_1_txid}: fail to update user data: {}", e);
+        error!("/send-challenge/{workflow_id}: fail to update user data: {}", e);
         return HttpResponse::InternalServerError().body(e.to_string())
     }
 
     if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
-        error!("/send-kickoff2/{workflow_id}/{kickoff_1_txid}: fail to unlock workflow: {}", e);
+        error!("/send-challenge/{workflow_id}: fail to unlock workflow: {}", e);
         return HttpResponse::InternalServerError().body(e.to_string());
     };
 
-    let body = serde_json::to_string_pretty(&ResponseStruct{kick_off_2_txid}).unwrap();
-    info!("/send-kickoff2/{workflow_id}/{kickoff_1_txid}: ok");
-    HttpResponse::Ok()
-        .content_type(ContentType::json())
-        .body(body)
+    info!("/send-challenge/{workflow_id}: ok");
+    broadcast_response(res, &ResponseStruct{challenge_txid, scheduled_height: res.scheduled_height(), fee_info: res.fee_info()})
 }
 
-#[post("/send-challenge/{workflow_id}")]
-async fn send_challenge(path: web::Path<i32>) -> impl Responder {
+#[derive(Deserialize)]
+struct ChallengeContributeRequest {
+    txid: Option<String>,
+    vout: Option<u32>,
+    witness: Option<Vec<String>>, // hex items, signed SIGHASH_SINGLE|ANYONECANPAY over the refund output
+    refund_address: Option<String>,
+    refund_amount: Option<u64>, // in sats
+    amount: Option<u64>, // regtest only, without an input: the service funds & signs a stub contribution of this amount
+}
+
+impl ChallengeContributeRequest {
+    fn to_contribution(&self, rpc: &Client) -> Result<sql::ChallengeContribution, String> {
+        let (txid, vout, witness) = match (&self.txid, self.vout, &self.witness) {
+            (Some(txid), Some(vout), Some(witness)) => (txid, vout, witness),
+            (None, None, None) => {
+                if config::network() != bitcoin::Network::Regtest {
+                    return Err("txid, vout & witness are required".to_string())
+                };
+                let amount = match self.amount {
+                    Some(v) => Amount::from_sat(v),
+                    _ => return Err("amount is required for a stub contribution".to_string()),
+                };
+                return transactions::stub_challenge_contribution(rpc, amount)
+            },
+            _ => return Err("txid, vout & witness must be provided together".to_string()),
+        };
+        let txid = utils::txid_from_str(txid)?;
+        let amount = match utils::get_utxo_value(rpc, txid, vout) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("contribution input {txid}:{vout} unavailable: {}", e))
+        };
+        let refund_address = match &self.refund_address {
+            Some(addr) => utils::address_from_str(addr)?,
+            _ => return Err("refund_address is required".to_string()),
+        };
+        let refund_amount = Amount::from_sat(self.refund_amount.unwrap_or(DUST_AMOUNT));
+        if refund_amount < Amount::from_sat(DUST_AMOUNT) || refund_amount >= amount {
+            return Err(format!("refund amount must be in [{DUST_AMOUNT}, {amount})"))
+        };
+        Ok(sql::ChallengeContribution {
+            outpoint: OutPoint { txid, vout },
+            amount,
+            witness: witness.clone(),
+            refund_script_pubkey: refund_address.script_pubkey(),
+            refund_amount,
+        })
+    }
+}
+
+#[post("/workflow/{workflow_id}/challenge/contribute")]
+async fn contribute_challenge(path: web::Path<i32>, req: web::Json<ChallengeContributeRequest>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
     #[derive(Serialize)]
     struct ResponseStruct {
-        challenge_txid: Txid,
+        contributors: usize,
+        collected: Amount,
+        required: Amount,
+        challenge_txid: Option<Txid>,
     }
 
     let workflow_id = path.into_inner();
-    info!("new REQUEST: /send-challenge/{workflow_id}");
+    info!("new REQUEST: /workflow/{workflow_id}/challenge/contribute");
     let db = match sql::open_db() {
         Ok(v) => v,
         Err(e) => { 
-            error!("/send-challenge/{workflow_id}: fail to connect db: {}", e);
+            error!("/workflow/{workflow_id}/challenge/contribute: fail to connect db: {}", e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
@@ -1166,18 +3792,19 @@ async fn send_challenge(path: web::Path<i32>) -> impl Responder {
     match sql::is_workflow_locked(&db, workflow_id) {
         Ok(v) => {
             if v {
-                error!("/send-challenge/{workflow_id}: workflow:{workflow_id} is processing a task, please wait");
-                return HttpResponse::Locked().body("workflow:{workflow_id} is processing a task, please wait".to_string())
+                error!("/workflow/{workflow_id}/challenge/contribute: workflow:{workflow_id} is processing a task, please wait");
+                return HttpResponse::Locked().body(format!("workflow:{workflow_id} is processing a task, please wait"))
             };
         },
         Err(e) => {
-            error!("/send-challenge/{workflow_id}: fail to get workflow lock: {}", e);
+            error!("/workflow/{workflow_id}/challenge/contribute: fail to get workflow lock: {}", e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
 
+    // held from the read of the contributions to their update, parallel contributors would overwrite each other
     if let Err(e) = sql::lock_workflow(&db, workflow_id) {
-        error!("/send-challenge/{workflow_id}: fail to lock workflow: {}", e);
+        error!("/workflow/{workflow_id}/challenge/contribute: fail to lock workflow: {}", e);
         return HttpResponse::InternalServerError().body(e.to_string());
     };
 
@@ -1185,70 +3812,130 @@ async fn send_challenge(path: web::Path<i32>) -> impl Responder {
         Ok(user_data_option) => { match user_data_option {
                 Some(data) => data,
                 _ => {
-                    error!("/send-challenge/{workflow_id}: workflow {workflow_id} does not exisit");
+                    error!("/workflow/{workflow_id}/challenge/contribute: workflow {workflow_id} does not exisit");
+                    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                        error!("/workflow/{workflow_id}/challenge/contribute: fail to unlock workflow: {}", e);
+                    };
                     return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
                 }
             }
         },
         Err(e) => { 
-            error!("/send-challenge/{workflow_id}: fail to get user data: {}", e);
+            error!("/workflow/{workflow_id}/challenge/contribute: fail to get user data: {}", e);
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/workflow/{workflow_id}/challenge/contribute: fail to unlock workflow: {}", e);
+            };
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
 
     if user_data.status != sql::STATUS::KICKOFF2 as u8 {
-        error!("/send-challenge/{workflow_id}: workflow {workflow_id} not currently at kickoff2 stage");
+        error!("/workflow/{workflow_id}/challenge/contribute: workflow {workflow_id} not currently at kickoff2 stage");
+        if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+            error!("/workflow/{workflow_id}/challenge/contribute: fail to unlock workflow: {}", e);
+        };
         return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at kickoff2 stage"))
     };
 
     let kick_off_1_txid = match user_data.kickoff_1 {
         Some(txid) => txid,
         _ => {
-            error!("/send-challenge/{workflow_id}: workflow {workflow_id} missing kickoff_1_txid");
-            return HttpResponse::InternalServerError().body("workflow {workflow_id} missing kickoff_1_txid".to_string())
+            error!("/workflow/{workflow_id}/challenge/contribute: workflow {workflow_id} missing kickoff_1_txid");
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/workflow/{workflow_id}/challenge/contribute: fail to unlock workflow: {}", e);
+            };
+            return HttpResponse::InternalServerError().body(format!("workflow {workflow_id} missing kickoff_1_txid"))
         }
     };
 
-    let rpc = match utils::new_rpc_client().await {
+    let rpc = rpc_pool.get();
+    let task_rpc = rpc.clone();
+    let req = req.into_inner();
+    let contribution = match workers::run_blocking(move || {
+        let contribution = req.to_contribution(&task_rpc)?;
+        // one bad input would make the whole challenge tx invalid
+        transactions::verify_challenge_contribution(&task_rpc, &contribution)?;
+        Ok(contribution)
+    }).await {
         Ok(v) => v,
         Err(e) => { 
-            error!("/send-challenge/{workflow_id}: fail to connect bitcoind: {}", e);
-            return HttpResponse::InternalServerError().body(e.to_string())
+            error!("/workflow/{workflow_id}/challenge/contribute: invalid contribution: {}", e);
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/workflow/{workflow_id}/challenge/contribute: fail to unlock workflow: {}", e);
+            };
+            return HttpResponse::BadRequest().body(e)
         }
     };
-    let challenge_txid = match transactions::challenge(&rpc, kick_off_1_txid).await {
+    if user_data.challenge_contributions.iter().any(|c| c.outpoint == contribution.outpoint) {
+        error!("/workflow/{workflow_id}/challenge/contribute: {} already contributed", contribution.outpoint);
+        if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+            error!("/workflow/{workflow_id}/challenge/contribute: fail to unlock workflow: {}", e);
+        };
+        return HttpResponse::BadRequest().body(format!("{} already contributed", contribution.outpoint))
+    };
+    user_data.challenge_contributions.push(contribution);
+
+    let task_rpc = rpc.clone();
+    let contributions = user_data.challenge_contributions.clone();
+    let progress = workers::run_blocking(move || transactions::get_challenge_crowdfund_progress(&task_rpc, kick_off_1_txid, &contributions)).await;
+    let (collected, required) = match progress {
         Ok(v) => v,
         Err(e) => { 
-            error!("/send-challenge/{workflow_id}: fail to send challenge tx: {}", e);
+            error!("/workflow/{workflow_id}/challenge/contribute: fail to get crowdfunding progress: {}", e);
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/workflow/{workflow_id}/challenge/contribute: fail to unlock workflow: {}", e);
+            };
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
 
-    user_data.status = sql::STATUS::CHALLENGE as u8;
-    user_data.challenge = Some(challenge_txid);
-
-    if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
-        error!("/send-challenge/{workflow_id}: fail to update user data: {}", e);
-        return HttpResponse::InternalServerError().body(e.to_string())
-    }
+    let mut res = Ok(None);
+    if collected >= required {
+        let contributions = user_data.challenge_contributions.clone();
+        res = match workers::run(async move { transactions::challenge_crowdfund(&rpc, kick_off_1_txid, &contributions).await }).await {
+            Ok(res) => steps::record_broadcast(&mut user_data, "challenge", res).map(|_| Some(res.txid())),
+            Err(e) => Err(format!("fail to send challenge tx: {}", e)),
+        };
+    };
+    let res = match res {
+        Ok(challenge_txid) => match sql::update_user_data(&db, workflow_id, &user_data) {
+            Ok(_) => Ok(challenge_txid),
+            Err(e) => Err(format!("fail to update user data: {}", e)),
+        },
+        Err(e) => Err(e),
+    };
 
     if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
-        error!("/send-challenge/{workflow_id}: fail to unlock workflow: {}", e);
-        return HttpResponse::InternalServerError().body(e.to_string());
+        error!("/workflow/{workflow_id}/challenge/contribute: fail to unlock workflow: {}", e);
     };
 
-    let body = serde_json::to_string_pretty(&ResponseStruct{challenge_txid}).unwrap();
-    info!("/send-challenge/{workflow_id}: ok");
+    let challenge_txid = match res {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/workflow/{workflow_id}/challenge/contribute: {}", e);
+            return HttpResponse::InternalServerError().body(e)
+        }
+    };
+
+    let body = serde_json::to_string_pretty(&ResponseStruct{
+        contributors: user_data.challenge_contributions.len(),
+        collected,
+        required,
+        challenge_txid,
+    }).unwrap();
+    info!("/workflow/{workflow_id}/challenge/contribute: ok");
     HttpResponse::Ok()
         .content_type(ContentType::json())
         .body(body)
 }
 
 #[post("/send-take1/{workflow_id}")]
-async fn send_take_1(path: web::Path<i32>) -> impl Responder {
+async fn send_take_1(req: HttpRequest, path: web::Path<i32>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
     #[derive(Serialize)]
     struct ResponseStruct {
         take_1_txid: Txid,
+        scheduled_height: Option<u64>,
+        fee_info: transactions::FeeInfo,
     }
 
     let workflow_id = path.into_inner();
@@ -1261,6 +3948,10 @@ async fn send_take_1(path: web::Path<i32>) -> impl Responder {
         }
     };
 
+    if let Err(resp) = check_workflow_auth(&format!("/send-take1/{workflow_id}"), &db, &req, workflow_id) {
+        return resp
+    };
+
     match sql::is_workflow_locked(&db, workflow_id) {
         Ok(v) => {
             if v {
@@ -1294,31 +3985,9 @@ async fn send_take_1(path: web::Path<i32>) -> impl Responder {
         }
     };
 
-    if user_data.status != sql::STATUS::KICKOFF2 as u8 {
-        error!("/send-take1/{workflow_id}: workflow {workflow_id} not currently at kickoff2 stage");
-        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at kickoff2 stage"))
-    };
-
-    let peg_in_txid = match user_data.pegin {
-        Some(txid) => txid,
-        _ => {
-            error!("/send-take1/{workflow_id}: workflow {workflow_id} missing pegin_txid");
-            return HttpResponse::InternalServerError().body("workflow {workflow_id} missing pegin_txid".to_string())
-        }
-    };
-    let kick_off_1_txid = match user_data.kickoff_1 {
-        Some(txid) => txid,
-        _ => {
-            error!("/send-take1/{workflow_id}: workflow {workflow_id} missing kickoff1_txid");
-            return HttpResponse::InternalServerError().body("workflow {workflow_id} missing kickoff1_txid".to_string())
-        }
-    };
-    let kick_off_2_txid = match user_data.kickoff_2 {
-        Some(txid) => txid,
-        _ => {
-            error!("/send-take1/{workflow_id}: workflow {workflow_id} missing kickoff2_txid");
-            return HttpResponse::InternalServerError().body("workflow {workflow_id} missing kickoff2_txid".to_string())
-        }
+    if let Err(e) = steps::check_stage("take-1", user_data.status) {
+        error!("/send-take1/{workflow_id}: workflow {workflow_id} {e}");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} {e}"))
     };
 
     let user_address = match sql::get_user_address(&db, workflow_id) {
@@ -1336,23 +4005,14 @@ async fn send_take_1(path: web::Path<i32>) -> impl Responder {
         }
     };
 
-    let rpc = match utils::new_rpc_client().await {
-        Ok(v) => v,
-        Err(e) => { 
-            error!("/send-take1/{workflow_id}: fail to connect bitcoind: {}", e);
-            return HttpResponse::InternalServerError().body(e.to_string())
-        }
-    };
-    let take_1_txid = match transactions::take_1(&rpc, peg_in_txid, kick_off_1_txid, kick_off_2_txid, user_address).await {
+    let res = match steps::take_1(rpc_pool.get(), &mut user_data, user_address).await {
         Ok(v) => v,
-        Err(e) => { 
-            error!("/send-take1/{workflow_id}: fail to send take1 tx: {}", e);
+        Err(e) => {
+            error!("/send-take1/{workflow_id}: {}", e);
             return HttpResponse::InternalServerError().body(e.to_string())
         }
     };
-
-    user_data.status = sql::STATUS::TAKE1 as u8;
-    user_data.take_1 = Some(take_1_txid);
+    let take_1_txid = res.txid();
 
     if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
         error!("/send-take1/{workflow_id}: fail to update user data: {}", e);
@@ -1364,22 +4024,23 @@ async fn send_take_1(path: web::Path<i32>) -> impl Responder {
         return HttpResponse::InternalServerError().body(e.to_string());
     };
 
-    let body = serde_json::to_string_pretty(&ResponseStruct{take_1_txid}).unwrap();
     info!("/send-take1/{workflow_id}: ok");
-    HttpResponse::Ok()
-        .content_type(ContentType::json())
-        .body(body)
+    broadcast_response(res, &ResponseStruct{take_1_txid, scheduled_height: res.scheduled_height(), fee_info: res.fee_info()})
 }
 
 #[post("/send-assert/{workflow_id}")]
-async fn send_assert(path: web::Path<i32>) -> impl Responder {
+async fn send_assert(req: HttpRequest, path: web::Path<i32>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
     #[derive(Serialize)]
     struct ResponseStruct {
         assert_txid: Txid,
+        fee_info: transactions::FeeInfo,
     }
 
     let workflow_id = path.into_inner();
     info!("new REQUEST: /send-assert/{workflow_id}");
+    if let Err(resp) = check_statics_ready(&format!("/send-assert/{workflow_id}")) {
+        return resp
+    };
     let db = match sql::open_db() {
         Ok(v) => v,
         Err(e) => { 
@@ -1388,6 +4049,10 @@ async fn send_assert(path: web::Path<i32>) -> impl Responder {
         }
     };
 
+    if let Err(resp) = check_workflow_auth(&format!("/send-assert/{workflow_id}"), &db, &req, workflow_id) {
+        return resp
+    };
+
     match sql::is_workflow_locked(&db, workflow_id) {
         Ok(v) => {
             if v {
@@ -1421,20 +4086,12 @@ async fn send_assert(path: web::Path<i32>) -> impl Responder {
         }
     };
 
-    if user_data.status != sql::STATUS::CHALLENGE as u8 {
-        error!("/send-assert/{workflow_id}: workflow {workflow_id} not currently at challenge stage");
-        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at challenge stage"))
-    };
-
-    let _kick_off_2_txid = match user_data.kickoff_2 {
-        Some(txid) => txid,
-        _ => {
-            error!("/send-assert/{workflow_id}: workflow {workflow_id} missing kickoff_2_txid");
-            return HttpResponse::InternalServerError().body("workflow {workflow_id} missing kickoff_2_txid".to_string())
-        }
+    if let Err(e) = steps::check_stage("assert", user_data.status) {
+        error!("/send-assert/{workflow_id}: workflow {workflow_id} {e}");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} {e}"))
     };
 
-    async fn long_task(workflow_id: i32) -> Result<Txid, String> {
+    async fn long_task(workflow_id: i32, rpc: RpcClient) -> Result<(Txid, transactions::FeeInfo), String> {
         let db = match sql::open_db() {
             Ok(v) => v,
             Err(e) => return Err(e.to_string()),
@@ -1447,25 +4104,7 @@ async fn send_assert(path: web::Path<i32>) -> impl Responder {
             },
             Err(e) => return Err(e.to_string()),
         };
-        let rpc = match utils::new_rpc_client().await {
-            Ok(v) => v,
-            Err(e) => return Err(e.to_string()),
-        };
-        let kick_off_2_txid = match user_data.kickoff_2 {
-            Some(txid) => txid,
-            _ => return Err("workflow {workflow_id} missing kickoff_2_txid".to_string()),
-        };
-        let corrupt_index = user_data.fake_index;
-        let connector_c_addr = Some(transactions::get_precomputed_connector_c_address());
-        let bitcom_lock_scripts = transactions::borrow_bitcom_lock_scripts();
-        let connector_c_tapscripts = transactions::borrow_assert_tapscripts();
-        let (assert_txid, _) = match transactions::assert(&rpc, kick_off_2_txid, bitcom_lock_scripts, connector_c_tapscripts, corrupt_index, connector_c_addr).await {
-            Ok(v) => v,
-            Err(e) => return Err(e.to_string()),
-        };
-
-        user_data.status = sql::STATUS::ASSERT as u8;
-        user_data.assert = Some(assert_txid);
+        let (assert_txid, fee_info) = steps::assert(rpc, &mut user_data).await?;
 
         if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
             return Err(e.to_string())
@@ -1475,17 +4114,18 @@ async fn send_assert(path: web::Path<i32>) -> impl Responder {
             return Err(e.to_string())
         };
 
-        Ok(assert_txid)
+        Ok((assert_txid, fee_info))
     }
 
-    let task = tokio::spawn(async move {
-        long_task(workflow_id).await
+    let rpc = rpc_pool.get();
+    let task = workers::spawn(async move {
+        long_task(workflow_id, rpc).await
     });
 
     match task.await {
         Ok(res) => match res {
-            Ok(assert_txid) => {
-                let body = serde_json::to_string_pretty(&ResponseStruct{assert_txid}).unwrap();
+            Ok((assert_txid, fee_info)) => {
+                let body = serde_json::to_string_pretty(&ResponseStruct{assert_txid, fee_info}).unwrap();
                 info!("/send-assert/{workflow_id}: ok");
                 HttpResponse::Ok()
                     .content_type(ContentType::json())
@@ -1504,14 +4144,19 @@ async fn send_assert(path: web::Path<i32>) -> impl Responder {
 }
 
 #[post("/send-take2/{workflow_id}")]
-async fn send_take_2(path: web::Path<i32>) -> impl Responder {
+async fn send_take_2(req: HttpRequest, path: web::Path<i32>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
     #[derive(Serialize)]
     struct ResponseStruct {
         take_2_txid: Txid,
+        scheduled_height: Option<u64>,
+        fee_info: transactions::FeeInfo,
     }
 
     let workflow_id = path.into_inner();
     info!("new REQUEST: /send-take2/{workflow_id}");
+    if let Err(resp) = check_statics_ready(&format!("/send-take2/{workflow_id}")) {
+        return resp
+    };
     let db = match sql::open_db() {
         Ok(v) => v,
         Err(e) => { 
@@ -1520,6 +4165,10 @@ async fn send_take_2(path: web::Path<i32>) -> impl Responder {
         }
     };
 
+    if let Err(resp) = check_workflow_auth(&format!("/send-take2/{workflow_id}"), &db, &req, workflow_id) {
+        return resp
+    };
+
     match sql::is_workflow_locked(&db, workflow_id) {
         Ok(v) => {
             if v {
@@ -1553,26 +4202,12 @@ async fn send_take_2(path: web::Path<i32>) -> impl Responder {
         }
     };
 
-    if user_data.status != sql::STATUS::ASSERT as u8 {
-        error!("/send-take2/{workflow_id}: workflow {workflow_id} not currently at assert stage");
-        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at assert stage"))
-    };
-    let _peg_in_txid = match user_data.pegin {
-        Some(txid) => txid,
-        _ => {
-            error!("/send-take2/{workflow_id}: workflow {workflow_id} missing pegin_txid");
-            return HttpResponse::InternalServerError().body("workflow {workflow_id} missing pegin_txid".to_string())
-        }
-    };
-    let _assert_txid = match user_data.assert {
-        Some(txid) => txid,
-        _ => {
-            error!("/send-take2/{workflow_id}: workflow {workflow_id} missing assert_txid");
-            return HttpResponse::InternalServerError().body("workflow {workflow_id} missing assert_txid".to_string())
-        }
+    if let Err(e) = steps::check_stage("take-2", user_data.status) {
+        error!("/send-take2/{workflow_id}: workflow {workflow_id} {e}");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} {e}"))
     };
 
-    async fn long_task(workflow_id: i32) -> Result<Txid, String> {
+    async fn long_task(workflow_id: i32, rpc: RpcClient) -> Result<transactions::Broadcast, String> {
         let db = match sql::open_db() {
             Ok(v) => v,
             Err(e) => return Err(e.to_string()),
@@ -1593,29 +4228,8 @@ async fn send_take_2(path: web::Path<i32>) -> impl Responder {
             },
             Err(e) => return Err(e.to_string()),
         };
-        let peg_in_txid = match user_data.pegin {
-            Some(txid) => txid,
-            _ => return Err("workflow {workflow_id} missing pegin_txid".to_string()),
-        };
-        let assert_txid = match user_data.assert {
-            Some(txid) => txid,
-            _ => return Err("workflow {workflow_id} missing assert_txid".to_string()),
-        };
-    
-        let rpc = match utils::new_rpc_client().await {
-            Ok(v) => v,
-            Err(e) => return Err(e.to_string()),
-        };
-        let connector_c_addr = Some(transactions::get_precomputed_connector_c_address());
-        let connector_c_tapscripts = transactions::borrow_assert_tapscripts();
-        let take_2_txid = match transactions::take_2(&rpc, peg_in_txid, assert_txid, connector_c_tapscripts, connector_c_addr, user_address).await {
-            Ok(v) => v,
-            Err(e) => return Err(e.to_string()),
-        };
-    
-        user_data.status = sql::STATUS::TAKE2 as u8;
-        user_data.take_2 = Some(take_2_txid);
-    
+        let res = steps::take_2(rpc, &mut user_data, user_address).await?;
+
         if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
             return Err(e.to_string())
         };
@@ -1624,21 +4238,19 @@ async fn send_take_2(path: web::Path<i32>) -> impl Responder {
             return Err(e.to_string())
         };
 
-        Ok(take_2_txid)
+        Ok(res)
     }
 
-    let task = tokio::spawn(async move {
-        long_task(workflow_id).await
+    let rpc = rpc_pool.get();
+    let task = workers::spawn(async move {
+        long_task(workflow_id, rpc).await
     });
 
     match task.await {
         Ok(res) => match res {
-            Ok(take_2_txid) => {
-                let body = serde_json::to_string_pretty(&ResponseStruct{take_2_txid}).unwrap();
+            Ok(res) => {
                 info!("/send-take2/{workflow_id}: ok");
-                HttpResponse::Ok()
-                    .content_type(ContentType::json())
-                    .body(body)
+                broadcast_response(res, &ResponseStruct{take_2_txid: res.txid(), scheduled_height: res.scheduled_height(), fee_info: res.fee_info()})
             },
             Err(e) => {
                 error!("/send-take2/{workflow_id}: fail to prepare & send take2: {}",e);
@@ -1653,14 +4265,19 @@ async fn send_take_2(path: web::Path<i32>) -> impl Responder {
 }
 
 #[post("/send-disprove/{workflow_id}")]
-async fn send_disprove(path: web::Path<i32>) -> impl Responder {
+async fn send_disprove(req: HttpRequest, path: web::Path<i32>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
     #[derive(Serialize)]
     struct ResponseStruct {
         disprove_txid: Txid,
+        scheduled_height: Option<u64>,
+        fee_info: transactions::FeeInfo,
     }
 
     let workflow_id = path.into_inner();
     info!("new REQUEST: /send-disprove/{workflow_id}");
+    if let Err(resp) = check_statics_ready(&format!("/send-disprove/{workflow_id}")) {
+        return resp
+    };
     let db = match sql::open_db() {
         Ok(v) => v,
         Err(e) => { 
@@ -1669,6 +4286,10 @@ async fn send_disprove(path: web::Path<i32>) -> impl Responder {
         }
     };
 
+    if let Err(resp) = check_workflow_auth(&format!("/send-disprove/{workflow_id}"), &db, &req, workflow_id) {
+        return resp
+    };
+
     match sql::is_workflow_locked(&db, workflow_id) {
         Ok(v) => {
             if v {
@@ -1702,19 +4323,12 @@ async fn send_disprove(path: web::Path<i32>) -> impl Responder {
         }
     };
 
-    if user_data.status != sql::STATUS::ASSERT as u8 {
-        error!("/send-disprove/{workflow_id}: workflow {workflow_id} not currently at assert stage");
-        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at assert stage"))
-    };
-    let _assert_txid = match user_data.assert {
-        Some(txid) => txid,
-        _ => {
-            error!("/send-disprove/{workflow_id}: workflow {workflow_id} missing assert_txid");
-            return HttpResponse::InternalServerError().body("workflow {workflow_id} missing assert_txid".to_string())
-        }
+    if let Err(e) = steps::check_stage("disprove", user_data.status) {
+        error!("/send-disprove/{workflow_id}: workflow {workflow_id} {e}");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} {e}"))
     };
 
-    async fn long_task(workflow_id: i32) -> Result<Txid, String> {
+    async fn long_task(workflow_id: i32, rpc: RpcClient) -> Result<transactions::Broadcast, String> {
         let db = match sql::open_db() {
             Ok(v) => v,
             Err(e) => return Err(e.to_string())
@@ -1727,29 +4341,8 @@ async fn send_disprove(path: web::Path<i32>) -> impl Responder {
             },
             Err(e) => return Err(e.to_string())
         };
-        let assert_txid = match user_data.assert {
-            Some(txid) => txid,
-            _ => return Err("workflow {workflow_id} missing assert_txid".to_string()),
-        };
-        let rpc = match utils::new_rpc_client().await {
-            Ok(v) => v,
-            Err(e) => return Err(e.to_string())
-        };
-        let connector_c_addr = Some(transactions::get_precomputed_connector_c_address());
-        let connector_c_tapscripts = transactions::borrow_assert_tapscripts();
-        let fake_index = match user_data.fake_index {
-            Some(i) => i as usize,
-            _ => return Err("can not disprove valid assert".to_string()),
-        };
-        let disprove_txid = match transactions::disprove(&rpc, assert_txid, connector_c_tapscripts, connector_c_addr, Some(fake_index)).await {
-            Ok(v) => v,
-            Err(e) => return Err(e.to_string())
-        };
-    
-        user_data.status = sql::STATUS::DISPROVE as u8;
-        user_data.disprove = Some(disprove_txid);
-    
-        
+        let res = steps::disprove(rpc, &mut user_data).await?;
+
         if let Err(e) = sql::update_user_data(&db, workflow_id, &user_data) {
             return Err(e.to_string())
         };
@@ -1758,21 +4351,19 @@ async fn send_disprove(path: web::Path<i32>) -> impl Responder {
             return Err(e.to_string())
         };
 
-        Ok(disprove_txid)
+        Ok(res)
     }
 
-    let task = tokio::spawn(async move {
-        long_task(workflow_id).await
+    let rpc = rpc_pool.get();
+    let task = workers::spawn(async move {
+        long_task(workflow_id, rpc).await
     });
 
     match task.await {
         Ok(res) => match res {
-            Ok(disprove_txid) => {
-                let body = serde_json::to_string_pretty(&ResponseStruct{disprove_txid}).unwrap();
+            Ok(res) => {
                 info!("/send-disprove/{workflow_id}: ok");
-                HttpResponse::Ok()
-                    .content_type(ContentType::json())
-                    .body(body)
+                broadcast_response(res, &ResponseStruct{disprove_txid: res.txid(), scheduled_height: res.scheduled_height(), fee_info: res.fee_info()})
             },
             Err(e) => {
                 error!("/send-disprove/{workflow_id}: fail to prepare & send disprove: {}",e);
@@ -1785,3 +4376,187 @@ async fn send_disprove(path: web::Path<i32>) -> impl Responder {
         }
     }
 }
+
+#[post("/send-disprove-assert/{assert_txid}")]
+async fn send_disprove_assert(req: HttpRequest, path: web::Path<String>, rpc_pool: web::Data<RpcPool>) -> impl Responder {
+    #[derive(Serialize)]
+    struct ResponseStruct {
+        workflow_id: i32,
+        disprove_txid: Txid,
+        scheduled_height: Option<u64>,
+        fee_info: transactions::FeeInfo,
+    }
+
+    let assert_txid = path.into_inner();
+    info!("new REQUEST: /send-disprove-assert/{assert_txid}");
+    if let Err(resp) = check_statics_ready(&format!("/send-disprove-assert/{assert_txid}")) {
+        return resp
+    };
+    let assert_txid = match utils::txid_from_str(&assert_txid) {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/send-disprove-assert/{assert_txid}: fail to decode txid: {}", e);
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+    };
+
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => { 
+            error!("/send-disprove-assert/{assert_txid}: fail to connect db: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    // only the recorded assert of one of our workflows, evaluating an assert takes minutes
+    let workflow_id = match sql::get_workflow_id_by_txid(&db, "assert", &assert_txid) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            error!("/send-disprove-assert/{assert_txid}: not the assert tx of a known workflow");
+            return HttpResponse::BadRequest().body(format!("{assert_txid} is not the assert tx of a known workflow"))
+        },
+        Err(e) => {
+            error!("/send-disprove-assert/{assert_txid}: fail to search workflow: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if !is_admin(&req) {
+        if let Err(resp) = check_workflow_auth(&format!("/send-disprove-assert/{assert_txid}"), &db, &req, workflow_id) {
+            return resp
+        };
+    };
+
+    let mut user_data = match sql::get_user_data(&db, workflow_id) {
+        Ok(Some(data)) => data,
+        Ok(None) => {
+            error!("/send-disprove-assert/{assert_txid}: workflow {workflow_id} does not exisit");
+            return HttpResponse::BadRequest().body(format!("workflow {workflow_id} does not exisit"))
+        },
+        Err(e) => {
+            error!("/send-disprove-assert/{assert_txid}: fail to get user data: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if user_data.status != sql::STATUS::ASSERT as u8 {
+        error!("/send-disprove-assert/{assert_txid}: workflow {workflow_id} not currently at assert stage");
+        return HttpResponse::BadRequest().body(format!("workflow {workflow_id} not currently at assert stage"))
+    };
+
+    let kick_off_1_txid = match user_data.kickoff_1 {
+        Some(txid) => txid,
+        _ => {
+            error!("/send-disprove-assert/{assert_txid}: workflow {workflow_id} missing kickoff_1_txid");
+            return HttpResponse::InternalServerError().body(format!("workflow {workflow_id} missing kickoff_1_txid"))
+        }
+    };
+
+    match sql::is_workflow_locked(&db, workflow_id) {
+        Ok(v) => {
+            if v {
+                error!("/send-disprove-assert/{assert_txid}: workflow:{workflow_id} is processing a task, please wait");
+                return HttpResponse::Locked().body(format!("workflow:{workflow_id} is processing a task, please wait"))
+            };
+        },
+        Err(e) => {
+            error!("/send-disprove-assert/{assert_txid}: fail to get workflow lock: {}", e);
+            return HttpResponse::InternalServerError().body(e.to_string())
+        }
+    };
+
+    if let Err(e) = sql::lock_workflow(&db, workflow_id) {
+        error!("/send-disprove-assert/{assert_txid}: fail to lock workflow: {}", e);
+        return HttpResponse::InternalServerError().body(e.to_string());
+    };
+
+    // generating connector_c & evaluating the assertions share the bounded validation queue
+    let _timer = metrics::StepTimer::new("disprove");
+    let key_set = transactions::get_workflow_key_set(kick_off_1_txid, &user_data);
+    let rpc = rpc_pool.get();
+    let task_rpc = rpc.clone();
+    let task = match workers::spawn_validation(move || {
+        let connector_c = transactions::get_cached_connector_c(&key_set);
+        let connector_c_addr = Some(connector_c.address.clone());
+        transactions::disprove_prepare(&task_rpc, assert_txid, &connector_c.tapscripts, connector_c_addr, &connector_c.spend_info, &key_set, None)
+    }) {
+        Ok(v) => v,
+        Err(e) => {
+            if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+                error!("/send-disprove-assert/{assert_txid}: fail to unlock workflow: {}", e);
+            };
+            error!("/send-disprove-assert/{assert_txid}: {}", e);
+            return validation_queue_full(e)
+        }
+    };
+
+    let res = match task.await {
+        Ok(Ok(tx)) => workers::run(async move { transactions::broadcast_after_timelock(&rpc, &tx, "disprove").await }).await,
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(format!("fail while run disprove task: {}", e)),
+    };
+    let res = match res {
+        Ok(res) => match steps::record_broadcast(&mut user_data, "disprove", res).and_then(|_| sql::update_user_data(&db, workflow_id, &user_data)) {
+            Ok(_) => Ok(res),
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
+    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+        error!("/send-disprove-assert/{assert_txid}: fail to unlock workflow: {}", e);
+    };
+
+    match res {
+        Ok(res) => {
+            info!("/send-disprove-assert/{assert_txid}: ok");
+            broadcast_response(res, &ResponseStruct{workflow_id, disprove_txid: res.txid(), scheduled_height: res.scheduled_height(), fee_info: res.fee_info()})
+        },
+        Err(e) => {
+            error!("/send-disprove-assert/{assert_txid}: fail to prepare & send disprove: {}",e);
+            HttpResponse::InternalServerError().body(e.to_string())
+        }
+    }
+}
+
+#[get("/healthz")]
+async fn healthz() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(serde_json::json!({"status": "ok"}).to_string())
+}
+
+// 503 until statics, setup, db & bitcoind are all usable
+#[get("/readyz")]
+async fn readyz() -> impl Responder {
+    let readiness = health::get_readiness().await;
+    let body = serde_json::to_string_pretty(&readiness).unwrap();
+    if readiness.ready {
+        HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .body(body)
+    } else {
+        warn!("/readyz: not ready: {body}");
+        HttpResponse::ServiceUnavailable()
+            .content_type(ContentType::json())
+            .body(body)
+    }
+}
+
+#[get("/metrics")]
+async fn get_metrics() -> impl Responder {
+    let mut workflows = vec![];
+    match sql::open_db().and_then(|db| sql::count_workflows_by_status(&db)) {
+        Ok(v) => {
+            for (status, count) in v {
+                workflows.push((metrics::gauge_labels(&[("status", &status.to_string())]), count as f64));
+            }
+        },
+        Err(e) => error!("/metrics: fail to count workflows: {}", e),
+    };
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics::render(&[
+            (metrics::WORKFLOWS, workflows),
+            (metrics::VALIDATION_PENDING, vec![(String::new(), workers::validation_pending() as f64)]),
+        ]))
+}
@@ -0,0 +1,285 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use bitcoin::{
+    absolute, Address, Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+    consensus::deserialize, opcodes, PublicKey, TapSighashType, EcdsaSighashType,
+    secp256k1::{schnorr, Message, Secp256k1, XOnlyPublicKey},
+    sighash::{Prevouts, SighashCache},
+    sign_message::{signed_msg_hash, MessageSignature},
+};
+use bitcoin_hashes::{hash160, sha256, Hash, HashEngine};
+use rusqlite::Connection;
+use crate::{config, faucet, sql, utils};
+
+const BIP322_TAG: &[u8] = b"BIP0322-signed-message";
+
+pub fn challenge_message(addr: &Address, nonce: &str) -> String {
+    format!("bitvm-demo login\naddress: {addr}\nnonce: {nonce}")
+}
+
+// issue a one-time nonce for `addr`, returns (nonce, message to sign, expires_at)
+pub fn new_challenge(db: &Connection, addr: &Address) -> Result<(String, String, u64), String> {
+    let nonce = utils::random_hex(16);
+    let expires_at = faucet::now() + config::AUTH_CHALLENGE_TTL;
+    sql::add_auth_challenge(db, &nonce, addr, expires_at)?;
+    let message = challenge_message(addr, &nonce);
+    Ok((nonce, message, expires_at))
+}
+
+// consume the nonce & exchange a valid signature over its message for a session token, returns (token, expires_at)
+pub fn new_session(db: &Connection, addr: &Address, nonce: &str, signature: &str) -> Result<(String, u64), String> {
+    let now = faucet::now();
+    match sql::take_auth_challenge(db, nonce)? {
+        Some((challenge_addr, expires_at)) => {
+            if challenge_addr != addr.to_string() {
+                return Err("nonce was issued for another address".to_string())
+            };
+            if expires_at < now {
+                return Err("nonce expired".to_string())
+            };
+        },
+        _ => return Err("unknown nonce".to_string()),
+    };
+    if !verify_message(addr, &challenge_message(addr, nonce), signature)? {
+        return Err("invalid signature".to_string())
+    };
+    let token = utils::random_hex(32);
+    let expires_at = now + config::AUTH_SESSION_TTL;
+    sql::add_auth_session(db, &token, addr, expires_at)?;
+    Ok((token, expires_at))
+}
+
+// address the session token was issued for, None if unknown or expired
+pub fn get_session_address(db: &Connection, token: &str) -> Result<Option<String>, String> {
+    match sql::get_auth_session(db, token)? {
+        Some((addr, expires_at)) if expires_at >= faucet::now() => Ok(Some(addr)),
+        _ => Ok(None),
+    }
+}
+
+// compares secrets without an early exit on the first differing byte
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false
+    };
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// BIP-322 simple signature (p2wpkh/p2tr key path) or legacy signmessage signature (p2pkh), base64 encoded
+pub fn verify_message(addr: &Address, message: &str, signature: &str) -> Result<bool, String> {
+    let sig_bytes = match BASE64.decode(signature.trim()) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to decode signature: {}", e)),
+    };
+    let script_pubkey = addr.script_pubkey();
+    if script_pubkey.is_p2pkh() {
+        return verify_legacy(addr, message, &sig_bytes)
+    };
+    let witness: Witness = match deserialize(&sig_bytes) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to decode bip322 witness: {}", e)),
+    };
+    let to_spend = bip322_to_spend(&script_pubkey, message);
+    let to_sign = bip322_to_sign(&to_spend, witness.clone());
+    if script_pubkey.is_p2wpkh() {
+        verify_p2wpkh(&script_pubkey, &to_sign, &witness)
+    } else if script_pubkey.is_p2tr() {
+        verify_p2tr(&to_spend, &to_sign, &witness)
+    } else {
+        Err("only p2pkh, p2wpkh & p2tr addresses are supported".to_string())
+    }
+}
+
+fn verify_legacy(addr: &Address, message: &str, sig_bytes: &[u8]) -> Result<bool, String> {
+    let secp = Secp256k1::verification_only();
+    let signature = match MessageSignature::from_slice(sig_bytes) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to decode message signature: {}", e)),
+    };
+    match signature.is_signed_by_address(&secp, addr, signed_msg_hash(message)) {
+        Ok(v) => Ok(v),
+        Err(e) => Err(format!("fail to verify message signature: {}", e)),
+    }
+}
+
+fn bip322_message_hash(message: &str) -> [u8; 32] {
+    let tag = sha256::Hash::hash(BIP322_TAG);
+    let mut engine = sha256::Hash::engine();
+    engine.input(tag.as_ref());
+    engine.input(tag.as_ref());
+    engine.input(message.as_bytes());
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+pub fn bip322_to_spend(script_pubkey: &ScriptBuf, message: &str) -> Transaction {
+    let script_sig = ScriptBuf::builder()
+        .push_opcode(opcodes::OP_0)
+        .push_slice(bip322_message_hash(message))
+        .into_script();
+    Transaction {
+        version: bitcoin::transaction::Version(0),
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint { txid: Txid::all_zeros(), vout: 0xFFFFFFFF },
+            script_sig,
+            sequence: Sequence::ZERO,
+            witness: Witness::default(),
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: script_pubkey.clone(),
+        }],
+    }
+}
+
+pub fn bip322_to_sign(to_spend: &Transaction, witness: Witness) -> Transaction {
+    Transaction {
+        version: bitcoin::transaction::Version(0),
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint { txid: to_spend.compute_txid(), vout: 0 },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ZERO,
+            witness,
+        }],
+        output: vec![TxOut {
+            value: Amount::ZERO,
+            script_pubkey: ScriptBuf::from_bytes(vec![opcodes::all::OP_RETURN.to_u8()]),
+        }],
+    }
+}
+
+fn verify_p2wpkh(script_pubkey: &ScriptBuf, to_sign: &Transaction, witness: &Witness) -> Result<bool, String> {
+    if witness.len() != 2 {
+        return Err("p2wpkh witness must hold a signature & a public key".to_string())
+    };
+    let (sig, pubkey) = (witness.nth(0).unwrap(), witness.nth(1).unwrap());
+    if hash160::Hash::hash(pubkey).to_byte_array() != script_pubkey.as_bytes()[2..22] {
+        return Ok(false)
+    };
+    let pubkey = match PublicKey::from_slice(pubkey) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to decode public key: {}", e)),
+    };
+    let (sig, sighash_type) = match sig.split_last() {
+        Some((sighash_type, sig)) => (sig, *sighash_type),
+        _ => return Err("empty signature".to_string()),
+    };
+    let sighash_type = match EcdsaSighashType::from_standard(sighash_type as u32) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("invalid sighash type: {}", e)),
+    };
+    let signature = match bitcoin::secp256k1::ecdsa::Signature::from_der(sig) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to decode signature: {}", e)),
+    };
+    let sighash = match SighashCache::new(to_sign).p2wpkh_signature_hash(0, script_pubkey, Amount::ZERO, sighash_type) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to compute sighash: {}", e)),
+    };
+    let secp = Secp256k1::verification_only();
+    Ok(secp.verify_ecdsa(&Message::from_digest(sighash.to_byte_array()), &signature, &pubkey.inner).is_ok())
+}
+
+fn verify_p2tr(to_spend: &Transaction, to_sign: &Transaction, witness: &Witness) -> Result<bool, String> {
+    if witness.len() != 1 {
+        return Err("only taproot key path signatures are supported".to_string())
+    };
+    let sig = witness.nth(0).unwrap();
+    let sighash_type = match sig.len() {
+        64 => TapSighashType::Default,
+        65 => match TapSighashType::from_consensus_u8(sig[64]) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("invalid sighash type: {}", e)),
+        },
+        _ => return Err("invalid schnorr signature length".to_string()),
+    };
+    let signature = match schnorr::Signature::from_slice(&sig[..64]) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to decode signature: {}", e)),
+    };
+    let output_key = match XOnlyPublicKey::from_slice(&to_spend.output[0].script_pubkey.as_bytes()[2..34]) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to decode output key: {}", e)),
+    };
+    let prevouts = [to_spend.output[0].clone()];
+    let sighash = match SighashCache::new(to_sign).taproot_key_spend_signature_hash(0, &Prevouts::All(&prevouts), sighash_type) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to compute sighash: {}", e)),
+    };
+    let secp = Secp256k1::verification_only();
+    Ok(secp.verify_schnorr(&signature, &Message::from_digest(sighash.to_byte_array()), &output_key).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{consensus::serialize, hex::FromHex, key::{Keypair, TapTweak}, Network};
+    use std::str::FromStr;
+
+    // test vectors of BIP-322
+    const VECTOR_ADDRESS: &str = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l";
+    const VECTOR_SIG_EMPTY: &str = "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+    const VECTOR_SIG_HELLO: &str = "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=";
+
+    fn vector_address() -> Address {
+        Address::from_str(VECTOR_ADDRESS).unwrap().assume_checked()
+    }
+
+    #[test]
+    fn bip322_vectors() {
+        assert_eq!(
+            bip322_message_hash(""),
+            <[u8; 32]>::from_hex("c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1").unwrap(),
+        );
+        assert_eq!(
+            bip322_message_hash("Hello World"),
+            <[u8; 32]>::from_hex("f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a").unwrap(),
+        );
+
+        let script_pubkey = vector_address().script_pubkey();
+        let to_spend = bip322_to_spend(&script_pubkey, "Hello World");
+        assert_eq!(to_spend.compute_txid().to_string(), "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b");
+        let to_sign = bip322_to_sign(&to_spend, Witness::default());
+        assert_eq!(to_sign.compute_txid().to_string(), "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf");
+
+        let addr = vector_address();
+        assert!(verify_message(&addr, "", VECTOR_SIG_EMPTY).unwrap());
+        assert!(verify_message(&addr, "Hello World", VECTOR_SIG_HELLO).unwrap());
+        // swapped messages
+        assert!(!verify_message(&addr, "Hello World", VECTOR_SIG_EMPTY).unwrap());
+        assert!(!verify_message(&addr, "", VECTOR_SIG_HELLO).unwrap());
+    }
+
+    #[test]
+    fn bip322_p2tr_key_path() {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::from_seckey_slice(&secp, &[0x11; 32]).unwrap();
+        let (internal_key, _) = keypair.x_only_public_key();
+        let addr = Address::p2tr(&secp, internal_key, None, Network::Regtest);
+        let message = challenge_message(&addr, "00");
+
+        let to_spend = bip322_to_spend(&addr.script_pubkey(), message.as_str());
+        let to_sign = bip322_to_sign(&to_spend, Witness::default());
+        let prevouts = [to_spend.output[0].clone()];
+        let sighash = SighashCache::new(&to_sign)
+            .taproot_key_spend_signature_hash(0, &Prevouts::All(&prevouts), TapSighashType::Default)
+            .unwrap();
+        let tweaked = keypair.tap_tweak(&secp, None).to_inner();
+        let sig = secp.sign_schnorr_no_aux_rand(&Message::from_digest(sighash.to_byte_array()), &tweaked);
+        let signature = BASE64.encode(serialize(&Witness::from_slice(&[sig.serialize().to_vec()])));
+
+        assert!(verify_message(&addr, &message, &signature).unwrap());
+        assert!(!verify_message(&addr, &challenge_message(&addr, "01"), &signature).unwrap());
+        // a script path witness is refused
+        let two_items = BASE64.encode(serialize(&Witness::from_slice(&[sig.serialize().to_vec(), vec![0x51]])));
+        assert!(verify_message(&addr, &message, &two_items).is_err());
+    }
+
+    #[test]
+    fn admin_token_compare() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(constant_time_eq(b"", b""));
+    }
+}
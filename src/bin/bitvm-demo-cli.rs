@@ -0,0 +1,335 @@
+// drives one workflow end-to-end through the api: request-btc, peg-in & kickoff-1 signed locally
+// with the user key, then kickoff-2, challenge, assert & take-2 or disprove
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use bitcoin::{
+    absolute, Address, Amount, OutPoint, PrivateKey, PublicKey, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+    consensus::{encode::serialize_hex, serialize}, opcodes, EcdsaSighashType,
+    secp256k1::{Message, Secp256k1},
+    sighash::SighashCache,
+};
+use bitcoin_hashes::{hash160, Hash};
+use bitcoincore_rpc::{Auth, Client, RpcApi};
+use clap::{Arg, ArgAction, Command};
+use reqwest::{header, Method, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use bitvm_demo_api::{auth, config, faucet};
+
+const RETRY_INTERVAL: u64 = 10; // in seconds, when a 503/423 comes without Retry-After
+const MAX_RETRIES: u32 = 360;
+
+// the last step to run before stopping, in protocol order
+const STEPS: [&str; 6] = ["request-btc", "peg-in", "kickoff-1", "kickoff-2", "challenge", "assert"];
+
+#[derive(Deserialize)]
+struct UnsignedInput {
+    txid: Txid,
+    vout: u32,
+    script_pubkey: String,
+    value: Amount,
+}
+
+#[derive(Deserialize)]
+struct UnsignedOutput {
+    testnet_address: String,
+    regtest_address: String,
+    value: Amount,
+}
+
+// /get-unsigned-pegin-tx & /get-unsigned-kickoff1-tx
+#[derive(Deserialize)]
+struct UnsignedTx {
+    input: UnsignedInput,
+    outputs: Vec<UnsignedOutput>,
+}
+
+struct Demo {
+    http: reqwest::Client,
+    api: String,
+    rpc: Client,
+    key: PrivateKey,
+    public_key: PublicKey,
+    script_pubkey: ScriptBuf,
+    address: Address,
+    token: Option<String>,
+}
+
+impl Demo {
+    fn new(api: String, rpc_url: &str, wif: &str) -> Result<Self, String> {
+        let key = match PrivateKey::from_wif(wif) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("fail to decode key: {}", e)),
+        };
+        let public_key = key.public_key(&Secp256k1::new());
+        if !public_key.compressed {
+            return Err("the key must be compressed, funds are paid to its p2wpkh address".to_string())
+        };
+        let script_pubkey = ScriptBuf::builder()
+            .push_opcode(opcodes::OP_0)
+            .push_slice(hash160::Hash::hash(&public_key.to_bytes()).to_byte_array())
+            .into_script();
+        let address = match Address::from_script(&script_pubkey, config::network()) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("fail to derive address: {}", e)),
+        };
+        let rpc = match Client::new(rpc_url, Auth::UserPass(config::RPCUSER.to_string(), config::RPCPASSWORD.to_string())) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("fail to connect to node: {}", e)),
+        };
+        Ok(Demo { http: reqwest::Client::new(), api, rpc, key, public_key, script_pubkey, address, token: None })
+    }
+
+    // calls the api, waiting out 503 (setup loading, validation queue full) & 423 (workflow busy)
+    async fn call(&self, method: Method, path: &str, body: Option<Value>, headers: &[(&str, String)]) -> Result<Value, String> {
+        for _ in 0..MAX_RETRIES {
+            let mut req = self.http.request(method.clone(), format!("{}{path}", self.api));
+            if let Some(token) = &self.token {
+                req = req.bearer_auth(token);
+            };
+            for (name, value) in headers {
+                req = req.header(*name, value);
+            }
+            if let Some(body) = &body {
+                req = req.json(body);
+            };
+            let resp = match req.send().await {
+                Ok(v) => v,
+                Err(e) => return Err(format!("{path}: fail to send request: {}", e)),
+            };
+            let status = resp.status();
+            if status == StatusCode::SERVICE_UNAVAILABLE || status == StatusCode::LOCKED {
+                let retry_after = resp.headers().get(header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .unwrap_or(RETRY_INTERVAL);
+                eprintln!("{path}: {status}, retry in {retry_after}s");
+                tokio::time::sleep(Duration::from_secs(retry_after)).await;
+                continue
+            };
+            let text = match resp.text().await {
+                Ok(v) => v,
+                Err(e) => return Err(format!("{path}: fail to read response: {}", e)),
+            };
+            if !status.is_success() {
+                return Err(format!("{path}: {status}: {text}"))
+            };
+            return match serde_json::from_str(&text) {
+                Ok(v) => Ok(v),
+                Err(e) => Err(format!("{path}: fail to decode response: {}", e)),
+            }
+        }
+        Err(format!("{path}: still unavailable after {MAX_RETRIES} retries"))
+    }
+
+    async fn post_txid(&self, path: &str, field: &str) -> Result<Txid, String> {
+        let resp = self.call(Method::POST, path, None, &[]).await?;
+        txid_field(&resp, field)
+    }
+
+    // exchanges a BIP-322 signature of the login challenge for a session token
+    async fn login(&mut self) -> Result<(), String> {
+        let challenge = self.call(Method::GET, &format!("/auth/challenge?address={}", self.address), None, &[]).await?;
+        let (nonce, message) = match (challenge["nonce"].as_str(), challenge["message"].as_str()) {
+            (Some(nonce), Some(message)) => (nonce.to_string(), message.to_string()),
+            _ => return Err("/auth/challenge: missing nonce or message".to_string()),
+        };
+        let to_spend = auth::bip322_to_spend(&self.script_pubkey, &message);
+        let to_sign = auth::bip322_to_sign(&to_spend, Witness::default());
+        let witness = self.sign_p2wpkh(&to_sign, Amount::ZERO)?;
+        let signature = BASE64.encode(serialize(&witness));
+        let body = json!({"address": self.address.to_string(), "nonce": nonce, "signature": signature});
+        let session = self.call(Method::POST, "/auth/session", Some(body), &[]).await?;
+        match session["token"].as_str() {
+            Some(token) => self.token = Some(token.to_string()),
+            _ => return Err("/auth/session: missing token".to_string()),
+        };
+        Ok(())
+    }
+
+    async fn request_btc(&self) -> Result<(i32, Txid), String> {
+        let headers: Vec<(&str, String)> = faucet::solve_pow(&self.address).into_iter()
+            .map(|nonce| ("X-Faucet-Pow", nonce))
+            .collect();
+        let resp = self.call(Method::POST, &format!("/request-btc/{}", self.address), None, &headers).await?;
+        let workflow_id = match resp["workflow_id"].as_i64() {
+            Some(v) => v as i32,
+            _ => return Err("/request-btc: missing workflow_id".to_string()),
+        };
+        Ok((workflow_id, txid_field(&resp, "faucet_txid")?))
+    }
+
+    // witness of input 0 of `tx`, spending `value` from the user's p2wpkh address
+    fn sign_p2wpkh(&self, tx: &Transaction, value: Amount) -> Result<Witness, String> {
+        let sighash = match SighashCache::new(tx).p2wpkh_signature_hash(0, &self.script_pubkey, value, EcdsaSighashType::All) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("fail to compute sighash: {}", e)),
+        };
+        let signature = Secp256k1::new().sign_ecdsa(&Message::from_digest(sighash.to_byte_array()), &self.key.inner);
+        let signature = bitcoin::ecdsa::Signature {
+            signature,
+            sighash_type: EcdsaSighashType::All,
+        };
+        let mut witness = Witness::new();
+        witness.push_ecdsa_signature(&signature);
+        witness.push(self.public_key.to_bytes());
+        Ok(witness)
+    }
+
+    // rebuilds the tx the api prepared (see transactions::peg_in_prepare), signs & broadcasts it
+    async fn sign_and_broadcast(&self, path: &str) -> Result<Txid, String> {
+        let unsigned: UnsignedTx = match serde_json::from_value(self.call(Method::GET, path, None, &[]).await?) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("{path}: fail to decode unsigned tx: {}", e)),
+        };
+        if unsigned.input.script_pubkey != hex::encode(self.script_pubkey.as_bytes()) {
+            return Err(format!("{path}: input is not locked to {}", self.address))
+        };
+        let mut output = vec![];
+        for o in unsigned.outputs {
+            let addr = if config::network() == bitcoin::Network::Regtest { o.regtest_address } else { o.testnet_address };
+            let addr = match Address::from_str(&addr) {
+                Ok(v) => v.assume_checked(),
+                Err(e) => return Err(format!("{path}: fail to decode address: {}", e)),
+            };
+            output.push(TxOut { value: o.value, script_pubkey: addr.script_pubkey() });
+        }
+        let mut tx = Transaction {
+            version: bitcoin::transaction::Version(2),
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint { txid: unsigned.input.txid, vout: unsigned.input.vout },
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::default(),
+            }],
+            output,
+        };
+        tx.input[0].witness = self.sign_p2wpkh(&tx, unsigned.input.value)?;
+        if let Err(e) = self.rpc.send_raw_transaction(serialize_hex(&tx)) {
+            return Err(format!("{path}: fail to broadcast: {}", e))
+        };
+        Ok(tx.compute_txid())
+    }
+}
+
+fn txid_field(resp: &Value, field: &str) -> Result<Txid, String> {
+    match resp[field].as_str().map(Txid::from_str) {
+        Some(Ok(v)) => Ok(v),
+        _ => Err(format!("missing or invalid {field} in {resp}")),
+    }
+}
+
+fn report(step: &str, txid: Txid, start: Instant) {
+    println!("{step:<12} {txid} {:>8.1}s", start.elapsed().as_secs_f64());
+}
+
+async fn run(demo: &mut Demo, stop_after: &str, disprove: bool, fake_index: u32) -> Result<(), String> {
+    let done = |step: &str| step == stop_after;
+    println!("user address: {}", demo.address);
+    let start = Instant::now();
+    demo.login().await?;
+    println!("{:<12} {:<64} {:>8.1}s", "login", "-", start.elapsed().as_secs_f64());
+
+    let start = Instant::now();
+    let (workflow_id, faucet_txid) = demo.request_btc().await?;
+    println!("workflow id: {workflow_id}");
+    report("request-btc", faucet_txid, start);
+    if done("request-btc") { return Ok(()) };
+
+    let start = Instant::now();
+    let pegin_txid = demo.sign_and_broadcast(&format!("/get-unsigned-pegin-tx/{workflow_id}")).await?;
+    demo.call(Method::POST, &format!("/post-pegin-txid/{workflow_id}/{pegin_txid}"), None, &[]).await?;
+    report("peg-in", pegin_txid, start);
+    if done("peg-in") { return Ok(()) };
+
+    let start = Instant::now();
+    let kickoff_1_txid = demo.sign_and_broadcast(&format!("/get-unsigned-kickoff1-tx/{workflow_id}")).await?;
+    report("kickoff-1", kickoff_1_txid, start);
+    if done("kickoff-1") { return Ok(()) };
+
+    let start = Instant::now();
+    let kickoff_2_txid = demo.post_txid(&format!("/send-kickoff2/{workflow_id}/{kickoff_1_txid}"), "kick_off_2_txid").await?;
+    report("kickoff-2", kickoff_2_txid, start);
+    if done("kickoff-2") { return Ok(()) };
+
+    let start = Instant::now();
+    let challenge_txid = demo.post_txid(&format!("/send-challenge/{workflow_id}"), "challenge_txid").await?;
+    report("challenge", challenge_txid, start);
+    if done("challenge") { return Ok(()) };
+
+    // a disprove needs a faulty assertion, the operator corrupts the one at `fake_index`
+    if disprove {
+        demo.call(Method::POST, &format!("/post-fake-index/{workflow_id}/{fake_index}"), None, &[]).await?;
+    };
+    let start = Instant::now();
+    let assert_txid = demo.post_txid(&format!("/send-assert/{workflow_id}"), "assert_txid").await?;
+    report("assert", assert_txid, start);
+    if done("assert") { return Ok(()) };
+
+    let start = Instant::now();
+    if disprove {
+        let disprove_txid = demo.post_txid(&format!("/send-disprove/{workflow_id}"), "disprove_txid").await?;
+        report("disprove", disprove_txid, start);
+    } else {
+        let take_2_txid = demo.post_txid(&format!("/send-take2/{workflow_id}"), "take_2_txid").await?;
+        report("take-2", take_2_txid, start);
+    };
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let matches = Command::new("bitvm-demo-cli")
+        .about("Runs a bitvm demo workflow end-to-end against the api")
+        .arg(Arg::new("key")
+            .long("key")
+            .required(true)
+            .help("WIF private key of the user, the faucet pays to its p2wpkh address"))
+        .arg(Arg::new("api")
+            .long("api")
+            .help("api base url [default: http://127.0.0.1:<BIND_PORT>]"))
+        .arg(Arg::new("rpc-url")
+            .long("rpc-url")
+            .default_value(config::RPC_URL)
+            .help("bitcoind rpc url, the user txs are broadcast there"))
+        .arg(Arg::new("disprove")
+            .long("disprove")
+            .action(ArgAction::SetTrue)
+            .help("fake an assertion & disprove it instead of taking the peg-in with take-2"))
+        .arg(Arg::new("fake-index")
+            .long("fake-index")
+            .value_parser(clap::value_parser!(u32))
+            .default_value("0")
+            .help("index of the faked assertion, with --disprove"))
+        .arg(Arg::new("stop-after")
+            .long("stop-after")
+            .value_parser(STEPS)
+            .help("stop once this step is done"))
+        .get_matches();
+
+    let api = match matches.get_one::<String>("api") {
+        Some(v) => v.trim_end_matches('/').to_string(),
+        _ => format!("http://127.0.0.1:{}", config::BIND_PORT),
+    };
+    let rpc_url = matches.get_one::<String>("rpc-url").unwrap();
+    let key = matches.get_one::<String>("key").unwrap();
+    let stop_after = matches.get_one::<String>("stop-after").map_or("", |v| v.as_str());
+    let disprove = matches.get_flag("disprove");
+    let fake_index = *matches.get_one::<u32>("fake-index").unwrap();
+
+    let mut demo = match Demo::new(api, rpc_url, key) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let start = Instant::now();
+    if let Err(e) = run(&mut demo, stop_after, disprove, fake_index).await {
+        eprintln!("ERROR: {}", e);
+        std::process::exit(1);
+    };
+    println!("{:<12} {:<64} {:>8.1}s", "total", "-", start.elapsed().as_secs_f64());
+}
@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use bitcoin::{Address, Amount, ScriptBuf, Transaction, Txid};
+use bitcoincore_rpc::Client;
+use once_cell::sync::Lazy;
+use rusqlite::Connection;
+use crate::{config, sql::{self, UserData, WorkflowRow}, transactions, utils::{self, TxCache}};
+
+// inputs & outputs matching no known script, e.g. wallet inputs, change, challenger funds
+pub const EXTERNAL: &str = "external";
+pub const UNIDENTIFIED: &str = "Unidentified";
+
+// connectors shared by every workflow, connector_5 has the same script as connector_0
+static CONNECTOR_SCRIPTS: Lazy<Vec<(ScriptBuf, &'static str)>> = Lazy::new(|| vec![
+    (transactions::get_precomputed_connector_0_address().script_pubkey(), "connector-0"),
+    (transactions::get_connector_1_address().script_pubkey(), "connector-1"),
+    (transactions::get_connector_2_address().script_pubkey(), "connector-2"),
+    (transactions::get_connector_3_address().script_pubkey(), "connector-3"),
+    (transactions::get_connector_a_address().script_pubkey(), "connector-a"),
+    (transactions::get_precomputed_connector_b_address().script_pubkey(), "connector-b"),
+    (transactions::get_precomputed_connector_4_address().script_pubkey(), "connector-4"),
+]);
+
+// the `tx_type` of /get-named-inputs-outputs
+pub fn tx_type_name(tx_type: u8) -> Option<&'static str> {
+    match tx_type {
+        2 => Some("Peg-In"),
+        3 => Some("Kickoff-1"),
+        4 => Some("Kickoff-2"),
+        5 => Some("Challenge"),
+        6 => Some("Take-1"),
+        7 => Some("Assert"),
+        8 => Some("Take-2"),
+        9 => Some("Disprove"),
+        _ => None,
+    }
+}
+
+// the txs recorded by a workflow, in protocol order
+pub fn workflow_txs(user_data: &UserData) -> Vec<(&'static str, Txid)> {
+    let mut txs = vec![("Faucet", user_data.faucet_1.map(|(txid, _)| txid))];
+    if user_data.faucet_2.map(|(txid, _)| txid) != user_data.faucet_1.map(|(txid, _)| txid) {
+        txs.push(("Faucet", user_data.faucet_2.map(|(txid, _)| txid)));
+    };
+    txs.extend([
+        ("Peg-In-Deposit", user_data.pegin_deposit),
+        ("Peg-In-Refund", user_data.pegin_refund),
+        ("Peg-In", user_data.pegin),
+        ("Peg-Out", user_data.peg_out),
+        ("Kickoff-1", user_data.kickoff_1),
+        ("Start-Time", user_data.start_time),
+        ("Start-Time-Timeout", user_data.start_time_timeout),
+        ("Kickoff-Timeout", user_data.kickoff_timeout),
+        ("Kickoff-2", user_data.kickoff_2),
+        ("Challenge", user_data.challenge),
+        ("Assert", user_data.assert),
+        ("Take-1", user_data.take_1),
+        ("Take-2", user_data.take_2),
+        ("Disprove", user_data.disprove),
+    ]);
+    txs.into_iter()
+        .filter_map(|(name, txid)| txid.map(|txid| (name, txid)))
+        .collect()
+}
+
+// known scripts of one classification: the shared connectors plus the workflow's own scripts
+struct ScriptBook {
+    roles: HashMap<ScriptBuf, String>,
+}
+
+impl ScriptBook {
+    fn new(workflow: Option<&WorkflowRow>) -> Self {
+        let mut roles: HashMap<ScriptBuf, String> = CONNECTOR_SCRIPTS.iter()
+            .map(|(script, role)| (script.clone(), role.to_string()))
+            .collect();
+        if let Some(row) = workflow {
+            let user_data = &row.data;
+            // connector_c & revealers are derived from the workflow's wots key,
+            // only matched once generated: a lookup must not start minutes of generation
+            if let Some(kick_off_1_txid) = user_data.kickoff_1 {
+                let key_set = transactions::get_workflow_key_set(kick_off_1_txid, user_data);
+                if let Some(addr) = transactions::peek_connector_c_address(&key_set) {
+                    roles.insert(addr.script_pubkey(), "connector-c".to_string());
+                };
+                for addr in transactions::peek_workflow_revealers_address(&key_set).unwrap_or_default() {
+                    roles.insert(addr.script_pubkey(), "revealer".to_string());
+                }
+            };
+            if let Ok(addr) = utils::address_from_str(&row.addr) {
+                roles.entry(addr.script_pubkey()).or_insert_with(|| "user".to_string());
+            };
+            if let Some(addr) = user_data.withdrawer_address.as_deref().and_then(|a| utils::address_from_str(a).ok()) {
+                roles.entry(addr.script_pubkey()).or_insert_with(|| "withdrawer".to_string());
+            };
+        };
+        ScriptBook { roles }
+    }
+
+    fn output_roles(&self, tx: &Transaction) -> Vec<String> {
+        output_roles(&self.roles, tx)
+    }
+}
+
+// roles of the outputs of `tx` by script, EXTERNAL if unknown
+fn output_roles(known: &HashMap<ScriptBuf, String>, tx: &Transaction) -> Vec<String> {
+    let mut roles: Vec<String> = tx.output.iter()
+        .map(|o| known.get(&o.script_pubkey).cloned().unwrap_or_else(|| EXTERNAL.to_string()))
+        .collect();
+    // connector_0 & connector_5 share the n-of-n script, connector_5 is the one paid next to connector_4 (assert)
+    if roles.iter().any(|r| r == "connector-4") {
+        for role in roles.iter_mut().filter(|r| *r == "connector-0") {
+            *role = "connector-5".to_string();
+        }
+    };
+    roles
+}
+
+// the workflow recording `tx`, else the one recording a tx it spends (e.g. a cpfp child)
+fn find_workflow(db: &Connection, tx: &Transaction) -> Result<Option<WorkflowRow>, String> {
+    let mut candidates = vec![tx.compute_txid()];
+    for input in tx.input.iter() {
+        if !candidates.contains(&input.previous_output.txid) {
+            candidates.push(input.previous_output.txid);
+        };
+    }
+    // one lookup for all candidates, newest workflow first
+    let rows = sql::find_workflows_by_txids(db, &candidates)?;
+    for txid in candidates {
+        if let Some(row) = rows.iter().find(|row| workflow_txs(&row.data).iter().any(|(_, t)| *t == txid)) {
+            return Ok(Some(row.clone()))
+        };
+    }
+    Ok(None)
+}
+
+// name a tx by the connectors it spends & pays, for txs no workflow records
+fn infer_tx_name(inputs: &[String], outputs: &[String]) -> &'static str {
+    let spends = |role: &str| inputs.iter().any(|r| r == role);
+    let pays = |role: &str| outputs.iter().any(|r| r == role);
+    if spends("connector-b") && pays("connector-4") {
+        "Assert"
+    } else if spends("connector-1") && pays("connector-b") {
+        "Kickoff-2"
+    } else if pays("connector-a") && pays("connector-1") {
+        "Kickoff-1"
+    } else if spends("connector-3") && spends("connector-a") {
+        "Take-1"
+    } else if spends("connector-4") && spends("connector-c") {
+        "Take-2"
+    } else if spends("connector-5") && spends("connector-c") {
+        "Disprove"
+    } else if spends("connector-a") && !spends("connector-1") {
+        "Challenge"
+    } else if pays("connector-0") && !spends("connector-0") {
+        "Peg-In"
+    } else {
+        UNIDENTIFIED
+    }
+}
+
+pub struct NamedTx {
+    pub tx_name: String,
+    pub workflow_id: Option<i32>,
+    pub inputs: Vec<(String, Option<Address>, Amount)>,
+    pub outputs: Vec<(String, Option<Address>, Amount)>,
+}
+
+// name `txid` & label its inputs/outputs by matching their scripts against the connectors
+pub fn classify_tx(rpc: &Client, db: &Connection, txid: Txid) -> Result<NamedTx, String> {
+    let mut tx_cache = TxCache::default();
+    let tx = tx_cache.get_raw_tx(rpc, txid)?.clone();
+    let workflow = find_workflow(db, &tx)?;
+    let book = ScriptBook::new(workflow.as_ref());
+
+    let mut inputs = vec![];
+    for input in tx.input.iter() {
+        let prev = input.previous_output;
+        let prev_tx = tx_cache.get_raw_tx(rpc, prev.txid)?;
+        let prev_txout = match prev_tx.output.get(prev.vout as usize) {
+            Some(v) => v,
+            _ => return Err(format!("fail to get prev_txout {prev}")),
+        };
+        let role = book.output_roles(prev_tx).swap_remove(prev.vout as usize);
+        inputs.push((role, Address::from_script(&prev_txout.script_pubkey, config::network()).ok(), prev_txout.value));
+    }
+    let outputs: Vec<(String, Option<Address>, Amount)> = book.output_roles(&tx).into_iter()
+        .zip(tx.output.iter())
+        .map(|(role, o)| (role, Address::from_script(&o.script_pubkey, config::network()).ok(), o.value))
+        .collect();
+
+    let recorded_name = workflow.as_ref().and_then(|row| {
+        workflow_txs(&row.data).into_iter().find(|(_, t)| *t == txid).map(|(name, _)| name)
+    });
+    let tx_name = match recorded_name {
+        Some(name) => name,
+        _ => {
+            let input_roles: Vec<String> = inputs.iter().map(|(role, _, _)| role.clone()).collect();
+            let output_roles: Vec<String> = outputs.iter().map(|(role, _, _)| role.clone()).collect();
+            infer_tx_name(&input_roles, &output_roles)
+        },
+    };
+
+    Ok(NamedTx {
+        tx_name: tx_name.to_string(),
+        workflow_id: workflow.map(|row| row.id),
+        inputs,
+        outputs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{absolute, transaction::Version, TxOut};
+
+    fn roles(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    fn script(tag: u8) -> ScriptBuf {
+        ScriptBuf::from_bytes(vec![0x6a, 0x01, tag])
+    }
+
+    fn tx_paying(scripts: &[ScriptBuf]) -> Transaction {
+        Transaction {
+            version: Version(2),
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![],
+            output: scripts.iter().map(|s| TxOut { value: Amount::from_sat(1000), script_pubkey: s.clone() }).collect(),
+        }
+    }
+
+    #[test]
+    fn output_roles_from_scripts() {
+        let known: HashMap<ScriptBuf, String> = HashMap::from([
+            (script(0), "connector-0".to_string()),
+            (script(3), "connector-3".to_string()),
+            (script(4), "connector-4".to_string()),
+            (script(0xb), "connector-b".to_string()),
+        ]);
+        // peg_in pays connector_0
+        assert_eq!(output_roles(&known, &tx_paying(&[script(0)])), roles(&["connector-0"]));
+        // assert pays connector_5 (same script as connector_0) next to connector_4
+        assert_eq!(
+            output_roles(&known, &tx_paying(&[script(4), script(0), script(9)])),
+            roles(&["connector-4", "connector-5", EXTERNAL]),
+        );
+        // unknown scripts are external whatever their position
+        assert_eq!(
+            output_roles(&known, &tx_paying(&[script(7), script(0xb), script(8)])),
+            roles(&[EXTERNAL, "connector-b", EXTERNAL]),
+        );
+        assert_eq!(
+            output_roles(&known, &tx_paying(&[script(3), script(0xb)])),
+            roles(&["connector-3", "connector-b"]),
+        );
+    }
+
+    #[test]
+    fn infer_names_from_roles() {
+        let name = |inputs: &[&str], outputs: &[&str]| infer_tx_name(&roles(inputs), &roles(outputs));
+        assert_eq!(name(&[EXTERNAL], &["connector-0"]), "Peg-In");
+        assert_eq!(name(&[EXTERNAL], &["connector-a", "connector-1", "connector-2"]), "Kickoff-1");
+        assert_eq!(name(&["connector-1"], &["connector-3", "connector-b", "revealer"]), "Kickoff-2");
+        // output order doesn't matter
+        assert_eq!(name(&["connector-1"], &["revealer", "connector-b", "connector-3"]), "Kickoff-2");
+        assert_eq!(name(&["connector-b"], &["connector-4", "connector-5", "connector-c"]), "Assert");
+        assert_eq!(name(&["connector-0", "connector-3", "connector-a"], &[EXTERNAL]), "Take-1");
+        assert_eq!(name(&["connector-0", "connector-4", "connector-c"], &[EXTERNAL]), "Take-2");
+        assert_eq!(name(&["connector-5", "connector-c"], &[EXTERNAL]), "Disprove");
+        // challenge spends connector_a at any input, next to the crowdfunding inputs
+        assert_eq!(name(&[EXTERNAL, "connector-a"], &[EXTERNAL]), "Challenge");
+        assert_eq!(name(&["connector-a"], &[EXTERNAL]), "Challenge");
+        assert_eq!(name(&[EXTERNAL], &[EXTERNAL]), UNIDENTIFIED);
+    }
+}
@@ -14,12 +14,20 @@ pub const BIND_PORT: u16 = 7080;
 pub const RPCUSER: &str = "test";
 pub const RPCPASSWORD: &str = "test";
 pub const RPC_URL: &str = "http://127.0.0.1:18443/wallet/public-test";
+pub const RPC_POOL_SIZE: usize = 4; // long-lived rpc clients shared by the handlers
+pub const RPC_TIMEOUT: u64 = 15; // in seconds
+pub const RPC_RETRIES: u32 = 3; // retries of a transient rpc failure (connection error, bitcoind warming up)
+pub const RPC_RETRY_BACKOFF: u64 = 200; // in milliseconds, doubled after every retry
 
 pub const PROOF_PATH: &str = "data-dir/dummy_proof.json";
 pub const COMPILE_PATH: &str = "data-dir/compile";
 pub const TAPSCRIPT_PATH: &str = "data-dir/tapscripts";
 pub const WOTS_SIGNATURE_PATH: &str = "data-dir/signed_assertions";
 pub const PRE_COMPUTED_DISPROVE_PATH: &str = "data-dir/disprove";
+pub const WORKFLOW_DATA_PATH: &str = "data-dir/workflows"; // per-workflow tapscripts/signed_assertions/disprove
+pub const VK_PATH: &str = "data-dir/vks"; // registered verifying keys & their compile data
+pub const CUSTOM_PROOF_PATH: &str = "data-dir/proofs"; // proofs submitted per workflow
+pub const VERIFY_PATH: &str = "data-dir/verify"; // scratch space of /verify/*
 pub const WOTS_SECRET: &str = "a138982ce17ac813d505a5b40b665d404e9528e7"; // just for test
 pub const N_TAPLEAVES: usize = g16::N_TAPLEAVES;
 pub const N_ASSERTIONS: usize = g16::N_VERIFIER_PUBLIC_INPUTS + g16::N_VERIFIER_FQS + g16::N_VERIFIER_HASHES;
@@ -36,6 +44,42 @@ pub const WITHDRAWER_SECRET: &str = base::WITHDRAWER_SECRET;
 pub const DEPOSITOR_EVM_ADDRESS: &str = base::DEPOSITOR_EVM_ADDRESS;
 pub const WITHDRAWER_EVM_ADDRESS: &str = base::WITHDRAWER_EVM_ADDRESS;
 
+pub const WATCHTOWER_ENABLED: bool = false; // auto challenge/disprove, conflicts with the manual demo flow
+pub const WATCHTOWER_INTERVAL: u64 = 10; // in seconds
+pub const WATCHTOWER_MAX_FEE: u64 = 1_000_000; // skip challenge/disprove txs paying more than this
+pub const WATCHTOWER_CHALLENGER_REWARD_ADDRESS: &str = ""; // empty: reward verifier_0
+
+pub const FEE_RATE: u64 = 0; // in sat/vB, 0: estimate with estimatesmartfee
+pub const FEE_CONF_TARGET: u16 = 6; // in blocks
+pub const FALLBACK_FEE_RATE: u64 = 2; // in sat/vB, used when bitcoind has no estimate (e.g. regtest)
+
+pub const SETUP_ETA: u64 = 1_800; // in seconds, rough duration of setup_all on first boot, reported while loading
+pub const STATICS_LOAD_ETA: u64 = 120; // in seconds, rough duration of loading the transactions statics
+
+pub const REGTEST_MINE_TO_MATURITY: bool = true; // mint blocks until timelocks mature instead of scheduling the broadcast
+pub const SCHEDULER_INTERVAL: u64 = 10; // in seconds
+pub const CPFP_AUTO_BUMP: bool = true; // bump the fee of txs unconfirmed after CPFP_STUCK_BLOCKS
+pub const CPFP_STUCK_BLOCKS: u64 = 3;
+
+pub const WORKER_THREADS: usize = 4; // runtime running the rpc calls & tx steps off the http workers
+pub const VALIDATION_WORKERS: usize = 1; // assertion/proof validations running at once
+pub const VALIDATION_QUEUE_SIZE: usize = 4; // validations waiting for a worker, more are answered with 503
+pub const VALIDATION_RETRY_AFTER: u64 = 60; // in seconds, sent with the 503 of a full validation queue
+pub const SCENARIO_MAX_RUNNING: u32 = 1; // scenarios of POST /scenarios running at once
+
+pub const ADMIN_TOKEN: &str = ""; // sent as `X-Admin-Token`, empty: admin endpoints disabled
+pub const TRUSTED_PROXY: &str = ""; // ip of the reverse proxy whose X-Forwarded-For/Forwarded headers are honoured, empty: none
+
+pub const AUTH_REQUIRED: bool = true; // state-changing endpoints need a session of the workflow's address, false only for a trusted local demo
+pub const AUTH_CHALLENGE_TTL: u64 = 300; // in seconds
+pub const AUTH_SESSION_TTL: u64 = 3_600; // in seconds
+
+pub const FAUCET_ADDRESS_QUOTA: u32 = 3; // requests per address per FAUCET_QUOTA_WINDOW, 0: unlimited
+pub const FAUCET_IP_QUOTA: u32 = 5; // requests per ip per FAUCET_QUOTA_WINDOW, 0: unlimited
+pub const FAUCET_QUOTA_WINDOW: u64 = 86_400; // in seconds
+pub const FAUCET_COOLDOWN: u64 = 300; // in seconds, between two requests of the same address or ip
+pub const FAUCET_DAILY_CAP: u64 = 10_000_000_000; // in sats, paid out by the faucet over 24h, 0: unlimited
+pub const FAUCET_POW_DIFFICULTY: u32 = 0; // leading zero bits of sha256("{address}:{nonce}"), 0: no pow required
 
 pub const CONNECTOR_0_ADDRESS: &str = "bcrt1pevdd404wz9tn9q9mv2m2qqwkr7ggk9gr42aslxl8khs0fv8nh98qj9lqh5";
 pub const CONNECTOR_4_ADDRESS: &str = "bcrt1qn0fq79zuqhgysahj29u7kghhmj6fukwmf5mgcxt5x86se03s297q8r3lmf";
@@ -0,0 +1,184 @@
+use bitcoin::{Address, Amount};
+use bitcoin_hashes::{sha256, Hash};
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+use crate::{config, metrics, sql};
+
+const DAY: u64 = 86_400;
+
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// faucet_1 + faucet_2, see transactions::faucet
+pub fn faucet_amount() -> Amount {
+    Amount::from_sat(config::PEGIN_AMOUNT + config::KICKOFF_AMOUNT)
+}
+
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut bits = 0;
+    for b in bytes {
+        if *b == 0 {
+            bits += 8;
+        } else {
+            bits += b.leading_zeros();
+            break;
+        }
+    }
+    bits
+}
+
+// the nonce must make sha256("{address}:{nonce}") start with FAUCET_POW_DIFFICULTY zero bits
+pub fn check_pow(addr: &Address, nonce: Option<&str>) -> Result<(), String> {
+    if config::FAUCET_POW_DIFFICULTY == 0 {
+        return Ok(())
+    };
+    let nonce = match nonce {
+        Some(v) => v,
+        _ => return Err(format!("missing proof-of-work, difficulty: {} bits", config::FAUCET_POW_DIFFICULTY)),
+    };
+    if !is_pow_valid(addr, nonce, config::FAUCET_POW_DIFFICULTY) {
+        return Err(format!("invalid proof-of-work, difficulty: {} bits", config::FAUCET_POW_DIFFICULTY))
+    };
+    Ok(())
+}
+
+fn is_pow_valid(addr: &Address, nonce: &str, difficulty: u32) -> bool {
+    let hash = sha256::Hash::hash(format!("{addr}:{nonce}").as_bytes());
+    leading_zero_bits(&hash.to_byte_array()) >= difficulty
+}
+
+// a nonce passing check_pow for `addr`, None when no proof-of-work is required
+pub fn solve_pow(addr: &Address) -> Option<String> {
+    if config::FAUCET_POW_DIFFICULTY == 0 {
+        return None
+    };
+    (0u64..).map(|n| n.to_string()).find(|nonce| is_pow_valid(addr, nonce, config::FAUCET_POW_DIFFICULTY))
+}
+
+// Ok(Some((reason, retry_after))) when the request exceeds a quota, retry_after in seconds
+pub fn check_quota(db: &Connection, addr: &Address, ip: &str, now: u64) -> Result<Option<(String, u64)>, String> {
+    if config::FAUCET_DAILY_CAP > 0 {
+        let (paid, earliest) = sql::get_faucet_amount_since(db, now.saturating_sub(DAY))?;
+        if paid + faucet_amount() > Amount::from_sat(config::FAUCET_DAILY_CAP) {
+            let retry_after = earliest.map_or(DAY, |t| (t + DAY).saturating_sub(now));
+            return Ok(Some(("faucet daily cap reached".to_string(), retry_after)))
+        };
+    };
+
+    // whitelisted addresses are only bound by the daily cap
+    if sql::is_faucet_whitelisted(db, addr)? {
+        return Ok(None)
+    };
+
+    if config::FAUCET_COOLDOWN > 0 {
+        if let Some(last) = sql::get_last_faucet_request_time(db, addr, ip)? {
+            if now < last + config::FAUCET_COOLDOWN {
+                return Ok(Some(("faucet cooldown".to_string(), last + config::FAUCET_COOLDOWN - now)))
+            };
+        };
+    };
+
+    let window_start = now.saturating_sub(config::FAUCET_QUOTA_WINDOW);
+    let quotas = [
+        ("addr", addr.to_string(), config::FAUCET_ADDRESS_QUOTA, "address"),
+        ("ip", ip.to_string(), config::FAUCET_IP_QUOTA, "ip"),
+    ];
+    for (column, value, quota, name) in quotas {
+        if quota == 0 {
+            continue
+        };
+        let (count, earliest) = sql::get_faucet_requests_since(db, column, &value, window_start)?;
+        if count >= quota {
+            let retry_after = earliest.map_or(config::FAUCET_QUOTA_WINDOW, |t| (t + config::FAUCET_QUOTA_WINDOW).saturating_sub(now));
+            return Ok(Some((format!("faucet quota of this {name} exceeded"), retry_after)))
+        };
+    }
+
+    Ok(None)
+}
+
+// outcome of `reserve`
+#[derive(Debug, PartialEq)]
+pub enum Reservation {
+    // id of the recorded request, `release` it if the payout fails
+    Reserved(i64),
+    // the request exceeds a quota: (reason, retry_after in seconds)
+    Refused(String, u64),
+}
+
+// check the quotas & record the request in one transaction, before paying out:
+// parallel requests are serialized by the write lock & can't all pass the same quota
+pub fn reserve(db: &Connection, addr: &Address, ip: &str, now: u64) -> Result<Reservation, String> {
+    let tx = match Transaction::new_unchecked(db, TransactionBehavior::Immediate) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to begin transaction: {}", e))
+    };
+    if let Some((reason, retry_after)) = check_quota(&tx, addr, ip, now)? {
+        return Ok(Reservation::Refused(reason, retry_after))
+    };
+    let id = sql::add_faucet_request(&tx, addr, ip, faucet_amount(), now)?;
+    match tx.commit() {
+        Ok(_) => Ok(Reservation::Reserved(id)),
+        Err(e) => Err(format!("fail to commit faucet request: {}", e))
+    }
+}
+
+// give back a reservation whose payout failed
+pub fn release(db: &Connection, id: i64) -> Result<bool, String> {
+    sql::remove_faucet_request(db, id)
+}
+
+pub fn record_payout() {
+    metrics::inc_counter(metrics::FAUCET_REQUESTS_TOTAL, &[], 1.0);
+    metrics::inc_counter(metrics::FAUCET_PAID_SATS_TOTAL, &[], faucet_amount().to_sat() as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+
+    #[test]
+    fn pow_leading_zero_bits() {
+        assert_eq!(leading_zero_bits(&[0x00, 0x00, 0x80]), 16);
+        assert_eq!(leading_zero_bits(&[0x00, 0x0f]), 12);
+        assert_eq!(leading_zero_bits(&[0xff]), 0);
+        let addr = utils::address_from_str(config::CONNECTOR_4_ADDRESS).unwrap();
+        let nonce = (0u64..).find(|n| is_pow_valid(&addr, &n.to_string(), 8)).unwrap();
+        assert!(is_pow_valid(&addr, &nonce.to_string(), 8));
+        assert!(is_pow_valid(&addr, "any", 0));
+    }
+
+    #[test]
+    fn quota_reserved_before_payout() {
+        let db = Connection::open_in_memory().unwrap();
+        sql::create_tables(&db).unwrap();
+        let addr = utils::address_from_str(config::CONNECTOR_4_ADDRESS).unwrap();
+        let now = 1_000_000;
+
+        let id = match reserve(&db, &addr, "10.0.0.1", now).unwrap() {
+            Reservation::Reserved(id) => id,
+            v => panic!("unexpected {v:?}"),
+        };
+        // the reservation already counts: same address or ip within the cooldown
+        assert!(matches!(reserve(&db, &addr, "10.0.0.2", now + 1).unwrap(), Reservation::Refused(_, _)));
+        // a released reservation gives the quota back
+        release(&db, id).unwrap();
+        assert!(matches!(reserve(&db, &addr, "10.0.0.1", now + 1).unwrap(), Reservation::Reserved(_)));
+
+        // the address quota holds across ips once the cooldowns are over
+        let mut t = now + 1;
+        for i in 1..config::FAUCET_ADDRESS_QUOTA {
+            t += config::FAUCET_COOLDOWN;
+            assert!(matches!(reserve(&db, &addr, &format!("10.0.1.{i}"), t).unwrap(), Reservation::Reserved(_)));
+        }
+        t += config::FAUCET_COOLDOWN;
+        assert_eq!(
+            reserve(&db, &addr, "10.0.2.1", t).unwrap(),
+            Reservation::Refused("faucet quota of this address exceeded".to_string(), now + 1 + config::FAUCET_QUOTA_WINDOW - t),
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use std::fmt::Write;
+use bitcoin::{Amount, Txid};
+use bitcoincore_rpc::Client;
+use rusqlite::Connection;
+use serde::Serialize;
+use crate::{sql::{self, UserData}, utils::{self, TxCache}};
+
+pub const NOT_BROADCAST: &str = "not yet broadcast";
+
+// tx nodes of the bitvm graph as (id, name), parents before children
+const NODES: [(&str, &str); 14] = [
+    ("peg_in_deposit", "Peg-In-Deposit"),
+    ("peg_in_refund", "Peg-In-Refund"),
+    ("peg_in", "Peg-In"),
+    ("peg_out", "Peg-Out"),
+    ("kickoff_1", "Kickoff-1"),
+    ("start_time", "Start-Time"),
+    ("start_time_timeout", "Start-Time-Timeout"),
+    ("kickoff_timeout", "Kickoff-Timeout"),
+    ("kickoff_2", "Kickoff-2"),
+    ("challenge", "Challenge"),
+    ("take_1", "Take-1"),
+    ("assert", "Assert"),
+    ("take_2", "Take-2"),
+    ("disprove", "Disprove"),
+];
+
+#[derive(Clone, Copy)]
+enum Vouts {
+    One(u32),
+    From(u32), // the revealers, one output each
+    None, // no output spent, the tx only follows the other
+}
+
+// connector outputs & the txs that may spend them: (from, connector, vouts, to)
+// peg_out is paid from the operator's funds, it fronts the peg-in to the withdrawer & is reimbursed through kickoff_1
+const EDGES: [(&str, &str, Vouts, &str); 22] = [
+    ("peg_in_deposit", "connector-z", Vouts::One(0), "peg_in"),
+    ("peg_in_deposit", "connector-z", Vouts::One(0), "peg_in_refund"),
+    ("peg_in", "connector-0", Vouts::One(0), "take_1"),
+    ("peg_in", "connector-0", Vouts::One(0), "take_2"),
+    ("peg_in", "withdrawal", Vouts::None, "peg_out"),
+    ("peg_out", "reimbursement", Vouts::None, "kickoff_1"),
+    ("kickoff_1", "connector-a", Vouts::One(0), "challenge"),
+    ("kickoff_1", "connector-a", Vouts::One(0), "take_1"),
+    ("kickoff_1", "connector-1", Vouts::One(1), "kickoff_2"),
+    ("kickoff_1", "connector-1", Vouts::One(1), "start_time_timeout"),
+    ("kickoff_1", "connector-1", Vouts::One(1), "kickoff_timeout"),
+    ("kickoff_1", "connector-2", Vouts::One(2), "start_time"),
+    ("kickoff_1", "connector-2", Vouts::One(2), "start_time_timeout"),
+    ("kickoff_2", "connector-3", Vouts::One(0), "take_1"),
+    ("kickoff_2", "connector-b", Vouts::One(1), "take_1"),
+    ("kickoff_2", "connector-b", Vouts::One(1), "assert"),
+    ("kickoff_2", "revealers", Vouts::From(2), "assert"),
+    ("assert", "connector-4", Vouts::One(0), "take_2"),
+    ("assert", "connector-5", Vouts::One(1), "take_2"),
+    ("assert", "connector-5", Vouts::One(1), "disprove"),
+    ("assert", "connector-c", Vouts::One(2), "take_2"),
+    ("assert", "connector-c", Vouts::One(2), "disprove"),
+];
+
+fn node_txid(user_data: &UserData, id: &str) -> Option<Txid> {
+    match id {
+        "peg_in_deposit" => user_data.pegin_deposit,
+        "peg_in_refund" => user_data.pegin_refund,
+        "peg_in" => user_data.pegin,
+        "peg_out" => user_data.peg_out,
+        "kickoff_1" => user_data.kickoff_1,
+        "start_time" => user_data.start_time,
+        "start_time_timeout" => user_data.start_time_timeout,
+        "kickoff_timeout" => user_data.kickoff_timeout,
+        "kickoff_2" => user_data.kickoff_2,
+        "challenge" => user_data.challenge,
+        "take_1" => user_data.take_1,
+        "assert" => user_data.assert,
+        "take_2" => user_data.take_2,
+        "disprove" => user_data.disprove,
+        _ => None,
+    }
+}
+
+#[derive(Serialize)]
+pub struct GraphNode {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub txid: Option<Txid>,
+    pub state: String, // NOT_BROADCAST, scheduled, mempool, confirmed or unknown
+    pub confirmations: Option<u32>,
+    pub scheduled_height: Option<u64>,
+    pub amount: Option<Amount>, // sum of the outputs
+    pub branch: &'static str, // taken, not-taken or pending
+}
+
+#[derive(Serialize)]
+pub struct GraphEdge {
+    pub from: &'static str,
+    pub to: &'static str,
+    pub connector: &'static str,
+    pub vout: String,
+    pub amount: Option<Amount>,
+    pub spent: bool,
+}
+
+#[derive(Serialize)]
+pub struct WorkflowGraph {
+    pub workflow_id: i32,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+// only a tx bitcoind knows is on the chain's path, a scheduled or dropped one may never be
+fn is_taken(nodes: &[GraphNode], id: &str) -> bool {
+    nodes.iter().any(|n| n.id == id && (n.state == "mempool" || n.state == "confirmed"))
+}
+
+// a tx is off the taken path when another tx spent one of its inputs, when one of its parents
+// is off the path, or when its outputs were never needed (e.g. the deposit of a direct peg-in)
+fn assign_branches(nodes: &mut [GraphNode]) {
+    for i in 0..nodes.len() {
+        let id = nodes[i].id;
+        if is_taken(nodes, id) {
+            nodes[i].branch = "taken";
+            continue
+        };
+        let conflicted = EDGES.iter()
+            .filter(|(_, _, _, to)| *to == id)
+            .any(|(from, connector, _, _)| EDGES.iter().any(|(f, c, _, t)| f == from && c == connector && *t != id && is_taken(nodes, t)));
+        let parent_off_path = EDGES.iter()
+            .filter(|(_, _, _, to)| *to == id)
+            .any(|(from, _, _, _)| nodes.iter().any(|n| n.id == *from && n.branch == "not-taken"));
+        let child_taken = EDGES.iter()
+            .filter(|(from, _, _, _)| *from == id)
+            .any(|(_, _, _, to)| is_taken(nodes, to));
+        if conflicted || parent_off_path || child_taken {
+            nodes[i].branch = "not-taken";
+        };
+    }
+}
+
+pub fn build_graph(rpc: &Client, db: &Connection, workflow_id: i32, user_data: &UserData) -> Result<WorkflowGraph, String> {
+    let mut tx_cache = TxCache::default();
+    let mut nodes: Vec<GraphNode> = vec![];
+    for (id, name) in NODES {
+        let txid = node_txid(user_data, id);
+        let (state, confirmations, scheduled_height, amount) = match txid {
+            Some(txid) => match utils::get_tx_confirmations(rpc, txid) {
+                Ok(n) => {
+                    let amount: Amount = tx_cache.get_raw_tx(rpc, txid)?.output.iter().map(|o| o.value).sum();
+                    let state = if n == 0 { "mempool" } else { "confirmed" };
+                    (state.to_string(), Some(n), None, Some(amount))
+                },
+                // recorded but unknown to bitcoind: waiting for its timelock, or dropped
+                _ => match sql::get_scheduled_height(db, &txid)? {
+                    Some(height) => ("scheduled".to_string(), None, Some(height), None),
+                    _ => ("unknown".to_string(), None, None, None),
+                },
+            },
+            _ => (NOT_BROADCAST.to_string(), None, None, None),
+        };
+        nodes.push(GraphNode { id, name, txid, state, confirmations, scheduled_height, amount, branch: "pending" });
+    }
+
+    assign_branches(&mut nodes);
+
+    let mut edges = vec![];
+    for (from, connector, vouts, to) in EDGES {
+        let from_txid = nodes.iter().find(|n| n.id == from).and_then(|n| n.txid);
+        let amount = match from_txid {
+            Some(txid) if nodes.iter().any(|n| n.id == from && n.amount.is_some()) => {
+                let tx = tx_cache.get_raw_tx(rpc, txid)?;
+                match vouts {
+                    Vouts::One(vout) => tx.output.get(vout as usize).map(|o| o.value),
+                    Vouts::From(vout) => Some(tx.output.iter().skip(vout as usize).map(|o| o.value).sum::<Amount>()),
+                    Vouts::None => None,
+                }
+            },
+            _ => None,
+        };
+        let vout = match vouts {
+            Vouts::One(vout) => vout.to_string(),
+            Vouts::From(vout) => format!("{vout}.."),
+            Vouts::None => String::new(),
+        };
+        edges.push(GraphEdge { from, to, connector, vout, amount, spent: is_taken(&nodes, to) });
+    }
+
+    Ok(WorkflowGraph { workflow_id, nodes, edges })
+}
+
+fn node_label(node: &GraphNode, line_break: &str) -> String {
+    let txid = match node.txid {
+        Some(txid) => txid.to_string(),
+        _ => NOT_BROADCAST.to_string(),
+    };
+    let mut label = format!("{}{line_break}{txid}{line_break}{}", node.name, node.state);
+    if let Some(n) = node.confirmations {
+        let _ = write!(label, " ({n})");
+    };
+    if let Some(amount) = node.amount {
+        let _ = write!(label, "{line_break}{} sat", amount.to_sat());
+    };
+    label
+}
+
+fn edge_label(edge: &GraphEdge) -> String {
+    match edge.amount {
+        Some(amount) => format!("{} {} sat", edge.connector, amount.to_sat()),
+        _ => edge.connector.to_string(),
+    }
+}
+
+pub fn to_dot(graph: &WorkflowGraph) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "digraph workflow_{} {{", graph.workflow_id);
+    let _ = writeln!(out, "    rankdir=LR;");
+    let _ = writeln!(out, "    node [shape=box, fontname=\"monospace\"];");
+    for node in graph.nodes.iter() {
+        let style = match node.branch {
+            "taken" => "style=\"filled\", fillcolor=\"palegreen\"",
+            "not-taken" => "style=\"dotted\", fontcolor=\"gray\"",
+            _ => "style=\"dashed\"",
+        };
+        let _ = writeln!(out, "    {} [label=\"{}\", {style}];", node.id, node_label(node, "\\n"));
+    }
+    for edge in graph.edges.iter() {
+        let style = if edge.spent { "solid" } else { "dashed" };
+        let _ = writeln!(out, "    {} -> {} [label=\"{}\", style=\"{style}\"];", edge.from, edge.to, edge_label(edge));
+    }
+    out.push_str("}\n");
+    out
+}
+
+pub fn to_mermaid(graph: &WorkflowGraph) -> String {
+    let mut out = String::from("flowchart LR\n");
+    for node in graph.nodes.iter() {
+        let _ = writeln!(out, "    {}[\"{}\"]", node.id, node_label(node, "<br/>"));
+    }
+    for edge in graph.edges.iter() {
+        let arrow = if edge.spent { "-->" } else { "-.->" };
+        let _ = writeln!(out, "    {} {arrow}|{}| {}", edge.from, edge_label(edge), edge.to);
+    }
+    out.push_str("    classDef taken fill:#c8e6c9\n");
+    out.push_str("    classDef not_taken stroke-dasharray:5 5,color:#999\n");
+    for node in graph.nodes.iter().filter(|n| n.branch != "pending") {
+        let _ = writeln!(out, "    class {} {}", node.id, node.branch.replace('-', "_"));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn txid(n: u8) -> Txid {
+        Txid::from_str(&format!("{:064x}", n)).unwrap()
+    }
+
+    // nodes of a workflow with the given (id, state) recorded, the rest not broadcast
+    fn nodes(states: &[(&str, &str)]) -> Vec<GraphNode> {
+        NODES.iter().enumerate().map(|(i, (id, name))| {
+            let state = states.iter().find(|(s, _)| s == id).map(|(_, state)| *state);
+            GraphNode {
+                id,
+                name,
+                txid: state.map(|_| txid(i as u8 + 1)),
+                state: state.unwrap_or(NOT_BROADCAST).to_string(),
+                confirmations: None,
+                scheduled_height: None,
+                amount: None,
+                branch: "pending",
+            }
+        }).collect()
+    }
+
+    fn branch<'a>(nodes: &'a [GraphNode], id: &str) -> &'a str {
+        nodes.iter().find(|n| n.id == id).unwrap().branch
+    }
+
+    #[test]
+    fn branches_follow_the_chain() {
+        let mut graph = nodes(&[
+            ("peg_in_deposit", "confirmed"),
+            ("peg_in", "confirmed"),
+            ("kickoff_1", "confirmed"),
+            ("challenge", "mempool"),
+        ]);
+        assign_branches(&mut graph);
+        assert_eq!(branch(&graph, "peg_in"), "taken");
+        assert_eq!(branch(&graph, "challenge"), "taken");
+        // peg_in spent the deposit, connector_a went to the challenge
+        assert_eq!(branch(&graph, "peg_in_refund"), "not-taken");
+        assert_eq!(branch(&graph, "take_1"), "not-taken");
+        // kickoff_1 was broadcast without it
+        assert_eq!(branch(&graph, "peg_out"), "not-taken");
+        assert_eq!(branch(&graph, "kickoff_2"), "pending");
+        assert_eq!(branch(&graph, "take_2"), "pending");
+    }
+
+    #[test]
+    fn scheduled_or_dropped_txs_are_not_taken() {
+        let mut graph = nodes(&[
+            ("peg_in_deposit", "confirmed"),
+            ("peg_in", "confirmed"),
+            ("kickoff_1", "confirmed"),
+            ("start_time_timeout", "scheduled"),
+            ("kickoff_2", "unknown"),
+        ]);
+        assign_branches(&mut graph);
+        assert_eq!(branch(&graph, "start_time_timeout"), "pending");
+        assert_eq!(branch(&graph, "kickoff_2"), "pending");
+        // neither spent connector_1 or connector_2
+        assert_eq!(branch(&graph, "start_time"), "pending");
+        assert_eq!(branch(&graph, "kickoff_timeout"), "pending");
+        assert_eq!(branch(&graph, "assert"), "pending");
+    }
+
+    fn small_graph() -> WorkflowGraph {
+        WorkflowGraph {
+            workflow_id: 7,
+            nodes: vec![
+                GraphNode {
+                    id: "peg_in",
+                    name: "Peg-In",
+                    txid: Some(txid(1)),
+                    state: "confirmed".to_string(),
+                    confirmations: Some(3),
+                    scheduled_height: None,
+                    amount: Some(Amount::from_sat(100_000)),
+                    branch: "taken",
+                },
+                GraphNode {
+                    id: "take_1",
+                    name: "Take-1",
+                    txid: None,
+                    state: NOT_BROADCAST.to_string(),
+                    confirmations: None,
+                    scheduled_height: None,
+                    amount: None,
+                    branch: "not-taken",
+                },
+            ],
+            edges: vec![GraphEdge {
+                from: "peg_in",
+                to: "take_1",
+                connector: "connector-0",
+                vout: "0".to_string(),
+                amount: Some(Amount::from_sat(100_000)),
+                spent: false,
+            }],
+        }
+    }
+
+    #[test]
+    fn render_dot() {
+        let dot = to_dot(&small_graph());
+        assert!(dot.starts_with("digraph workflow_7 {\n"));
+        assert!(dot.contains(&format!("    peg_in [label=\"Peg-In\\n{}\\nconfirmed (3)\\n100000 sat\", style=\"filled\", fillcolor=\"palegreen\"];\n", txid(1))));
+        assert!(dot.contains(&format!("    take_1 [label=\"Take-1\\n{NOT_BROADCAST}\\n{NOT_BROADCAST}\", style=\"dotted\", fontcolor=\"gray\"];\n")));
+        assert!(dot.contains("    peg_in -> take_1 [label=\"connector-0 100000 sat\", style=\"dashed\"];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn render_mermaid() {
+        let mermaid = to_mermaid(&small_graph());
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains(&format!("    peg_in[\"Peg-In<br/>{}<br/>confirmed (3)<br/>100000 sat\"]\n", txid(1))));
+        assert!(mermaid.contains("    peg_in -.->|connector-0 100000 sat| take_1\n"));
+        assert!(mermaid.contains("    class peg_in taken\n"));
+        assert!(mermaid.contains("    class take_1 not_taken\n"));
+    }
+}
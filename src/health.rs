@@ -0,0 +1,190 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+use bitcoin::Amount;
+use log::{info, error};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use crate::{config, setup, sql, transactions, utils};
+
+const N_STATICS: usize = 5;
+
+static STATICS_LOADED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, PartialEq)]
+enum LoadStage {
+    Pending,
+    Setup,
+    Statics,
+    Ready,
+    Failed,
+}
+
+struct LoadState {
+    stage: LoadStage,
+    statics_done: usize,
+    started_at: Instant,
+    stage_started_at: Instant,
+}
+
+static LOAD_STATE: Lazy<Mutex<LoadState>> = Lazy::new(|| Mutex::new(LoadState {
+    stage: LoadStage::Pending,
+    statics_done: 0,
+    started_at: Instant::now(),
+    stage_started_at: Instant::now(),
+}));
+
+#[derive(Clone, Serialize)]
+pub struct LoadProgress {
+    pub stage: String,
+    pub statics_loaded: usize,
+    pub statics_total: usize,
+    pub elapsed_secs: u64,
+    pub eta_secs: Option<u64>,
+}
+
+pub fn is_statics_loaded() -> bool {
+    STATICS_LOADED.load(Ordering::SeqCst)
+}
+
+fn set_stage(stage: LoadStage) {
+    let mut state = LOAD_STATE.lock().unwrap();
+    state.stage = stage;
+    state.stage_started_at = Instant::now();
+}
+
+fn set_statics_done(done: usize) {
+    LOAD_STATE.lock().unwrap().statics_done = done;
+}
+
+// run setup (first boot only) & force the transactions statics on their own thread, the server keeps serving meanwhile
+pub fn start_loading() {
+    let _ = &*LOAD_STATE;
+    let res = std::thread::Builder::new()
+        .name("statics".to_string())
+        .spawn(|| {
+            let res = std::panic::catch_unwind(|| {
+                if !setup::check_setup() {
+                    info!("Initializing ......");
+                    set_stage(LoadStage::Setup);
+                    setup::setup_all();
+                };
+
+                info!("load statics");
+                set_stage(LoadStage::Statics);
+                let _ = &*transactions::CONNECTOR_C_TAPSCRIPTS;
+                set_statics_done(1);
+                let _ = &*transactions::BITCOM_LOCK_SCRIPTS;
+                set_statics_done(2);
+                let _ = &*transactions::BITCOM_UNLOCK_SCRIPTS;
+                set_statics_done(3);
+                let _ = &*transactions::REVEALERS_ADDRESS;
+                set_statics_done(4);
+                let _ = &*transactions::CONNECTOR_C_SPEND_INFO;
+                set_statics_done(N_STATICS);
+            });
+            match res {
+                Ok(_) => {
+                    STATICS_LOADED.store(true, Ordering::SeqCst);
+                    set_stage(LoadStage::Ready);
+                    info!("statics loaded in {}s", LOAD_STATE.lock().unwrap().started_at.elapsed().as_secs());
+                },
+                Err(_) => {
+                    set_stage(LoadStage::Failed);
+                    error!("fail to load statics, see the panic above");
+                },
+            }
+        });
+    if let Err(e) = res {
+        error!("fail to start statics loader: {}", e);
+    };
+}
+
+pub fn get_load_progress() -> LoadProgress {
+    let state = LOAD_STATE.lock().unwrap();
+    let stage_elapsed = state.stage_started_at.elapsed().as_secs();
+    // setup has no finer progress, statics are extrapolated from the ones already loaded
+    let statics_eta = match state.statics_done {
+        0 => config::STATICS_LOAD_ETA.saturating_sub(stage_elapsed),
+        done => stage_elapsed * (N_STATICS - done) as u64 / done as u64,
+    };
+    let (stage, eta_secs) = match state.stage {
+        LoadStage::Pending => ("pending", Some(config::STATICS_LOAD_ETA)),
+        LoadStage::Setup => ("setup", Some(config::SETUP_ETA.saturating_sub(stage_elapsed) + config::STATICS_LOAD_ETA)),
+        LoadStage::Statics => ("statics", Some(statics_eta)),
+        LoadStage::Ready => ("ready", None),
+        LoadStage::Failed => ("failed", None),
+    };
+    LoadProgress {
+        stage: stage.to_string(),
+        statics_loaded: state.statics_done,
+        statics_total: N_STATICS,
+        elapsed_secs: state.started_at.elapsed().as_secs(),
+        eta_secs,
+    }
+}
+
+#[derive(Serialize)]
+pub struct SetupManifest {
+    pub tapscripts: bool,
+    pub signed_assertions: bool,
+}
+
+#[derive(Serialize)]
+pub struct DependencyStatus {
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+impl DependencyStatus {
+    fn from_result<T>(res: &Result<T, String>) -> Self {
+        match res {
+            Ok(_) => DependencyStatus { ok: true, error: None },
+            Err(e) => DependencyStatus { ok: false, error: Some(e.clone()) },
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub statics_loaded: bool,
+    pub loading: LoadProgress,
+    pub setup: SetupManifest,
+    pub db: DependencyStatus,
+    pub rpc: DependencyStatus,
+    pub chain_tip: Option<u64>,
+    pub wallet_balance: Option<Amount>,
+}
+
+pub async fn get_readiness() -> Readiness {
+    let statics_loaded = is_statics_loaded();
+    let (tapscripts, signed_assertions) = setup::get_setup_manifest();
+
+    let db_res = sql::open_db().and_then(|db| sql::check_writable(&db));
+    let db = DependencyStatus::from_result(&db_res);
+
+    let (rpc, chain_tip, wallet_balance) = match utils::new_rpc_client().await {
+        Ok(client) => {
+            let tip_res = utils::get_block_count(&client);
+            let balance_res = utils::get_wallet_balance(&client);
+            let rpc = match (&tip_res, &balance_res) {
+                (Err(e), _) | (_, Err(e)) => DependencyStatus { ok: false, error: Some(e.clone()) },
+                _ => DependencyStatus { ok: true, error: None },
+            };
+            (rpc, tip_res.ok(), balance_res.ok())
+        },
+        Err(e) => (DependencyStatus { ok: false, error: Some(e) }, None, None),
+    };
+
+    Readiness {
+        ready: statics_loaded && tapscripts && signed_assertions && db.ok && rpc.ok,
+        statics_loaded,
+        loading: get_load_progress(),
+        setup: SetupManifest { tapscripts, signed_assertions },
+        db,
+        rpc,
+        chain_tip,
+        wallet_balance,
+    }
+}
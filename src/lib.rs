@@ -0,0 +1,19 @@
+#![allow(unused_imports)]
+pub mod config;
+pub mod utils;
+pub mod setup;
+pub mod transactions;
+pub mod api;
+pub mod sql;
+pub mod watchtower;
+pub mod scheduler;
+pub mod faucet;
+pub mod auth;
+pub mod health;
+pub mod metrics;
+pub mod workers;
+pub mod rpc;
+pub mod classify;
+pub mod graph;
+pub mod scenario;
+pub mod steps;
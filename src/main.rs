@@ -1,10 +1,5 @@
 #![allow(unused_imports)]
-pub mod config;
-pub mod utils;
-pub mod setup;
-pub mod transactions;
-pub mod api;
-pub mod sql;
+use bitvm_demo_api::{api, config, health, metrics, rpc, scenario, scheduler, transactions, utils, watchtower, workers};
 
 use std::io::Write;
 use std::fs::File;
@@ -15,7 +10,7 @@ use bitcoin_hashes::Hash;
 use bitvm::bridge::transactions::kick_off_1;
 use bitvm::treepp::Script;
 use clap::{Command, Arg};
-use actix_web::{web, App, HttpResponse, HttpServer};
+use actix_web::{web, dev::Service, http::StatusCode, App, HttpResponse, HttpServer};
 use actix_cors::Cors;
 use log::{info, warn, error, debug, trace};
 
@@ -25,41 +20,106 @@ use log::{info, warn, error, debug, trace};
 async fn main() -> std::io::Result<()> {
     log4rs::init_file("log4rs.yml", Default::default()).unwrap();
 
-    if !setup::check_setup() {
-        info!("Initializing ......");
-        setup::setup_all();
-    };
-
     if !utils::check_rpc().await {
         error!("ERROR: bitcoin node is down/incomplete/misconfigured!");
         std::process::exit(2);
     }
 
-    info!("load statics");
-    let _ = &transactions::CONNECTOR_C_TAPSCRIPTS;
-    let _ = &transactions::BITCOM_LOCK_SCRIPTS;
-    let _ = &transactions::BITCOM_UNLOCK_SCRIPTS;
-    let _ = &transactions::REVEALERS_ADDRESS;
-    let _ = &transactions::CONNECTOR_C_SPEND_INFO;
+    workers::init();
+
+    let rpc_pool = match rpc::shared() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("ERROR: fail to create rpc clients: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    // setup & statics take minutes on first boot, endpoints needing them answer 503 until loaded
+    health::start_loading();
+
+    scenario::fail_interrupted();
+
+    info!("start scheduler");
+    scheduler::start();
+
+    if config::WATCHTOWER_ENABLED {
+        info!("start watchtower");
+        watchtower::start();
+    };
 
     let ip = config::BIND_IP;
     let port = config::BIND_PORT;
     info!("Listening to {ip}:{port} ......");
-    HttpServer::new(|| App::new()
+    HttpServer::new(move || App::new()
+        .app_data(web::Data::new(rpc_pool.clone()))
         .service(api::get_named_inputs_outputs)
         .service(api::get_user_workflow)
         .service(api::get_workflow_info)
+        .service(api::get_workflow_graph)
         .service(api::request_btc)
         .service(api::get_unsigned_pegin_tx)
         .service(api::post_pegin_txid)
+        .service(api::get_unsigned_pegin_deposit_tx)
+        .service(api::post_pegin_deposit_txid)
+        .service(api::get_pegin_confirm_refund_tx)
+        .service(api::send_pegin_confirm_refund)
         .service(api::post_fake_index)
+        .service(api::register_vk)
+        .service(api::get_vk_info)
+        .service(api::post_proof)
+        .service(api::verify_proof)
+        .service(api::verify_assertions)
         .service(api::get_unsigned_kickoff1_tx)
         .service(api::send_kickoff_2)
+        .service(api::send_start_time)
+        .service(api::send_start_time_timeout)
+        .service(api::send_kickoff_timeout)
+        .service(api::get_spendable_height)
+        .service(api::bump_workflow_tx)
+        .service(api::request_pegout)
+        .service(api::get_pegout)
+        .service(api::contribute_challenge)
+        .service(api::create_workflow)
+        .service(api::list_user_workflows)
+        .service(api::request_workflow_btc)
+        .service(api::start_scenario)
+        .service(api::get_scenario)
+        .service(api::add_faucet_whitelist)
+        .service(api::remove_faucet_whitelist)
+        .service(api::admin_list_workflows)
+        .service(api::admin_set_status)
+        .service(api::admin_unlock_workflow)
+        .service(api::admin_rerun_step)
+        .service(api::admin_archive_workflow)
+        .service(api::admin_delete_workflow)
+        .service(api::admin_drain_faucet)
+        .service(api::admin_get_audit)
+        .service(api::auth_challenge)
+        .service(api::auth_session)
+        .service(api::healthz)
+        .service(api::readyz)
+        .service(api::get_metrics)
         .service(api::send_challenge)
         .service(api::send_take_1)
         .service(api::send_assert)
         .service(api::send_take_2)
         .service(api::send_disprove)
+        .service(api::send_disprove_assert)
+        .wrap_fn(|req, srv| {
+            let start = std::time::Instant::now();
+            let fut = srv.call(req);
+            async move {
+                let res = fut.await?;
+                let route = res.request().match_pattern().unwrap_or_else(|| "unmatched".to_string());
+                let status = res.status();
+                metrics::observe(metrics::HTTP_REQUEST_DURATION, &[("route", &route), ("status", status.as_str())], start.elapsed().as_secs_f64());
+                if status == StatusCode::LOCKED {
+                    metrics::inc_counter(metrics::LOCK_CONTENTION_TOTAL, &[("route", &route)], 1.0);
+                };
+                Ok(res)
+            }
+        })
         .wrap(
             Cors::default()
                 .allow_any_origin() 
@@ -133,7 +193,7 @@ async fn disprove_opt_test(corrupt_index: u32) {
     println!("\nkick_off......");
     let kick_off_1_txid = transactions::kick_off_1(&rpc);
     println!("kick_off_1_txid: {kick_off_1_txid}");
-    let kick_off_2_txid = transactions::kick_off_2(&rpc, kick_off_1_txid, bitcom_lock_scripts).await.unwrap();
+    let kick_off_2_txid = transactions::kick_off_2(&rpc, kick_off_1_txid, bitcom_lock_scripts).await.unwrap().txid();
     println!("kick_off_2_txid: {kick_off_2_txid}");
     let duration = now.elapsed().unwrap().as_millis().to_string();
     println!("\npegin+kickoff cost: [{duration}]ms");
@@ -141,14 +201,16 @@ async fn disprove_opt_test(corrupt_index: u32) {
     let now = SystemTime::now();
     println!("\nfake {corrupt_index}th assertions");
     println!("assert......");
-    let (assert_txid, _) = transactions::assert(&rpc, kick_off_2_txid, &bitcom_lock_scripts, &connector_c_tapscripts, Some(corrupt_index), Some(connector_c_addr.clone())).await.unwrap();
+    let key_set = transactions::WotsKeySet::default_set();
+    let bitcom_unlock_scripts = key_set.bitcom_unlock_scripts(Some(corrupt_index as usize));
+    let (assert_txid, _, _) = transactions::assert(&rpc, kick_off_2_txid, &bitcom_lock_scripts, &connector_c_tapscripts, bitcom_unlock_scripts, Some(connector_c_addr.clone())).await.unwrap();
     println!("assert_txid: {assert_txid}");
     let duration = now.elapsed().unwrap().as_millis().to_string();
     println!("\nassert cost: [{duration}]ms");
 
     let now = SystemTime::now();
     println!("\ndisprove......");
-    let disprove_txid = transactions::disprove(&rpc, assert_txid, &connector_c_tapscripts, Some(connector_c_addr), Some(corrupt_index as usize)).await.unwrap();
+    let disprove_txid = transactions::disprove(&rpc, assert_txid, &connector_c_tapscripts, Some(connector_c_addr), &transactions::CONNECTOR_C_SPEND_INFO, &key_set).await.unwrap().txid();
     println!("disprove_txid: {disprove_txid}");
     let duration = now.elapsed().unwrap().as_millis().to_string();
     println!("\ndisprove cost: [{duration}]ms");
@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+use once_cell::sync::Lazy;
+
+// in seconds, shared by request latency & step durations
+const BUCKETS: [f64; 12] = [0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+
+pub const HTTP_REQUEST_DURATION: &str = "bitvm_http_request_duration_seconds";
+pub const STEP_DURATION: &str = "bitvm_step_duration_seconds";
+pub const BROADCAST_TOTAL: &str = "bitvm_broadcast_total";
+pub const FAUCET_REQUESTS_TOTAL: &str = "bitvm_faucet_requests_total";
+pub const FAUCET_PAID_SATS_TOTAL: &str = "bitvm_faucet_paid_sats_total";
+pub const FAUCET_REJECTED_TOTAL: &str = "bitvm_faucet_rejected_total";
+pub const LOCK_CONTENTION_TOTAL: &str = "bitvm_workflow_lock_contention_total";
+pub const WORKFLOWS: &str = "bitvm_workflows";
+pub const VALIDATION_PENDING: &str = "bitvm_validation_pending";
+
+fn help(name: &str) -> &'static str {
+    match name {
+        HTTP_REQUEST_DURATION => "HTTP request latency by route",
+        STEP_DURATION => "duration of workflow steps by transaction kind",
+        BROADCAST_TOTAL => "transaction broadcasts by result & failure reason",
+        FAUCET_REQUESTS_TOTAL => "faucet payouts",
+        FAUCET_PAID_SATS_TOTAL => "sats paid out by the faucet",
+        FAUCET_REJECTED_TOTAL => "faucet requests rejected by quota or proof-of-work",
+        LOCK_CONTENTION_TOTAL => "requests rejected because the workflow was locked",
+        WORKFLOWS => "workflows by status",
+        VALIDATION_PENDING => "assertion/proof validations running or queued",
+        _ => "",
+    }
+}
+
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+#[derive(Default)]
+struct Registry {
+    // keyed by (metric name, rendered labels)
+    counters: BTreeMap<(&'static str, String), f64>,
+    histograms: BTreeMap<(&'static str, String), Histogram>,
+}
+
+static REGISTRY: Lazy<Mutex<Registry>> = Lazy::new(|| Mutex::new(Registry::default()));
+
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    let labels: Vec<String> = labels.iter()
+        .map(|(k, v)| format!("{k}=\"{}\"", v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")))
+        .collect();
+    labels.join(",")
+}
+
+pub fn inc_counter(name: &'static str, labels: &[(&str, &str)], value: f64) {
+    let mut registry = REGISTRY.lock().unwrap();
+    *registry.counters.entry((name, render_labels(labels))).or_insert(0.0) += value;
+}
+
+pub fn observe(name: &'static str, labels: &[(&str, &str)], secs: f64) {
+    let mut registry = REGISTRY.lock().unwrap();
+    let histogram = registry.histograms.entry((name, render_labels(labels))).or_insert(Histogram {
+        buckets: [0; BUCKETS.len()],
+        sum: 0.0,
+        count: 0,
+    });
+    for (i, bound) in BUCKETS.iter().enumerate() {
+        if secs <= *bound {
+            histogram.buckets[i] += 1;
+        }
+    }
+    histogram.sum += secs;
+    histogram.count += 1;
+}
+
+// records the time until dropped under STEP_DURATION{kind}
+pub struct StepTimer {
+    kind: &'static str,
+    start: Instant,
+}
+
+impl StepTimer {
+    pub fn new(kind: &'static str) -> Self {
+        StepTimer { kind, start: Instant::now() }
+    }
+}
+
+impl Drop for StepTimer {
+    fn drop(&mut self) {
+        observe(STEP_DURATION, &[("kind", self.kind)], self.start.elapsed().as_secs_f64());
+    }
+}
+
+// coarse reason of a bitcoind rejection, keeps label cardinality bounded
+pub fn broadcast_failure_reason(err: &str) -> &'static str {
+    if err.contains("missingorspent") || err.contains("missing-inputs") {
+        "missing-inputs"
+    } else if err.contains("already") {
+        "already-known"
+    } else if err.contains("non-BIP68-final") || err.contains("non-final") {
+        "timelock"
+    } else if err.contains("fee") {
+        "fee"
+    } else if err.contains("mandatory-script-verify") || err.contains("non-mandatory-script-verify") {
+        "script"
+    } else if err.contains("bad-txns") {
+        "invalid"
+    } else {
+        "other"
+    }
+}
+
+// prometheus text format, `gauges` are sampled by the caller at scrape time
+pub fn render(gauges: &[(&'static str, Vec<(String, f64)>)]) -> String {
+    let registry = REGISTRY.lock().unwrap();
+    let mut out = String::new();
+
+    let mut last_name = "";
+    for ((name, labels), value) in registry.counters.iter() {
+        if *name != last_name {
+            let _ = writeln!(out, "# HELP {name} {}\n# TYPE {name} counter", help(name));
+            last_name = *name;
+        }
+        let _ = writeln!(out, "{name}{{{labels}}} {value}");
+    }
+
+    let mut last_name = "";
+    for ((name, labels), histogram) in registry.histograms.iter() {
+        if *name != last_name {
+            let _ = writeln!(out, "# HELP {name} {}\n# TYPE {name} histogram", help(name));
+            last_name = *name;
+        }
+        let sep = if labels.is_empty() { "" } else { "," };
+        for (i, bound) in BUCKETS.iter().enumerate() {
+            let _ = writeln!(out, "{name}_bucket{{{labels}{sep}le=\"{bound}\"}} {}", histogram.buckets[i]);
+        }
+        let _ = writeln!(out, "{name}_bucket{{{labels}{sep}le=\"+Inf\"}} {}", histogram.count);
+        let _ = writeln!(out, "{name}_sum{{{labels}}} {}", histogram.sum);
+        let _ = writeln!(out, "{name}_count{{{labels}}} {}", histogram.count);
+    }
+
+    for (name, samples) in gauges {
+        let _ = writeln!(out, "# HELP {name} {}\n# TYPE {name} gauge", help(name));
+        for (labels, value) in samples {
+            let _ = writeln!(out, "{name}{{{labels}}} {value}");
+        }
+    }
+    out
+}
+
+pub fn gauge_labels(labels: &[(&str, &str)]) -> String {
+    render_labels(labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bitcoind_rejections() {
+        assert_eq!(broadcast_failure_reason("bad-txns-inputs-missingorspent"), "missing-inputs");
+        assert_eq!(broadcast_failure_reason("txn-already-in-mempool"), "already-known");
+        assert_eq!(broadcast_failure_reason("non-BIP68-final"), "timelock");
+        assert_eq!(broadcast_failure_reason("non-final"), "timelock");
+        assert_eq!(broadcast_failure_reason("min relay fee not met, 100 < 141"), "fee");
+        assert_eq!(broadcast_failure_reason("mandatory-script-verify-flag-failed (Signature must be zero for failed CHECK(MULTI)SIG operation)"), "script");
+        assert_eq!(broadcast_failure_reason("bad-txns-in-belowout"), "invalid");
+        assert_eq!(broadcast_failure_reason("dust"), "other");
+    }
+
+    #[test]
+    fn render_prometheus_text() {
+        // the registry is shared with the other tests, only look at our own labels
+        inc_counter(BROADCAST_TOTAL, &[("result", "render-test"), ("reason", "a\"b")], 1.0);
+        inc_counter(BROADCAST_TOTAL, &[("result", "render-test"), ("reason", "a\"b")], 2.0);
+        inc_counter(BROADCAST_TOTAL, &[("result", "render-test"), ("reason", "other")], 1.0);
+        observe(STEP_DURATION, &[("kind", "render-test")], 0.07);
+        let gauges = vec![(WORKFLOWS, vec![(gauge_labels(&[("status", "1")]), 2.0)])];
+        let out = render(&gauges);
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert!(lines.contains(&"bitvm_broadcast_total{result=\"render-test\",reason=\"a\\\"b\"} 3"));
+        assert!(lines.contains(&"bitvm_broadcast_total{result=\"render-test\",reason=\"other\"} 1"));
+        // one header per metric, not per label set
+        assert_eq!(lines.iter().filter(|l| **l == "# TYPE bitvm_broadcast_total counter").count(), 1);
+
+        assert!(lines.contains(&"# TYPE bitvm_step_duration_seconds histogram"));
+        assert!(lines.contains(&"bitvm_step_duration_seconds_bucket{kind=\"render-test\",le=\"0.05\"} 0"));
+        assert!(lines.contains(&"bitvm_step_duration_seconds_bucket{kind=\"render-test\",le=\"0.1\"} 1"));
+        assert!(lines.contains(&"bitvm_step_duration_seconds_bucket{kind=\"render-test\",le=\"300\"} 1"));
+        assert!(lines.contains(&"bitvm_step_duration_seconds_bucket{kind=\"render-test\",le=\"+Inf\"} 1"));
+        assert!(lines.contains(&"bitvm_step_duration_seconds_count{kind=\"render-test\"} 1"));
+
+        assert!(lines.contains(&"# TYPE bitvm_workflows gauge"));
+        assert!(lines.contains(&"bitvm_workflows{status=\"1\"} 2"));
+    }
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use bitcoincore_rpc::Client;
+use bitcoincore_rpc::jsonrpc::{self, simple_http::SimpleHttpTransport, Request, Response, Transport};
+use log::warn;
+use once_cell::sync::Lazy;
+use crate::config;
+
+// bitcoind rejects requests with -28 while it is still loading
+const RPC_IN_WARMUP: i32 = -28;
+
+// a transport failure can happen after bitcoind ran the call (e.g. a read timeout),
+// only calls without side effects are safe to send again
+const READ_ONLY_METHODS: [&str; 20] = [
+    "getbestblockhash", "getblock", "getblockchaininfo", "getblockcount", "getblockhash",
+    "getblockheader", "getmempoolentry", "getmempoolinfo", "getnetworkinfo", "getrawmempool",
+    "getrawtransaction", "gettxout", "gettransaction", "getwalletinfo", "getbalance",
+    "getaddressinfo", "listunspent", "estimatesmartfee", "testmempoolaccept", "decoderawtransaction",
+];
+
+pub type RpcClient = Arc<Client>;
+
+fn is_read_only(method: &str) -> bool {
+    READ_ONLY_METHODS.contains(&method)
+}
+
+// retries warmup errors, & transport failures (connection refused/reset, timeouts, full work queue)
+// of read-only calls, with exponential backoff; rpc errors are returned as is
+struct RetryTransport {
+    inner: SimpleHttpTransport,
+}
+
+impl RetryTransport {
+    // `read_only`: safe to resend after a transport failure
+    fn retry<T>(&self, method: &str, read_only: bool, mut send: impl FnMut() -> Result<T, jsonrpc::Error>, is_warmup: impl Fn(&T) -> bool) -> Result<T, jsonrpc::Error> {
+        let mut backoff = config::RPC_RETRY_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            let res = send();
+            let transient = match &res {
+                Ok(v) => is_warmup(v),
+                Err(jsonrpc::Error::Transport(_)) => read_only,
+                Err(_) => false,
+            };
+            if !transient || attempt >= config::RPC_RETRIES {
+                return res
+            };
+            attempt += 1;
+            match &res {
+                Err(e) => warn!("rpc {method}: {}, retry {attempt}/{} in {backoff}ms", e, config::RPC_RETRIES),
+                _ => warn!("rpc {method}: bitcoind warming up, retry {attempt}/{} in {backoff}ms", config::RPC_RETRIES),
+            };
+            std::thread::sleep(Duration::from_millis(backoff));
+            backoff *= 2;
+        }
+    }
+}
+
+fn in_warmup(resp: &Response) -> bool {
+    resp.error.as_ref().map_or(false, |e| e.code == RPC_IN_WARMUP)
+}
+
+impl Transport for RetryTransport {
+    fn send_request(&self, req: Request) -> Result<Response, jsonrpc::Error> {
+        self.retry(req.method, is_read_only(req.method), || self.inner.send_request(req.clone()), in_warmup)
+    }
+
+    fn send_batch(&self, reqs: &[Request]) -> Result<Vec<Response>, jsonrpc::Error> {
+        let read_only = reqs.iter().all(|req| is_read_only(req.method));
+        self.retry("batch", read_only, || self.inner.send_batch(reqs), |resps| resps.iter().any(in_warmup))
+    }
+
+    fn fmt_target(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.fmt_target(f)
+    }
+}
+
+fn new_client() -> Result<Client, String> {
+    let transport = match SimpleHttpTransport::builder().url(config::RPC_URL) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("invalid rpc url: {}", e)),
+    };
+    let transport = transport
+        .auth(config::RPCUSER, Some(config::RPCPASSWORD))
+        .timeout(Duration::from_secs(config::RPC_TIMEOUT))
+        .build();
+    let client = jsonrpc::Client::with_transport(RetryTransport { inner: transport });
+    Ok(Client::from_jsonrpc(client))
+}
+
+// long-lived clients keep their http connection open between calls, handed out round robin;
+// cheap to clone, shared by the http handlers through app_data
+#[derive(Clone)]
+pub struct RpcPool {
+    clients: Arc<Vec<RpcClient>>,
+    next: Arc<AtomicUsize>,
+}
+
+impl RpcPool {
+    pub fn new(size: usize) -> Result<Self, String> {
+        let mut clients = vec![];
+        for _ in 0..size.max(1) {
+            clients.push(Arc::new(new_client()?));
+        }
+        Ok(RpcPool { clients: Arc::new(clients), next: Arc::new(AtomicUsize::new(0)) })
+    }
+
+    pub fn get(&self) -> RpcClient {
+        let i = self.next.fetch_add(1, Ordering::Relaxed) % self.clients.len();
+        self.clients[i].clone()
+    }
+}
+
+static SHARED_POOL: Lazy<Result<RpcPool, String>> = Lazy::new(|| RpcPool::new(config::RPC_POOL_SIZE));
+
+// the pool used by the http handlers, the scheduler & the watchtower
+pub fn shared() -> Result<RpcPool, String> {
+    SHARED_POOL.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_read_only_calls_are_resent() {
+        assert!(is_read_only("getblockcount"));
+        assert!(is_read_only("getrawtransaction"));
+        assert!(is_read_only("gettxout"));
+        assert!(!is_read_only("sendtoaddress"));
+        assert!(!is_read_only("sendrawtransaction"));
+        assert!(!is_read_only("generatetoaddress"));
+        assert!(!is_read_only("signrawtransactionwithwallet"));
+    }
+}
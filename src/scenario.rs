@@ -0,0 +1,276 @@
+use std::time::Instant;
+use bitcoin::{Address, EcdsaSighashType, PublicKey, Transaction, Txid, secp256k1::Keypair};
+use bitcoincore_rpc::Client;
+use bitvm::bridge::{contexts::base::generate_keys_from_secret, scripts::generate_pay_to_pubkey_script_address};
+use log::{info, error};
+use crate::{config, faucet, rpc::RpcClient, sql::{self, ScenarioStep, UserData}, steps, transactions, utils, workers};
+
+// one-click demos of POST /scenarios: every run gets a workflow of its own, owned by a throwaway
+// key the server signs the user's peg-in & kickoff-1 with, later steps are those of the /send-* endpoints
+pub const SCENARIOS: [&str; 3] = ["take-1", "take-2", "disprove"];
+
+pub fn steps(scenario: &str) -> Option<Vec<&'static str>> {
+    let mut steps = vec!["faucet", "peg-in", "kickoff-1", "kickoff-2"];
+    match scenario {
+        "take-1" => steps.push("take-1"),
+        "take-2" => steps.extend(["challenge", "assert", "take-2"]),
+        "disprove" => steps.extend(["challenge", "assert", "disprove"]),
+        _ => return None,
+    };
+    Some(steps)
+}
+
+// the user of a scenario run, the faucet pays to its p2wsh(p2pk) address
+#[derive(Clone)]
+pub struct DemoUser {
+    pub address: Address,
+    keypair: Keypair,
+    public_key: PublicKey,
+}
+
+impl DemoUser {
+    pub fn generate() -> Self {
+        let (_, keypair, public_key) = generate_keys_from_secret(config::network(), &utils::random_hex(32));
+        let address = generate_pay_to_pubkey_script_address(config::network(), &public_key);
+        DemoUser { address, keypair, public_key }
+    }
+
+    // sign the faucet output spent by input 0
+    fn sign(&self, rpc: &Client, tx: &mut Transaction) -> Result<(), String> {
+        let prevout = tx.input[0].previous_output;
+        let value = utils::get_utxo_value(rpc, prevout.txid, prevout.vout)?;
+        utils::sign_p2wsh_p2pk_input(tx, 0, &self.keypair, &self.public_key, value, EcdsaSighashType::All);
+        Ok(())
+    }
+}
+
+fn required<T>(v: Option<T>, name: &str) -> Result<T, String> {
+    match v {
+        Some(v) => Ok(v),
+        _ => Err(format!("workflow missing {name}")),
+    }
+}
+
+// sign the user's tx spending a faucet output & broadcast it
+async fn sign_and_broadcast(user: &DemoUser, rpc: &RpcClient, tx_name: &'static str, prepare: fn(&Client, Txid, u32) -> Result<Transaction, String>, (txid, vout): (Txid, u32)) -> Result<transactions::Broadcast, String> {
+    let (task_user, task_rpc) = (user.clone(), rpc.clone());
+    let tx = workers::run_blocking(move || {
+        let mut tx = prepare(&task_rpc, txid, vout)?;
+        task_user.sign(&task_rpc, &mut tx)?;
+        Ok(tx)
+    }).await?;
+    transactions::broadcast_after_timelock(rpc, &tx, tx_name).await
+}
+
+// runs in the background, the workflow stays locked until the scenario is done or failed
+pub async fn run(scenario_id: i32, workflow_id: i32, user: DemoUser, rpc: RpcClient) {
+    let res = run_steps(scenario_id, workflow_id, &user, &rpc).await;
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("scenario {scenario_id}: fail to connect db: {}", e);
+            return
+        }
+    };
+    if let Err(e) = sql::unlock_workflow(&db, workflow_id) {
+        error!("scenario {scenario_id}: fail to unlock workflow {workflow_id}: {}", e);
+    };
+    let mut data = match sql::get_scenario(&db, scenario_id) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            error!("scenario {scenario_id}: does not exist");
+            return
+        },
+        Err(e) => {
+            error!("scenario {scenario_id}: fail to get scenario: {}", e);
+            return
+        }
+    };
+    match res {
+        Ok(_) => {
+            info!("scenario {scenario_id}: {} done", data.scenario);
+            data.status = sql::SCENARIO_DONE.to_string();
+        },
+        Err(e) => {
+            error!("scenario {scenario_id}: {}", e);
+            data.status = sql::SCENARIO_FAILED.to_string();
+            data.error = Some(e);
+        },
+    };
+    data.updated_at = faucet::now();
+    if let Err(e) = sql::update_scenario(&db, scenario_id, &data) {
+        error!("scenario {scenario_id}: fail to update scenario: {}", e);
+    };
+}
+
+async fn run_steps(scenario_id: i32, workflow_id: i32, user: &DemoUser, rpc: &RpcClient) -> Result<(), String> {
+    let db = sql::open_db()?;
+    let mut data = match sql::get_scenario(&db, scenario_id)? {
+        Some(v) => v,
+        _ => return Err(format!("scenario {scenario_id} does not exist")),
+    };
+    let mut user_data = match sql::get_user_data(&db, workflow_id)? {
+        Some(v) => v,
+        _ => return Err(format!("workflow {workflow_id} does not exisit")),
+    };
+    let steps = match steps(&data.scenario) {
+        Some(v) => v,
+        _ => return Err(format!("unknown scenario: {}", data.scenario)),
+    };
+    user_data.fake_index = data.fake_index;
+
+    for step in steps {
+        let start = Instant::now();
+        let res = run_step(workflow_id, user, rpc, &mut user_data, step).await;
+        // saved on failure too: a tx scheduled for its timelock is noted for the scheduler
+        sql::update_user_data(&db, workflow_id, &user_data)?;
+        let txid = match res {
+            Ok(v) => v,
+            Err(e) => return Err(format!("{step}: {e}")),
+        };
+        data.steps.push(ScenarioStep {
+            name: step.to_string(),
+            txid,
+            duration_ms: start.elapsed().as_millis() as u64,
+        });
+        data.updated_at = faucet::now();
+        sql::update_scenario(&db, scenario_id, &data)?;
+        info!("scenario {scenario_id}: {step} {txid}");
+    }
+    Ok(())
+}
+
+// the steps after kickoff_1 are those of the /send-* endpoints (see steps), the scenario goes on
+// only once a step's tx is broadcast: it waits for the scheduler while the tx's timelock matures
+async fn run_step(workflow_id: i32, user: &DemoUser, rpc: &RpcClient, user_data: &mut UserData, step: &str) -> Result<Txid, String> {
+    if step != "faucet" {
+        steps::check_stage(step, user_data.status)?;
+    };
+    let txid = match step {
+        "faucet" => {
+            let (task_rpc, address) = (rpc.clone(), user.address.clone());
+            let (faucet_1, faucet_2) = workers::run_blocking(move || transactions::faucet(&task_rpc, &address)).await?;
+            faucet::record_payout();
+            user_data.faucet_1 = Some((faucet_1.txid, faucet_1.vout));
+            user_data.faucet_2 = Some((faucet_2.txid, faucet_2.vout));
+            user_data.status = sql::STATUS::FAUCET as u8;
+            faucet_1.txid
+        },
+        "peg-in" => {
+            let faucet_1 = required(user_data.faucet_1, "faucet_1")?;
+            let peg_in_txid = sign_and_broadcast(user, rpc, "peg_in", transactions::peg_in_prepare, faucet_1).await?.sent()?;
+            user_data.pegin = Some(peg_in_txid);
+            user_data.status = sql::STATUS::PEGIN as u8;
+            peg_in_txid
+        },
+        "kickoff-1" => {
+            let faucet_2 = required(user_data.faucet_2, "faucet_2")?;
+            let kick_off_1_txid = sign_and_broadcast(user, rpc, "kick_off_1", transactions::kickoff_1_prepare, faucet_2).await?.sent()?;
+            user_data.kickoff_1 = Some(kick_off_1_txid);
+            user_data.status = sql::STATUS::KICKOFF1 as u8;
+            kick_off_1_txid
+        },
+        "kickoff-2" => {
+            let res = steps::kick_off_2(rpc.clone(), user_data).await?;
+            wait_broadcast(workflow_id, user_data, res).await?
+        },
+        "challenge" => {
+            let res = steps::challenge(rpc.clone(), user_data).await?;
+            wait_broadcast(workflow_id, user_data, res).await?
+        },
+        "take-1" => {
+            let res = steps::take_1(rpc.clone(), user_data, user.address.clone()).await?;
+            wait_broadcast(workflow_id, user_data, res).await?
+        },
+        "assert" => steps::assert(rpc.clone(), user_data).await?.0,
+        "take-2" => {
+            let res = steps::take_2(rpc.clone(), user_data, user.address.clone()).await?;
+            wait_broadcast(workflow_id, user_data, res).await?
+        },
+        "disprove" => {
+            let res = steps::disprove(rpc.clone(), user_data).await?;
+            wait_broadcast(workflow_id, user_data, res).await?
+        },
+        _ => return Err(format!("unknown step: {step}")),
+    };
+    Ok(txid)
+}
+
+// a tx scheduled for its timelock is broadcast & recorded in the workflow by the scheduler
+// (see scheduler::settle_workflow), reload the workflow once it settled
+async fn wait_broadcast(workflow_id: i32, user_data: &mut UserData, res: transactions::Broadcast) -> Result<Txid, String> {
+    let (txid, height) = match res {
+        transactions::Broadcast::Sent(txid, _) => return Ok(txid),
+        transactions::Broadcast::Scheduled(txid, height, _) => (txid, height),
+    };
+    info!("scenario: workflow {workflow_id} waits for {txid}, scheduled at height {height}");
+    let db = sql::open_db()?;
+    // the scheduler finds the workflow through its `scheduled` tx
+    sql::update_user_data(&db, workflow_id, user_data)?;
+    let status = user_data.status;
+    loop {
+        utils::wait(config::SCHEDULER_INTERVAL).await;
+        let data = match sql::get_user_data(&db, workflow_id)? {
+            Some(v) => v,
+            _ => return Err(format!("workflow {workflow_id} does not exisit")),
+        };
+        if data.scheduled.is_some() {
+            continue
+        };
+        *user_data = data;
+        // recording the tx moves the status on, a dropped tx leaves it
+        if user_data.status == status {
+            return Err(format!("scheduled tx {txid} dropped by the scheduler"))
+        };
+        return Ok(txid)
+    }
+}
+
+// a restart cuts the running scenarios short, their workflows are left to the admin endpoints
+pub fn fail_interrupted() {
+    let res = sql::open_db().and_then(|db| sql::fail_running_scenarios(&db, "interrupted by a restart", faucet::now()));
+    if let Err(e) = res {
+        error!("scenario: fail to mark interrupted scenarios: {}", e);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the status a step leaves the workflow at
+    fn status_after(step: &str) -> u8 {
+        let status = match step {
+            "faucet" => sql::STATUS::FAUCET,
+            "peg-in" => sql::STATUS::PEGIN,
+            "kickoff-1" => sql::STATUS::KICKOFF1,
+            "kickoff-2" => sql::STATUS::KICKOFF2,
+            "challenge" => sql::STATUS::CHALLENGE,
+            "take-1" => sql::STATUS::TAKE1,
+            "assert" => sql::STATUS::ASSERT,
+            "take-2" => sql::STATUS::TAKE2,
+            "disprove" => sql::STATUS::DISPROVE,
+            _ => panic!("unknown step: {step}"),
+        };
+        status as u8
+    }
+
+    #[test]
+    fn steps_follow_the_workflow_stages() {
+        for scenario in SCENARIOS {
+            let names = steps(scenario).unwrap();
+            assert_eq!(names[0], "faucet");
+            assert_eq!(names.last(), Some(&scenario));
+            let mut status = status_after("faucet");
+            for step in names.iter().skip(1) {
+                if let Err(e) = steps::check_stage(step, status) {
+                    panic!("{scenario}: {step}: {e}");
+                };
+                status = status_after(step);
+                // a step can't run twice
+                assert!(steps::check_stage(step, status).is_err(), "{scenario}: {step} runs twice");
+            }
+        }
+        assert_eq!(steps("take-3"), None);
+    }
+}
@@ -0,0 +1,217 @@
+use bitcoin::Txid;
+use bitcoincore_rpc::Client;
+use rusqlite::Connection;
+use log::{info, warn, error};
+use crate::{config, sql, transactions, utils};
+
+// broadcast txs handed over by `transactions::broadcast_after_timelock` once their timelocks mature,
+// and bump the fee of broadcast txs stuck in the mempool
+pub fn start() {
+    let res = std::thread::Builder::new()
+        .name("scheduler".to_string())
+        .spawn(|| {
+            let rt = tokio::runtime::Runtime::new().expect("fail to create scheduler runtime");
+            rt.block_on(run());
+        });
+    if let Err(e) = res {
+        error!("fail to start scheduler: {}", e);
+    };
+}
+
+pub async fn run() {
+    info!("scheduler: started, checking every {}s", config::SCHEDULER_INTERVAL);
+    loop {
+        if let Err(e) = broadcast_due().await {
+            error!("scheduler: {}", e);
+        };
+        if let Err(e) = bump_stuck().await {
+            error!("scheduler: {}", e);
+        };
+        utils::wait(config::SCHEDULER_INTERVAL).await;
+    }
+}
+
+async fn broadcast_due() -> Result<(), String> {
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to connect db: {}", e))
+    };
+    let rpc = match utils::new_rpc_client().await {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to connect bitcoind: {}", e))
+    };
+    let tip = match utils::get_block_count(&rpc) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    // a tx scheduled at height H can enter the mempool once the tip reaches H - 1
+    let due = match sql::get_due_scheduled_txs(&db, tip + 1) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    for (txid, name, tx_hex) in due.iter() {
+        broadcast(&db, &rpc, txid, name, tx_hex);
+    }
+    Ok(())
+}
+
+async fn bump_stuck() -> Result<(), String> {
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to connect db: {}", e))
+    };
+    let rpc = match utils::new_rpc_client().await {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to connect bitcoind: {}", e))
+    };
+    let tip = match utils::get_block_count(&rpc) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    let pending = match sql::get_pending_txs(&db) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    for (txid, name, tx_hex, height) in pending.iter() {
+        let tx = match utils::tx_from_hex(tx_hex) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("scheduler: stop watching undecodable {name} tx {txid}: {}", e);
+                let _ = sql::remove_pending_tx(&db, txid);
+                continue
+            }
+        };
+        match utils::get_tx_confirmations(&rpc, tx.compute_txid()) {
+            Ok(0) => {},
+            Ok(_) => {
+                info!("scheduler: {name} tx {txid} confirmed");
+                let _ = sql::remove_pending_tx(&db, txid);
+                continue
+            },
+            Err(e) => {
+                warn!("scheduler: fail to get {name} tx {txid} confirmations: {}", e);
+                continue
+            }
+        };
+        if !config::CPFP_AUTO_BUMP || tip < height + config::CPFP_STUCK_BLOCKS {
+            continue
+        };
+        match transactions::bump_fee(&rpc, &tx, name).await {
+            Ok(child_txid) => info!("scheduler: {name} tx {txid} stuck, bumped by {child_txid}"),
+            Err(e) => warn!("scheduler: fail to bump stuck {name} tx {txid}: {}", e),
+        };
+        // wait another CPFP_STUCK_BLOCKS before the next attempt
+        if let Err(e) = sql::add_pending_tx(&db, &tx.compute_txid(), name, tx_hex, tip) {
+            error!("scheduler: {}", e);
+        };
+    }
+    Ok(())
+}
+
+fn broadcast(db: &Connection, rpc: &Client, txid: &str, name: &str, tx_hex: &str) {
+    let tx = match utils::tx_from_hex(tx_hex) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("scheduler: drop undecodable {name} tx {txid}: {}", e);
+            let _ = sql::remove_scheduled_tx(db, txid);
+            settle_workflow(db, txid, false);
+            return
+        }
+    };
+    // fees may have risen since the tx was scheduled
+    let res = match transactions::get_fee_info(rpc, &tx) {
+        Ok(fee_info) => transactions::broadcast_at_target_fee(rpc, &tx, name, &fee_info),
+        Err(e) => Err(e),
+    };
+    match res {
+        Ok(_) => info!("scheduler: {name} tx {txid} broadcast"),
+        // a restart between the broadcast & its removal from the schedule, the tx is out
+        Err(e) if is_already_broadcast(&e) => info!("scheduler: {name} tx {txid} already broadcast: {}", e),
+        // an input spent by a competing branch will never succeed, keep retrying anything else
+        Err(e) if is_conflicted(&e) => {
+            warn!("scheduler: drop {name} tx {txid}: {}", e);
+            if let Err(e) = sql::remove_scheduled_tx(db, txid) {
+                error!("scheduler: {}", e);
+            };
+            settle_workflow(db, txid, false);
+            return
+        },
+        Err(e) => {
+            warn!("scheduler: fail to broadcast {name} tx {txid}, retry later: {}", e);
+            return
+        },
+    };
+    if config::network() == bitcoin::Network::Regtest {
+        if let Err(e) = utils::mint_block(rpc, 1) {
+            error!("scheduler: fail to mint block: {}", e);
+        };
+    } else if let Err(e) = transactions::watch_confirmation(rpc, &tx, name) {
+        error!("scheduler: {}", e);
+    };
+    if let Err(e) = sql::remove_scheduled_tx(db, txid) {
+        error!("scheduler: {}", e);
+    };
+    settle_workflow(db, txid, true);
+}
+
+fn is_already_broadcast(err: &str) -> bool {
+    err.contains("txn-already-in-mempool") || err.contains("txn-already-known")
+        || err.contains("already in block chain") || err.contains("outputs already in utxo set")
+}
+
+fn is_conflicted(err: &str) -> bool {
+    err.contains("missingorspent") || err.contains("txn-mempool-conflict")
+}
+
+// the workflow keeps its status while a tx waits here, record the tx once broadcast or forget it once dropped
+fn settle_workflow(db: &Connection, txid: &str, broadcast: bool) {
+    let txid = match txid.parse::<Txid>() {
+        Ok(v) => v,
+        Err(_) => return
+    };
+    let workflow_id = match sql::get_workflow_id_by_txid(db, "scheduled.txid", &txid) {
+        Ok(Some(v)) => v,
+        Ok(None) => return,
+        Err(e) => {
+            error!("scheduler: fail to find workflow of {txid}: {}", e);
+            return
+        }
+    };
+    let mut user_data = match sql::get_user_data(db, workflow_id) {
+        Ok(Some(v)) => v,
+        Ok(None) => return,
+        Err(e) => {
+            error!("scheduler: fail to get workflow {workflow_id}: {}", e);
+            return
+        }
+    };
+    let scheduled = match user_data.scheduled.take() {
+        Some(v) => v,
+        None => return
+    };
+    if broadcast {
+        if let Err(e) = user_data.record_tx(&scheduled.kind, scheduled.txid) {
+            error!("scheduler: fail to record {} tx {txid} of workflow {workflow_id}: {}", scheduled.kind, e);
+            return
+        };
+    };
+    if let Err(e) = sql::update_user_data(db, workflow_id, &user_data) {
+        error!("scheduler: fail to update workflow {workflow_id}: {}", e);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broadcast_errors() {
+        assert!(is_already_broadcast("txn-already-in-mempool"));
+        assert!(is_already_broadcast("txn-already-known"));
+        assert!(is_already_broadcast("Transaction already in block chain"));
+        assert!(!is_conflicted("txn-already-in-mempool"));
+        assert!(is_conflicted("bad-txns-inputs-missingorspent"));
+        assert!(is_conflicted("txn-mempool-conflict"));
+        assert!(!is_already_broadcast("non-BIP68-final") && !is_conflicted("non-BIP68-final"));
+    }
+}
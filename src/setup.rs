@@ -6,8 +6,9 @@ use bitvm::bridge::groth16;
 use bitvm::chunk;
 use bitvm::groth16::g16;
 use bitvm::treepp::*;
+use bitcoin_hashes::{sha256, Hash};
 use log::{info, warn};
-use crate::{config, utils};
+use crate::{config, utils, transactions::WotsKeySet};
 
 pub fn check_setup() -> bool {
     let mut flag = true;
@@ -33,19 +34,7 @@ pub fn check_setup() -> bool {
     //     flag = false;
     // }
     
-    let tapscript_dir = config::TAPSCRIPT_PATH;
-    let index = g16::N_TAPLEAVES - 1;
-    let last_tapscript_path = &format!("{}/tapscript_{index}.json", tapscript_dir);
-    let already_generated = match metadata(last_tapscript_path) {
-        Ok(meta) => {
-            if meta.len() > 0 {
-                true
-            } else {
-                false
-            }
-        },
-        Err(_) => false,
-    };
+    let (already_generated, already_signed) = get_setup_manifest();
     if already_generated {
         info!("Generate tapscripts is done :)");
     } else {
@@ -53,21 +42,6 @@ pub fn check_setup() -> bool {
         flag = false;
     };
 
-    let sigs_dir = config::WOTS_SIGNATURE_PATH;
-    let index = g16::N_VERIFIER_FQS + g16::N_VERIFIER_HASHES + g16::N_VERIFIER_PUBLIC_INPUTS - 1;
-    let last_sig_path = &format!("{}/signed_assertion_{index}.json", sigs_dir);
-    let already_signed = match metadata(last_sig_path) {
-        Ok(meta) => {
-            if meta.len() > 0 {
-                true
-            } else {
-                false
-            }
-        },
-        Err(_) => {
-            false
-        },
-    };
     if already_signed {
         info!("Generate WotsSignature is done :)");
     } else {
@@ -78,6 +52,22 @@ pub fn check_setup() -> bool {
     flag
 }
 
+fn is_non_empty_file(path: &str) -> bool {
+    match metadata(path) {
+        Ok(meta) => meta.len() > 0,
+        Err(_) => false,
+    }
+}
+
+// (tapscripts generated, wots signatures generated) of the default setup
+pub fn get_setup_manifest() -> (bool, bool) {
+    let index = g16::N_TAPLEAVES - 1;
+    let last_tapscript_path = format!("{}/tapscript_{index}.json", config::TAPSCRIPT_PATH);
+    let index = g16::N_VERIFIER_FQS + g16::N_VERIFIER_HASHES + g16::N_VERIFIER_PUBLIC_INPUTS - 1;
+    let last_sig_path = format!("{}/signed_assertion_{index}.json", config::WOTS_SIGNATURE_PATH);
+    (is_non_empty_file(&last_tapscript_path), is_non_empty_file(&last_sig_path))
+}
+
 pub fn setup_all() {
     info!("compiling...... (this may take serveral minutes)");
     let now = SystemTime::now();
@@ -108,30 +98,22 @@ pub fn setup_all() {
 }
 
 pub fn compile() { 
+    compile_for(config::COMPILE_PATH, config::PROOF_PATH);
+}
+
+// compile the verifier chunks of the vk stored in `proof_path`
+pub fn compile_for(compile_dir: &str, proof_path: &str) { 
     // create data dir
-    let compile_dir = config::COMPILE_PATH;
     if Path::new(compile_dir).exists() {
-        let index = g16::N_TAPLEAVES - 1;
-        let last_tapnode_path = &format!("{}/tapnode_{index}.json", compile_dir);
-        let already_compiled = match metadata(last_tapnode_path) {
-            Ok(meta) => {
-                if meta.len() > 0 {
-                    true
-                } else {
-                    false
-                }
-            },
-            Err(_) => false,
-        };
-        if already_compiled {
+        if is_compiled(compile_dir) {
             return;
         }
     } else {
-        fs::create_dir(compile_dir).expect("fail to create data dir");
+        fs::create_dir_all(compile_dir).expect("fail to create data dir");
     }
 
     // generate & write compile data to file 
-    let (vk, _, _) = groth16::load_proof_from_file(config::PROOF_PATH);
+    let (vk, _, _) = groth16::load_proof_from_file(proof_path);
     let ops_scripts = chunk::api::api_compile(&vk);
     for i in 0..ops_scripts.len() {
         let mut script_cache = HashMap::new();
@@ -142,34 +124,26 @@ pub fn compile() {
 
 
 pub fn generate_tapscripts() {
+    generate_tapscripts_for(config::TAPSCRIPT_PATH, config::COMPILE_PATH, config::PROOF_PATH, config::WOTS_SECRET);
+}
+
+// generate the connector_c tapscripts locked to the wots key derived from `wots_secret`
+pub fn generate_tapscripts_for(tapscript_dir: &str, compile_dir: &str, proof_path: &str, wots_secret: &str) {
     // compile must be done before generate tapscripts
-    compile();
+    compile_for(compile_dir, proof_path);
     // create data dir
-    let tapscript_dir = config::TAPSCRIPT_PATH;
     if Path::new(tapscript_dir).exists() {
-        let index = g16::N_TAPLEAVES - 1;
-        let last_tapscript_path = &format!("{}/tapscript_{index}.json", tapscript_dir);
-        let already_generated = match metadata(last_tapscript_path) {
-            Ok(meta) => {
-                if meta.len() > 0 {
-                    true
-                } else {
-                    false
-                }
-            },
-            Err(_) => false,
-        };
-        if already_generated {
+        if is_tapscripts_generated(tapscript_dir) {
             return;
         }
     } else {
-        fs::create_dir(tapscript_dir).expect("fail to create data dir");
+        fs::create_dir_all(tapscript_dir).expect("fail to create data dir");
     }
 
     // load compile data
     let mut op_scripts = vec![];
     for index in 0..g16::N_TAPLEAVES {
-        let read = chunk::test_utils::read_scripts_from_file(&format!("{}/tapnode_{index}.json", config::COMPILE_PATH));
+        let read = chunk::test_utils::read_scripts_from_file(&format!("{}/tapnode_{index}.json", compile_dir));
         let read_scr = read.get(&(index as u32)).unwrap();
         assert_eq!(read_scr.len(), 1);
         let tap_node = read_scr[0].clone();
@@ -178,7 +152,7 @@ pub fn generate_tapscripts() {
     let ops_scripts: [Script; g16::N_TAPLEAVES] = op_scripts.try_into().unwrap(); 
     
     // generate & write tapscripts to file
-    let (wots_pk, _) = groth16::generate_wots_keys_from_secrets(config::WOTS_SECRET);
+    let (wots_pk, _) = groth16::generate_wots_keys_from_secrets(wots_secret);
     let taps = chunk::api::generate_tapscripts(wots_pk, &ops_scripts);
     for i in 0..taps.len() {
         let mut script_cache = HashMap::new();
@@ -189,33 +163,205 @@ pub fn generate_tapscripts() {
 
 
 pub fn generate_signed_assertions() {
+    generate_signed_assertions_for(config::WOTS_SIGNATURE_PATH, config::PROOF_PATH, config::WOTS_SECRET);
+}
+
+// sign the assertions of the proof in `proof_path` with the wots key derived from `wots_secret`
+pub fn generate_signed_assertions_for(sigs_dir: &str, proof_path: &str, wots_secret: &str) {
     // create data dir 
-    let sigs_dir = config::WOTS_SIGNATURE_PATH;
     if Path::new(sigs_dir).exists() {
-        let index = g16::N_VERIFIER_FQS + g16::N_VERIFIER_HASHES + g16::N_VERIFIER_PUBLIC_INPUTS - 1;
-        let last_sig_path = &format!("{}/signed_assertion_{index}.json", sigs_dir);
-        let already_signed = match metadata(last_sig_path) {
-            Ok(meta) => {
-                if meta.len() > 0 {
-                    true
-                } else {
-                    false
-                }
-            },
-            Err(_) => false,
-        };
-        if already_signed {
+        if is_signed_assertions_generated(sigs_dir) {
             return;
         }
     } else {
-        fs::create_dir(sigs_dir).expect("fail to create data dir");
+        fs::create_dir_all(sigs_dir).expect("fail to create data dir");
     }
 
     // generate & write wots sigs to file
-    let (vk, proof, pubin) = groth16::load_proof_from_file(config::PROOF_PATH);
-    let (_, wots_sk) = groth16::generate_wots_keys_from_secrets(config::WOTS_SECRET);
+    let (vk, proof, pubin) = groth16::load_proof_from_file(proof_path);
+    let (_, wots_sk) = groth16::generate_wots_keys_from_secrets(wots_secret);
     utils::suppress_output(|| {
-        groth16::generate_signed_assertions(proof, pubin, &wots_sk, &vk, true, &sigs_dir);
+        groth16::generate_signed_assertions(proof, pubin, &wots_sk, &vk, true, sigs_dir);
     });
 }
 
+// per-workflow setup: tapscripts & signed assertions for the workflow's own wots key
+pub fn check_workflow_setup(key_set: &WotsKeySet) -> bool {
+    is_tapscripts_generated(&key_set.tapscript_dir) && is_signed_assertions_generated(&key_set.signature_dir)
+}
+
+pub fn setup_workflow(key_set: &WotsKeySet) {
+    info!("generating tapscripts for {}...... (this may take serveral minutes)", key_set.tapscript_dir);
+    let now = SystemTime::now();
+    generate_tapscripts_for(&key_set.tapscript_dir, &key_set.compile_dir, &key_set.proof_path, &key_set.wots_secret);
+    let duration = match now.elapsed() {
+        Ok(v) => v.as_secs().to_string(),
+        Err(_) => "?".to_string(),
+    };
+    info!("done. [{duration} s]");
+
+    info!("generating wots_signatures for {}...... (this may take serveral minutes)", key_set.signature_dir);
+    let now = SystemTime::now();
+    generate_signed_assertions_for(&key_set.signature_dir, &key_set.proof_path, &key_set.wots_secret);
+    let duration = match now.elapsed() {
+        Ok(v) => v.as_secs().to_string(),
+        Err(_) => "?".to_string(),
+    };
+    info!("done. [{duration} s]");
+}
+
+// custom verifying keys, registered through the api
+// layout of a vk/proof file is the same as config::PROOF_PATH: {"0": vk, "1": proof, "2": public_inputs}
+pub fn vk_dir(vk_id: &str) -> String {
+    format!("{}/{vk_id}", config::VK_PATH)
+}
+
+pub fn vk_proof_path(vk_id: &str) -> String {
+    format!("{}/vk.json", vk_dir(vk_id))
+}
+
+pub fn vk_compile_dir(vk_id: &str) -> String {
+    format!("{}/compile", vk_dir(vk_id))
+}
+
+// vk ids end up in paths, only [A-Za-z0-9_-] is accepted
+pub fn is_valid_vk_id(vk_id: &str) -> bool {
+    !vk_id.is_empty() && vk_id.len() <= 64 && vk_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+pub fn is_vk_registered(vk_id: &str) -> bool {
+    is_valid_vk_id(vk_id) && Path::new(&vk_proof_path(vk_id)).exists()
+}
+
+pub fn is_vk_compiled(vk_id: &str) -> bool {
+    is_compiled(&vk_compile_dir(vk_id))
+}
+
+// store the vk (entry "0") and return its id, missing proof/public_inputs are filled from the dummy proof
+pub fn register_vk(vk_json: &serde_json::Value) -> Result<String, String> {
+    let vk_entry = match vk_json.get("0") {
+        Some(v) => v,
+        _ => return Err("missing verifying key (entry \"0\")".to_string()),
+    };
+    let vk_id = hex::encode(&sha256::Hash::hash(vk_entry.to_string().as_bytes()).to_byte_array()[..8]);
+    let mut content = match read_proof_json(config::PROOF_PATH) {
+        Ok(v) => v,
+        Err(e) => return Err(e),
+    };
+    content["0"] = vk_entry.clone();
+    for key in ["1", "2"] {
+        if let Some(v) = vk_json.get(key) {
+            content[key] = v.clone();
+        };
+    }
+    if let Err(e) = fs::create_dir_all(vk_dir(&vk_id)) {
+        return Err(format!("fail to create vk dir: {}", e))
+    };
+    if let Err(e) = fs::write(vk_proof_path(&vk_id), content.to_string()) {
+        return Err(format!("fail to write vk file: {}", e))
+    };
+    Ok(vk_id)
+}
+
+// compile a registered vk, the tapscripts are generated per workflow from the compiled chunks
+// with the workflow's own wots key (see WotsKeySet::with_proof & setup_workflow)
+pub fn setup_vk(vk_id: &str) {
+    info!("compiling vk {vk_id}...... (this may take serveral minutes)");
+    let now = SystemTime::now();
+    compile_for(&vk_compile_dir(vk_id), &vk_proof_path(vk_id));
+    let duration = match now.elapsed() {
+        Ok(v) => v.as_secs().to_string(),
+        Err(_) => "?".to_string(),
+    };
+    info!("done. [{duration} s]");
+}
+
+// store a proof (entries "1" & "2") for the given vk, return the path of the merged proof file
+pub fn store_workflow_proof(workflow_id: i32, vk_id: &str, proof_json: &serde_json::Value) -> Result<String, String> {
+    let proof_path = format!("{}/workflow_{workflow_id}.json", config::CUSTOM_PROOF_PATH);
+    match write_proof_file(&proof_path, Some(vk_id), proof_json) {
+        Ok(_) => Ok(proof_path),
+        Err(e) => Err(e),
+    }
+}
+
+// merge proof & public_inputs with the vk (dummy vk if `vk_id` is None) and write them to `proof_path`
+pub fn write_proof_file(proof_path: &str, vk_id: Option<&str>, proof_json: &serde_json::Value) -> Result<(), String> {
+    let vk_path = match vk_id {
+        Some(id) => vk_proof_path(id),
+        _ => config::PROOF_PATH.to_string(),
+    };
+    let mut content = match read_proof_json(&vk_path) {
+        Ok(v) => v,
+        Err(e) => return Err(e),
+    };
+    for key in ["1", "2"] {
+        match proof_json.get(key) {
+            Some(v) => content[key] = v.clone(),
+            _ => return Err(format!("missing entry \"{key}\" (1: proof, 2: public_inputs)")),
+        };
+    }
+    if let Some(dir) = Path::new(proof_path).parent() {
+        if let Err(e) = fs::create_dir_all(dir) {
+            return Err(format!("fail to create proof dir: {}", e))
+        };
+    };
+    match fs::write(proof_path, content.to_string()) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("fail to write proof file: {}", e)),
+    }
+}
+
+fn read_proof_json(proof_path: &str) -> Result<serde_json::Value, String> {
+    let content = match fs::read_to_string(proof_path) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to read {proof_path}: {}", e)),
+    };
+    match serde_json::from_str(&content) {
+        Ok(v) => Ok(v),
+        Err(e) => Err(format!("fail to deserialize {proof_path}: {}", e)),
+    }
+}
+
+fn is_compiled(compile_dir: &str) -> bool {
+    let index = g16::N_TAPLEAVES - 1;
+    let last_tapnode_path = &format!("{}/tapnode_{index}.json", compile_dir);
+    match metadata(last_tapnode_path) {
+        Ok(meta) => meta.len() > 0,
+        Err(_) => false,
+    }
+}
+
+fn is_tapscripts_generated(tapscript_dir: &str) -> bool {
+    let index = g16::N_TAPLEAVES - 1;
+    let last_tapscript_path = &format!("{}/tapscript_{index}.json", tapscript_dir);
+    match metadata(last_tapscript_path) {
+        Ok(meta) => meta.len() > 0,
+        Err(_) => false,
+    }
+}
+
+pub fn is_signed_assertions_generated(sigs_dir: &str) -> bool {
+    let index = g16::N_VERIFIER_FQS + g16::N_VERIFIER_HASHES + g16::N_VERIFIER_PUBLIC_INPUTS - 1;
+    let last_sig_path = &format!("{}/signed_assertion_{index}.json", sigs_dir);
+    match metadata(last_sig_path) {
+        Ok(meta) => meta.len() > 0,
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vk_id_charset() {
+        assert!(is_valid_vk_id("0a1b2c3d4e5f6789"));
+        assert!(is_valid_vk_id("my_vk-2"));
+        assert!(!is_valid_vk_id(""));
+        assert!(!is_valid_vk_id("../data-dir"));
+        assert!(!is_valid_vk_id("a/b"));
+        assert!(!is_valid_vk_id("vk.json"));
+        assert!(!is_valid_vk_id(&"a".repeat(65)));
+    }
+}
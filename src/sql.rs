@@ -1,5 +1,5 @@
-use rusqlite::{params, Connection, Result, OptionalExtension};
-use bitcoin::{Txid, Address, address::NetworkUnchecked};
+use rusqlite::{params, Connection, Result, OptionalExtension, Transaction, TransactionBehavior};
+use bitcoin::{Txid, Address, Amount, OutPoint, ScriptBuf, address::NetworkUnchecked};
 use serde::{Deserialize, Serialize};
 use serde_json;
 use crate::config;
@@ -15,22 +15,121 @@ pub enum STATUS {
     ASSERT = 7,
     TAKE2 = 8,
     DISPROVE = 9,
+    PEGIN_DEPOSIT = 10,
+    PEGIN_REFUND = 11,
+    START_TIME = 12,
+    START_TIME_TIMEOUT = 13,
+    KICKOFF_TIMEOUT = 14,
+    PEGOUT = 15,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+// pegged in, with or without a peg-out fronted by the operator, and not kicked off yet
+pub fn is_pegged_in(status: u8) -> bool {
+    status == STATUS::PEGIN as u8 || status == STATUS::PEGOUT as u8
+}
+
+// an input crowdfunding the challenge tx, signed SIGHASH_SINGLE|ANYONECANPAY
+// over its refund output, which sits at the same index as the input
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChallengeContribution {
+    pub outpoint: OutPoint,
+    pub amount: Amount,
+    pub witness: Vec<String>, // hex encoded witness items
+    pub refund_script_pubkey: ScriptBuf,
+    pub refund_amount: Amount,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct UserData {
     pub status: u8,
     pub fake_index: Option<u32>,
     pub faucet_1: Option<(Txid, u32)>,
     pub faucet_2: Option<(Txid, u32)>,
+    #[serde(default)]
+    pub pegin_deposit: Option<Txid>,
+    #[serde(default)]
+    pub pegin_refund: Option<Txid>,
     pub pegin: Option<Txid>,
+    #[serde(default)]
+    pub peg_out: Option<Txid>,
+    #[serde(default)]
+    pub withdrawer_evm_address: Option<String>,
+    #[serde(default)]
+    pub withdrawer_address: Option<String>,
     pub kickoff_1: Option<Txid>,
+    #[serde(default)]
+    pub start_time: Option<Txid>,
+    #[serde(default)]
+    pub start_time_timeout: Option<Txid>,
+    #[serde(default)]
+    pub kickoff_timeout: Option<Txid>,
     pub kickoff_2: Option<Txid>,
     pub challenge: Option<Txid>,
+    #[serde(default)]
+    pub challenge_contributions: Vec<ChallengeContribution>,
     pub assert: Option<Txid>,
     pub disprove: Option<Txid>,
     pub take_1: Option<Txid>,
     pub take_2: Option<Txid>,
+    #[serde(default)]
+    pub vk_id: Option<String>,
+    #[serde(default)]
+    pub proof_path: Option<String>,
+    #[serde(default)]
+    pub scheduled: Option<ScheduledTx>,
+}
+
+// a tx of the workflow waiting in the scheduler for its timelock, recorded once broadcast
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ScheduledTx {
+    pub kind: String,
+    pub txid: Txid,
+    pub height: u64,
+}
+
+impl UserData {
+    // record a broadcast tx of the workflow & move it to the matching status
+    pub fn record_tx(&mut self, tx_kind: &str, txid: Txid) -> Result<(), String> {
+        let status = match tx_kind {
+            "pegin-refund" => { self.pegin_refund = Some(txid); STATUS::PEGIN_REFUND },
+            "pegout" => { self.peg_out = Some(txid); STATUS::PEGOUT },
+            "kickoff2" => { self.kickoff_2 = Some(txid); STATUS::KICKOFF2 },
+            "start-time" => { self.start_time = Some(txid); STATUS::START_TIME },
+            "start-time-timeout" => { self.start_time_timeout = Some(txid); STATUS::START_TIME_TIMEOUT },
+            "kickoff-timeout" => { self.kickoff_timeout = Some(txid); STATUS::KICKOFF_TIMEOUT },
+            "challenge" => { self.challenge = Some(txid); STATUS::CHALLENGE },
+            "take1" => { self.take_1 = Some(txid); STATUS::TAKE1 },
+            "take2" => { self.take_2 = Some(txid); STATUS::TAKE2 },
+            "disprove" => { self.disprove = Some(txid); STATUS::DISPROVE },
+            _ => return Err(format!("unknown tx kind: {tx_kind}")),
+        };
+        self.status = status as u8;
+        Ok(())
+    }
+}
+
+pub const SCENARIO_RUNNING: &str = "running";
+pub const SCENARIO_DONE: &str = "done";
+pub const SCENARIO_FAILED: &str = "failed";
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ScenarioStep {
+    pub name: String,
+    pub txid: Txid,
+    pub duration_ms: u64,
+}
+
+// a run of POST /scenarios, see scenario::run
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ScenarioData {
+    pub scenario: String,
+    pub fake_index: Option<u32>,
+    pub workflow_id: Option<i32>,
+    pub status: String, // SCENARIO_RUNNING, SCENARIO_DONE or SCENARIO_FAILED
+    pub steps: Vec<ScenarioStep>,
+    pub error: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
 }
 
 pub fn open_db() -> Result<Connection, String> {
@@ -39,6 +138,17 @@ pub fn open_db() -> Result<Connection, String> {
         Ok(v) => v,
         Err(e) => return Err(format!("fail to open db: {}", e))
     };
+    // writers wait for each other (e.g. faucet::reserve) instead of failing with SQLITE_BUSY
+    if let Err(e) = db.busy_timeout(std::time::Duration::from_secs(5)) {
+        return Err(format!("fail to set db busy timeout: {}", e))
+    };
+    match create_tables(&db) {
+        Ok(_) => Ok(db),
+        Err(e) => Err(e)
+    }
+}
+
+pub fn create_tables(db: &Connection) -> Result<(), String> {
     let create_res = db.execute(
         "CREATE TABLE IF NOT EXISTS workflow (
             id      INTEGER PRIMARY KEY,
@@ -47,9 +157,132 @@ pub fn open_db() -> Result<Connection, String> {
             lock    INTEGER NOT NULL
         )", 
         []);
-    match create_res {
-        Ok(_) => Ok(db),
-        Err(e) => Err(format!("fail to try create table: {}", e))
+    if let Err(e) = create_res {
+        return Err(format!("fail to try create table: {}", e))
+    };
+    let create_res = db.execute(
+        "CREATE TABLE IF NOT EXISTS watchtower (
+            id      INTEGER PRIMARY KEY,
+            height  INTEGER NOT NULL
+        )", 
+        []);
+    if let Err(e) = create_res {
+        return Err(format!("fail to try create table: {}", e))
+    };
+    let create_res = db.execute(
+        "CREATE TABLE IF NOT EXISTS scheduled_tx (
+            txid    TEXT PRIMARY KEY,
+            name    TEXT NOT NULL,
+            tx      TEXT NOT NULL,
+            height  INTEGER NOT NULL
+        )", 
+        []);
+    if let Err(e) = create_res {
+        return Err(format!("fail to try create table: {}", e))
+    };
+    let create_res = db.execute(
+        "CREATE TABLE IF NOT EXISTS pending_tx (
+            txid    TEXT PRIMARY KEY,
+            name    TEXT NOT NULL,
+            tx      TEXT NOT NULL,
+            height  INTEGER NOT NULL
+        )", 
+        []);
+    if let Err(e) = create_res {
+        return Err(format!("fail to try create table: {}", e))
+    };
+    let create_res = db.execute(
+        "CREATE TABLE IF NOT EXISTS faucet_request (
+            id      INTEGER PRIMARY KEY,
+            addr    TEXT NOT NULL,
+            ip      TEXT NOT NULL,
+            amount  INTEGER NOT NULL,
+            time    INTEGER NOT NULL
+        )", 
+        []);
+    if let Err(e) = create_res {
+        return Err(format!("fail to try create table: {}", e))
+    };
+    let create_res = db.execute(
+        "CREATE TABLE IF NOT EXISTS faucet_whitelist (
+            addr    TEXT PRIMARY KEY
+        )", 
+        []);
+    if let Err(e) = create_res {
+        return Err(format!("fail to try create table: {}", e))
+    };
+    let create_res = db.execute(
+        "CREATE TABLE IF NOT EXISTS auth_challenge (
+            nonce       TEXT PRIMARY KEY,
+            addr        TEXT NOT NULL,
+            expires_at  INTEGER NOT NULL
+        )", 
+        []);
+    if let Err(e) = create_res {
+        return Err(format!("fail to try create table: {}", e))
+    };
+    let create_res = db.execute(
+        "CREATE TABLE IF NOT EXISTS auth_session (
+            token       TEXT PRIMARY KEY,
+            addr        TEXT NOT NULL,
+            expires_at  INTEGER NOT NULL
+        )", 
+        []);
+    if let Err(e) = create_res {
+        return Err(format!("fail to try create table: {}", e))
+    };
+    let create_res = db.execute(
+        "CREATE TABLE IF NOT EXISTS workflow_archive (
+            id          INTEGER PRIMARY KEY,
+            addr        TEXT NOT NULL,
+            data        TEXT,
+            archived_at INTEGER NOT NULL
+        )", 
+        []);
+    if let Err(e) = create_res {
+        return Err(format!("fail to try create table: {}", e))
+    };
+    let create_res = db.execute(
+        "CREATE TABLE IF NOT EXISTS scenario (
+            id      INTEGER PRIMARY KEY,
+            data    TEXT NOT NULL
+        )", 
+        []);
+    if let Err(e) = create_res {
+        return Err(format!("fail to try create table: {}", e))
+    };
+    let create_res = db.execute(
+        "CREATE TABLE IF NOT EXISTS admin_audit (
+            id      INTEGER PRIMARY KEY,
+            time    INTEGER NOT NULL,
+            ip      TEXT NOT NULL,
+            action  TEXT NOT NULL,
+            target  TEXT NOT NULL,
+            detail  TEXT NOT NULL,
+            outcome INTEGER NOT NULL DEFAULT 200
+        )", 
+        []);
+    if let Err(e) = create_res {
+        return Err(format!("fail to try create table: {}", e))
+    };
+    // audit logs created before outcomes were recorded
+    let has_outcome = match db.query_row("SELECT COUNT(*) FROM pragma_table_info('admin_audit') WHERE name = 'outcome'", [], |row| row.get::<_, i64>(0)) {
+        Ok(v) => v > 0,
+        Err(e) => return Err(format!("fail to query db: {}", e))
+    };
+    if !has_outcome {
+        if let Err(e) = db.execute("ALTER TABLE admin_audit ADD COLUMN outcome INTEGER NOT NULL DEFAULT 200", []) {
+            return Err(format!("fail to add audit outcome column: {}", e))
+        };
+    };
+    Ok(())
+}
+
+// take & release the write lock without changing anything
+pub fn check_writable(db: &Connection) -> Result<bool, String> {
+    match db.execute_batch("BEGIN IMMEDIATE; ROLLBACK;") {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("db is not writable: {}", e))
     }
 }
 
@@ -64,6 +297,18 @@ pub fn get_user_id(db: &Connection, addr: &Address) -> Result<Option<i32>, Strin
     }
 }
 
+// find the workflow whose `field` (e.g. "kickoff_1", "assert") equals txid
+pub fn get_workflow_id_by_txid(db: &Connection, field: &str, txid: &Txid) -> Result<Option<i32>, String> {
+    let mut stmt = match db.prepare("SELECT MAX(id) FROM workflow WHERE json_extract(data, ?1) = ?2") {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    match stmt.query_row(params![format!("$.{field}"), txid.to_string()], |row| row.get(0)) {
+        Ok(v) => Ok(v),
+        Err(e) => return Err(format!("fail to query db: {}", e))
+    }
+}
+
 pub fn get_user_data(db: &Connection, id: i32) -> Result<Option<UserData>, String> {
     let mut stmt = match db.prepare("SELECT data FROM workflow WHERE id = ?1") {
         Ok(v) => v,
@@ -100,14 +345,26 @@ pub fn new_user(db: &Connection, addr: &Address) -> Result<i32, String> {
         fake_index: None,
         faucet_1: None,
         faucet_2: None,
+        pegin_deposit: None,
+        pegin_refund: None,
         pegin: None,
+        peg_out: None,
+        withdrawer_evm_address: None,
+        withdrawer_address: None,
         kickoff_1: None,
+        start_time: None,
+        start_time_timeout: None,
+        kickoff_timeout: None,
         kickoff_2: None,
         challenge: None,
+        challenge_contributions: vec![],
         assert: None,
         disprove: None,
         take_1: None,
         take_2: None,
+        vk_id: None,
+        proof_path: None,
+        scheduled: None,
     };
     let insert_res = db.execute(
         "INSERT INTO workflow (addr, data, lock) values (?1, ?2, ?3)", 
@@ -117,16 +374,175 @@ pub fn new_user(db: &Connection, addr: &Address) -> Result<i32, String> {
             0
         ]);
     match insert_res {
-        Ok(_) => {
-            match get_user_id(db, addr) {
-                Ok(v) => Ok(v.unwrap()),
-                Err(e) => Err(e),
-            }
-        },
+        // an address may own several workflows, return the id of the row just created
+        Ok(_) => Ok(db.last_insert_rowid() as i32),
         Err(e) => Err(e.to_string()),
     }
 }
 
+// workflows owned by addr, newest first, optionally filtered by status
+pub fn list_user_workflows(db: &Connection, addr: &Address, status: Option<u8>, limit: u32, offset: u32) -> Result<Vec<(i32, UserData)>, String> {
+    let mut stmt = match db.prepare(
+        "SELECT id, data FROM workflow WHERE addr = ?1 AND (?2 IS NULL OR json_extract(data, '$.status') = ?2) ORDER BY id DESC LIMIT ?3 OFFSET ?4"
+    ) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    let rows = match stmt.query_map(params![serde_json::to_string(&addr).unwrap(), status, limit, offset], |row| {
+        Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?))
+    }) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to query db: {}", e))
+    };
+    let mut workflows = vec![];
+    for row in rows {
+        match row {
+            Ok((id, data)) => workflows.push((id, serde_json::from_str(&data).unwrap())),
+            Err(e) => return Err(format!("fail to read row: {}", e))
+        }
+    }
+    Ok(workflows)
+}
+
+#[derive(Clone)]
+pub struct WorkflowRow {
+    pub id: i32,
+    pub addr: String,
+    pub locked: bool,
+    pub data: UserData,
+}
+
+// admin search over all workflows, `txid` matches any txid recorded in the workflow
+pub fn search_workflows(db: &Connection, addr: Option<&Address>, status: Option<u8>, locked: Option<bool>, txid: Option<&str>, limit: u32, offset: u32) -> Result<Vec<WorkflowRow>, String> {
+    let mut stmt = match db.prepare(
+        "SELECT id, addr, lock, data FROM workflow
+        WHERE (?1 IS NULL OR addr = ?1)
+        AND (?2 IS NULL OR json_extract(data, '$.status') = ?2)
+        AND (?3 IS NULL OR lock = ?3)
+        AND (?4 IS NULL OR instr(data, ?4) > 0)
+        ORDER BY id DESC LIMIT ?5 OFFSET ?6"
+    ) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    let addr = addr.map(|a| serde_json::to_string(a).unwrap());
+    let locked = locked.map(|l| l as i32);
+    let rows = match stmt.query_map(params![addr, status, locked, txid, limit, offset], |row| {
+        Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?, row.get::<_, i32>(2)?, row.get::<_, String>(3)?))
+    }) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to query db: {}", e))
+    };
+    let mut workflows = vec![];
+    for row in rows {
+        match row {
+            Ok((id, addr, lock, data)) => workflows.push(WorkflowRow {
+                id,
+                addr: serde_json::from_str(&addr).unwrap_or(addr),
+                locked: lock != 0,
+                data: serde_json::from_str(&data).unwrap(),
+            }),
+            Err(e) => return Err(format!("fail to read row: {}", e))
+        }
+    }
+    Ok(workflows)
+}
+
+// workflows recording any of `txids` as a value of their data, newest first
+pub fn find_workflows_by_txids(db: &Connection, txids: &[Txid]) -> Result<Vec<WorkflowRow>, String> {
+    let mut stmt = match db.prepare(
+        "SELECT id, addr, lock, data FROM workflow w
+        WHERE EXISTS (SELECT 1 FROM json_tree(w.data) t WHERE t.atom IN (SELECT value FROM json_each(?1)))
+        ORDER BY id DESC"
+    ) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    let txids = serde_json::to_string(&txids.iter().map(|t| t.to_string()).collect::<Vec<_>>()).unwrap();
+    let rows = match stmt.query_map(params![txids], |row| {
+        Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?, row.get::<_, i32>(2)?, row.get::<_, String>(3)?))
+    }) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to query db: {}", e))
+    };
+    let mut workflows = vec![];
+    for row in rows {
+        match row {
+            Ok((id, addr, lock, data)) => workflows.push(WorkflowRow {
+                id,
+                addr: serde_json::from_str(&addr).unwrap_or(addr),
+                locked: lock != 0,
+                data: serde_json::from_str(&data).unwrap(),
+            }),
+            Err(e) => return Err(format!("fail to read row: {}", e))
+        }
+    }
+    Ok(workflows)
+}
+
+// move the workflow to workflow_archive, `time` in unix seconds
+// copy & delete in one transaction, a failed delete doesn't leave the workflow in both tables
+pub fn archive_workflow(db: &Connection, id: i32, time: u64) -> Result<bool, String> {
+    let tx = match Transaction::new_unchecked(db, TransactionBehavior::Immediate) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to begin transaction: {}", e))
+    };
+    let archived = match tx.execute(
+        "INSERT OR REPLACE INTO workflow_archive (id, addr, data, archived_at) SELECT id, addr, data, ?2 FROM workflow WHERE id = ?1",
+        params![id, time as i64],
+    ) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to archive workflow: {}", e))
+    };
+    if archived == 0 {
+        return Ok(false)
+    };
+    let deleted = delete_workflow(&tx, id)?;
+    match tx.commit() {
+        Ok(_) => Ok(deleted),
+        Err(e) => Err(format!("fail to commit archive: {}", e))
+    }
+}
+
+pub fn delete_workflow(db: &Connection, id: i32) -> Result<bool, String> {
+    match db.execute("DELETE FROM workflow WHERE id = ?1", params![id]) {
+        Ok(v) => Ok(v > 0),
+        Err(e) => Err(format!("fail to delete workflow: {}", e))
+    }
+}
+
+pub fn count_workflows_by_status(db: &Connection) -> Result<Vec<(u8, u32)>, String> {
+    let mut stmt = match db.prepare("SELECT json_extract(data, '$.status'), COUNT(*) FROM workflow GROUP BY 1") {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    let rows = match stmt.query_map([], |row| Ok((row.get::<_, u8>(0)?, row.get::<_, u32>(1)?))) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to query db: {}", e))
+    };
+    let mut counts = vec![];
+    for row in rows {
+        match row {
+            Ok(v) => counts.push(v),
+            Err(e) => return Err(format!("fail to read row: {}", e))
+        }
+    }
+    Ok(counts)
+}
+
+pub fn count_user_workflows(db: &Connection, addr: &Address, status: Option<u8>) -> Result<u32, String> {
+    let mut stmt = match db.prepare(
+        "SELECT COUNT(*) FROM workflow WHERE addr = ?1 AND (?2 IS NULL OR json_extract(data, '$.status') = ?2)"
+    ) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    match stmt.query_row(params![serde_json::to_string(&addr).unwrap(), status], |row| row.get(0)) {
+        Ok(v) => Ok(v),
+        Err(e) => return Err(format!("fail to query db: {}", e))
+    }
+}
+
 pub fn update_user_data(db: &Connection, id: i32, data: &UserData) -> Result<bool, String> {
     match db.execute("UPDATE workflow SET data = ?1 WHERE id = ?2", params![serde_json::to_string(&data).unwrap(), id]) {
         Ok(_) => Ok(true),
@@ -149,16 +565,15 @@ pub fn is_workflow_locked(db: &Connection, id: i32) -> Result<bool, String> {
     Ok(workflow_lock != 0)
 }
 
+// checked & set in one statement, two callers can't both take the lock
 pub fn lock_workflow(db: &Connection, id: i32) -> Result<bool, String> {
-    match is_workflow_locked(db, id) {
-        Ok(v) => {
-            if v {
-                return Err(format!("already locked"))
-            };
+    match db.execute("UPDATE workflow SET lock = ?1 WHERE id = ?2 AND lock = 0", params![1, id]) {
+        Ok(0) => match is_workflow_locked(db, id) {
+            // no such workflow, left to the caller's own lookup
+            Ok(false) => Ok(true),
+            Ok(true) => Err("already locked".to_string()),
+            Err(e) => Err(format!("fail to read lock: {}", e)),
         },
-        Err(e) => return Err(format!("fail to read lock: {}", e))
-    };
-    match db.execute("UPDATE workflow SET lock = ?1 WHERE id = ?2", params![1, id]) {
         Ok(_) => Ok(true),
         Err(e) => Err(format!("fail to lock workflow: {}", e))
     }
@@ -171,3 +586,350 @@ pub fn unlock_workflow(db: &Connection, id: i32) -> Result<bool, String> {
     }
 }
 
+
+// last block height scanned by the watchtower
+pub fn get_watchtower_height(db: &Connection) -> Result<Option<u64>, String> {
+    let mut stmt = match db.prepare("SELECT height FROM watchtower WHERE id = 0") {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    match stmt.query_row([], |row| row.get::<_, i64>(0)).optional() {
+        Ok(v) => Ok(v.map(|h| h as u64)),
+        Err(e) => Err(format!("fail to query db: {}", e))
+    }
+}
+
+pub fn set_watchtower_height(db: &Connection, height: u64) -> Result<bool, String> {
+    match db.execute("INSERT OR REPLACE INTO watchtower (id, height) values (0, ?1)", params![height as i64]) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("fail to update watchtower height: {}", e))
+    }
+}
+
+// txs waiting for their timelocks, broadcast by the scheduler at `height`
+pub fn add_scheduled_tx(db: &Connection, txid: &Txid, name: &str, tx_hex: &str, height: u64) -> Result<bool, String> {
+    match db.execute(
+        "INSERT OR REPLACE INTO scheduled_tx (txid, name, tx, height) values (?1, ?2, ?3, ?4)",
+        params![txid.to_string(), name, tx_hex, height as i64],
+    ) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("fail to insert scheduled tx: {}", e))
+    }
+}
+
+// return: [(txid, name, tx_hex)] whose height <= `height`
+pub fn get_due_scheduled_txs(db: &Connection, height: u64) -> Result<Vec<(String, String, String)>, String> {
+    let mut stmt = match db.prepare("SELECT txid, name, tx FROM scheduled_tx WHERE height <= ?1") {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    let rows = match stmt.query_map(params![height as i64], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to query db: {}", e))
+    };
+    let mut res = vec![];
+    for row in rows {
+        match row {
+            Ok(v) => res.push(v),
+            Err(e) => return Err(format!("fail to read row: {}", e))
+        };
+    };
+    Ok(res)
+}
+
+pub fn get_scheduled_height(db: &Connection, txid: &Txid) -> Result<Option<u64>, String> {
+    let mut stmt = match db.prepare("SELECT height FROM scheduled_tx WHERE txid = ?1") {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    match stmt.query_row(params![txid.to_string()], |row| row.get::<_, i64>(0)).optional() {
+        Ok(v) => Ok(v.map(|h| h as u64)),
+        Err(e) => Err(format!("fail to query db: {}", e))
+    }
+}
+
+pub fn remove_scheduled_tx(db: &Connection, txid: &str) -> Result<bool, String> {
+    match db.execute("DELETE FROM scheduled_tx WHERE txid = ?1", params![txid]) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("fail to delete scheduled tx: {}", e))
+    }
+}
+
+// broadcast txs waiting for confirmation, `height`: tip when broadcast (or last bumped)
+pub fn add_pending_tx(db: &Connection, txid: &Txid, name: &str, tx_hex: &str, height: u64) -> Result<bool, String> {
+    match db.execute(
+        "INSERT OR REPLACE INTO pending_tx (txid, name, tx, height) values (?1, ?2, ?3, ?4)",
+        params![txid.to_string(), name, tx_hex, height as i64],
+    ) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("fail to insert pending tx: {}", e))
+    }
+}
+
+// return: [(txid, name, tx_hex, height)]
+pub fn get_pending_txs(db: &Connection) -> Result<Vec<(String, String, String, u64)>, String> {
+    let mut stmt = match db.prepare("SELECT txid, name, tx, height FROM pending_tx") {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    let rows = match stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get::<_, i64>(3)? as u64))) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to query db: {}", e))
+    };
+    let mut res = vec![];
+    for row in rows {
+        match row {
+            Ok(v) => res.push(v),
+            Err(e) => return Err(format!("fail to read row: {}", e))
+        };
+    };
+    Ok(res)
+}
+
+pub fn remove_pending_tx(db: &Connection, txid: &str) -> Result<bool, String> {
+    match db.execute("DELETE FROM pending_tx WHERE txid = ?1", params![txid]) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("fail to delete pending tx: {}", e))
+    }
+}
+
+// faucet payouts, `time` in unix seconds
+// return: id of the request
+pub fn add_faucet_request(db: &Connection, addr: &Address, ip: &str, amount: Amount, time: u64) -> Result<i64, String> {
+    match db.execute(
+        "INSERT INTO faucet_request (addr, ip, amount, time) values (?1, ?2, ?3, ?4)",
+        params![addr.to_string(), ip, amount.to_sat() as i64, time as i64],
+    ) {
+        Ok(_) => Ok(db.last_insert_rowid()),
+        Err(e) => Err(format!("fail to insert faucet request: {}", e))
+    }
+}
+
+pub fn remove_faucet_request(db: &Connection, id: i64) -> Result<bool, String> {
+    match db.execute("DELETE FROM faucet_request WHERE id = ?1", params![id]) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("fail to delete faucet request: {}", e))
+    }
+}
+
+// (number of requests, time of the earliest one) by `column` ("addr" or "ip") since `time`
+pub fn get_faucet_requests_since(db: &Connection, column: &str, value: &str, time: u64) -> Result<(u32, Option<u64>), String> {
+    let query = match column {
+        "addr" => "SELECT COUNT(*), MIN(time) FROM faucet_request WHERE addr = ?1 AND time >= ?2",
+        "ip" => "SELECT COUNT(*), MIN(time) FROM faucet_request WHERE ip = ?1 AND time >= ?2",
+        _ => return Err(format!("unknown faucet request column: {column}")),
+    };
+    let mut stmt = match db.prepare(query) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    match stmt.query_row(params![value, time as i64], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, Option<i64>>(1)?))) {
+        Ok((count, earliest)) => Ok((count, earliest.map(|t| t as u64))),
+        Err(e) => Err(format!("fail to query db: {}", e))
+    }
+}
+
+pub fn get_last_faucet_request_time(db: &Connection, addr: &Address, ip: &str) -> Result<Option<u64>, String> {
+    let mut stmt = match db.prepare("SELECT MAX(time) FROM faucet_request WHERE addr = ?1 OR ip = ?2") {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    match stmt.query_row(params![addr.to_string(), ip], |row| row.get::<_, Option<i64>>(0)) {
+        Ok(v) => Ok(v.map(|t| t as u64)),
+        Err(e) => Err(format!("fail to query db: {}", e))
+    }
+}
+
+// (total paid out, time of the earliest payout) since `time`
+pub fn get_faucet_amount_since(db: &Connection, time: u64) -> Result<(Amount, Option<u64>), String> {
+    let mut stmt = match db.prepare("SELECT COALESCE(SUM(amount), 0), MIN(time) FROM faucet_request WHERE time >= ?1") {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    match stmt.query_row(params![time as i64], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<i64>>(1)?))) {
+        Ok((amount, earliest)) => Ok((Amount::from_sat(amount as u64), earliest.map(|t| t as u64))),
+        Err(e) => Err(format!("fail to query db: {}", e))
+    }
+}
+
+pub fn is_faucet_whitelisted(db: &Connection, addr: &Address) -> Result<bool, String> {
+    let mut stmt = match db.prepare("SELECT COUNT(*) FROM faucet_whitelist WHERE addr = ?1") {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    match stmt.query_row(params![addr.to_string()], |row| row.get::<_, u32>(0)) {
+        Ok(v) => Ok(v > 0),
+        Err(e) => Err(format!("fail to query db: {}", e))
+    }
+}
+
+pub fn add_faucet_whitelist(db: &Connection, addr: &Address) -> Result<bool, String> {
+    match db.execute("INSERT OR IGNORE INTO faucet_whitelist (addr) values (?1)", params![addr.to_string()]) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("fail to update faucet whitelist: {}", e))
+    }
+}
+
+pub fn remove_faucet_whitelist(db: &Connection, addr: &Address) -> Result<bool, String> {
+    match db.execute("DELETE FROM faucet_whitelist WHERE addr = ?1", params![addr.to_string()]) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("fail to update faucet whitelist: {}", e))
+    }
+}
+
+// login nonces of GET /auth/challenge, expired ones are dropped on insert
+pub fn add_auth_challenge(db: &Connection, nonce: &str, addr: &Address, expires_at: u64) -> Result<bool, String> {
+    if let Err(e) = db.execute("DELETE FROM auth_challenge WHERE expires_at < strftime('%s', 'now')", []) {
+        return Err(format!("fail to purge auth challenges: {}", e))
+    };
+    match db.execute(
+        "INSERT INTO auth_challenge (nonce, addr, expires_at) values (?1, ?2, ?3)",
+        params![nonce, addr.to_string(), expires_at as i64],
+    ) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("fail to insert auth challenge: {}", e))
+    }
+}
+
+// nonces are single use, returns (addr, expires_at) & deletes the nonce in the same statement:
+// of two requests racing on a nonce only one gets it back
+pub fn take_auth_challenge(db: &Connection, nonce: &str) -> Result<Option<(String, u64)>, String> {
+    let mut stmt = match db.prepare("DELETE FROM auth_challenge WHERE nonce = ?1 RETURNING addr, expires_at") {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare delete: {}", e))
+    };
+    match stmt.query_row(params![nonce], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))).optional() {
+        Ok(v) => Ok(v),
+        Err(e) => Err(format!("fail to delete auth challenge: {}", e))
+    }
+}
+
+pub fn add_auth_session(db: &Connection, token: &str, addr: &Address, expires_at: u64) -> Result<bool, String> {
+    if let Err(e) = db.execute("DELETE FROM auth_session WHERE expires_at < strftime('%s', 'now')", []) {
+        return Err(format!("fail to purge auth sessions: {}", e))
+    };
+    match db.execute(
+        "INSERT INTO auth_session (token, addr, expires_at) values (?1, ?2, ?3)",
+        params![token, addr.to_string(), expires_at as i64],
+    ) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("fail to insert auth session: {}", e))
+    }
+}
+
+// (addr, expires_at) of a session token
+pub fn get_auth_session(db: &Connection, token: &str) -> Result<Option<(String, u64)>, String> {
+    let mut stmt = match db.prepare("SELECT addr, expires_at FROM auth_session WHERE token = ?1") {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    match stmt.query_row(params![token], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))).optional() {
+        Ok(v) => Ok(v),
+        Err(e) => Err(format!("fail to query db: {}", e))
+    }
+}
+
+// `outcome`: http status of the attempt
+pub fn add_admin_audit(db: &Connection, time: u64, ip: &str, action: &str, target: &str, outcome: u16, detail: &str) -> Result<bool, String> {
+    match db.execute(
+        "INSERT INTO admin_audit (time, ip, action, target, outcome, detail) values (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![time as i64, ip, action, target, outcome, detail],
+    ) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("fail to insert admin audit: {}", e))
+    }
+}
+
+// (time, ip, action, target, outcome, detail), newest first
+pub fn get_admin_audit(db: &Connection, limit: u32, offset: u32) -> Result<Vec<(u64, String, String, String, u16, String)>, String> {
+    let mut stmt = match db.prepare("SELECT time, ip, action, target, outcome, detail FROM admin_audit ORDER BY id DESC LIMIT ?1 OFFSET ?2") {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    let rows = match stmt.query_map(params![limit, offset], |row| {
+        Ok((row.get::<_, i64>(0)? as u64, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
+    }) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to query db: {}", e))
+    };
+    let mut entries = vec![];
+    for row in rows {
+        match row {
+            Ok(v) => entries.push(v),
+            Err(e) => return Err(format!("fail to read row: {}", e))
+        }
+    }
+    Ok(entries)
+}
+
+pub fn new_scenario(db: &Connection, data: &ScenarioData) -> Result<i32, String> {
+    match db.execute("INSERT INTO scenario (data) values (?1)", params![serde_json::to_string(data).unwrap()]) {
+        Ok(_) => Ok(db.last_insert_rowid() as i32),
+        Err(e) => Err(format!("fail to insert scenario: {}", e))
+    }
+}
+
+pub fn update_scenario(db: &Connection, id: i32, data: &ScenarioData) -> Result<bool, String> {
+    match db.execute("UPDATE scenario SET data = ?1 WHERE id = ?2", params![serde_json::to_string(data).unwrap(), id]) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("fail to update scenario: {}", e))
+    }
+}
+
+pub fn get_scenario(db: &Connection, id: i32) -> Result<Option<ScenarioData>, String> {
+    let mut stmt = match db.prepare("SELECT data FROM scenario WHERE id = ?1") {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to prepare select: {}", e))
+    };
+    let data = match stmt.query_row(params![id], |row| row.get::<_, String>(0)).optional() {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to query db: {}", e))
+    };
+    match data.map(|data| serde_json::from_str(&data)) {
+        Some(Ok(v)) => Ok(Some(v)),
+        Some(Err(e)) => Err(format!("fail to decode scenario: {}", e)),
+        None => Ok(None),
+    }
+}
+
+pub fn count_running_scenarios(db: &Connection) -> Result<u32, String> {
+    match db.query_row(
+        "SELECT COUNT(*) FROM scenario WHERE json_extract(data, '$.status') = ?1",
+        params![SCENARIO_RUNNING],
+        |row| row.get::<_, u32>(0),
+    ) {
+        Ok(v) => Ok(v),
+        Err(e) => Err(format!("fail to query db: {}", e))
+    }
+}
+
+// create the workflow of a scenario & the scenario running on it, locked until the run ends
+// Ok(None) when `max_running` scenarios already run: counted & inserted in one transaction, parallel requests can't pass the cap
+pub fn new_scenario_workflow(db: &Connection, addr: &Address, data: &mut ScenarioData, max_running: u32) -> Result<Option<(i32, i32)>, String> {
+    let tx = match Transaction::new_unchecked(db, TransactionBehavior::Immediate) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to begin transaction: {}", e))
+    };
+    if count_running_scenarios(&tx)? >= max_running {
+        return Ok(None)
+    };
+    let workflow_id = new_user(&tx, addr)?;
+    lock_workflow(&tx, workflow_id)?;
+    data.workflow_id = Some(workflow_id);
+    let scenario_id = new_scenario(&tx, data)?;
+    match tx.commit() {
+        Ok(_) => Ok(Some((scenario_id, workflow_id))),
+        Err(e) => Err(format!("fail to commit transaction: {}", e))
+    }
+}
+
+// runs cut short by a restart never finish, mark them failed
+pub fn fail_running_scenarios(db: &Connection, reason: &str, time: u64) -> Result<bool, String> {
+    match db.execute(
+        "UPDATE scenario SET data = json_set(data, '$.status', ?1, '$.error', ?2, '$.updated_at', ?3) WHERE json_extract(data, '$.status') = ?4",
+        params![SCENARIO_FAILED, reason, time as i64, SCENARIO_RUNNING],
+    ) {
+        Ok(_) => Ok(true),
+        Err(e) => Err(format!("fail to update scenarios: {}", e))
+    }
+}
@@ -0,0 +1,162 @@
+use bitcoin::{Address, Txid};
+use crate::{rpc::RpcClient, sql::{self, UserData}, transactions::{self, Broadcast, FeeInfo}, workers};
+
+// workflow steps shared by their endpoints & the scenarios (scenario::run_step): `check_stage` is the
+// status each one starts from, the steps after kickoff_1 build & broadcast their tx on the worker runtime
+// & record it in `user_data`, the caller saves it
+
+// Err(stage) when the workflow at `status` can't run `step`, the stage it must be at
+pub fn check_stage(step: &str, status: u8) -> Result<(), String> {
+    let (ready, stage) = match step {
+        "peg-in" => (status == sql::STATUS::FAUCET as u8, "faucet"),
+        "kickoff-1" => (sql::is_pegged_in(status), "pegin"),
+        "kickoff-2" => (
+            sql::is_pegged_in(status) || status == sql::STATUS::KICKOFF1 as u8 || status == sql::STATUS::START_TIME as u8,
+            "pegin/kickoff1/start_time",
+        ),
+        "challenge" | "take-1" => (status == sql::STATUS::KICKOFF2 as u8, "kickoff2"),
+        "assert" => (status == sql::STATUS::CHALLENGE as u8, "challenge"),
+        "take-2" | "disprove" => (status == sql::STATUS::ASSERT as u8, "assert"),
+        _ => return Err(format!("unknown step: {step}")),
+    };
+    if !ready {
+        return Err(format!("not currently at {stage} stage"))
+    };
+    Ok(())
+}
+
+pub fn required(txid: Option<Txid>, name: &str) -> Result<Txid, String> {
+    match txid {
+        Some(v) => Ok(v),
+        _ => Err(format!("workflow missing {name}_txid")),
+    }
+}
+
+// record a broadcast tx in the workflow, a tx waiting for its timelock is only noted:
+// the scheduler records it (& moves the status on) once it is broadcast
+pub fn record_broadcast(user_data: &mut UserData, tx_kind: &str, res: Broadcast) -> Result<(), String> {
+    match res {
+        Broadcast::Sent(txid, _) => user_data.record_tx(tx_kind, txid),
+        Broadcast::Scheduled(txid, height, _) => {
+            user_data.scheduled = Some(sql::ScheduledTx { kind: tx_kind.to_string(), txid, height });
+            Ok(())
+        },
+    }
+}
+
+// take_1/take_2 reimburse the operator once it fronted a peg-out, else pay the user directly
+pub fn get_take_receive_address(user_data: &UserData, user_address: Address) -> Option<Address> {
+    match user_data.peg_out {
+        Some(_) => None,
+        _ => Some(user_address),
+    }
+}
+
+pub async fn kick_off_2(rpc: RpcClient, user_data: &mut UserData) -> Result<Broadcast, String> {
+    let kick_off_1_txid = required(user_data.kickoff_1, "kickoff_1")?;
+    let key_set = transactions::get_workflow_key_set(kick_off_1_txid, user_data);
+    let bitcom_lock_scripts = match transactions::load_bitcom_lock_scripts(&key_set).await {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to generate bitcom lock scripts: {}", e))
+    };
+    let res = match workers::run(async move { transactions::kick_off_2(&rpc, kick_off_1_txid, &bitcom_lock_scripts).await }).await {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to send kickoff2 tx: {}", e))
+    };
+    record_broadcast(user_data, "kickoff2", res)?;
+    Ok(res)
+}
+
+pub async fn challenge(rpc: RpcClient, user_data: &mut UserData) -> Result<Broadcast, String> {
+    let kick_off_1_txid = required(user_data.kickoff_1, "kickoff_1")?;
+    let res = match workers::run(async move { transactions::challenge(&rpc, kick_off_1_txid).await }).await {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to send challenge tx: {}", e))
+    };
+    record_broadcast(user_data, "challenge", res)?;
+    Ok(res)
+}
+
+pub async fn take_1(rpc: RpcClient, user_data: &mut UserData, user_address: Address) -> Result<Broadcast, String> {
+    let peg_in_txid = required(user_data.pegin, "pegin")?;
+    let kick_off_1_txid = required(user_data.kickoff_1, "kickoff_1")?;
+    let kick_off_2_txid = required(user_data.kickoff_2, "kickoff_2")?;
+    let receive_address = get_take_receive_address(user_data, user_address);
+    let res = match workers::run(async move { transactions::take_1(&rpc, peg_in_txid, kick_off_1_txid, kick_off_2_txid, receive_address).await }).await {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to send take1 tx: {}", e))
+    };
+    record_broadcast(user_data, "take1", res)?;
+    Ok(res)
+}
+
+// the assert tx is never scheduled, its timelock is the challenge's
+pub async fn assert(rpc: RpcClient, user_data: &mut UserData) -> Result<(Txid, FeeInfo), String> {
+    let kick_off_1_txid = required(user_data.kickoff_1, "kickoff_1")?;
+    let kick_off_2_txid = required(user_data.kickoff_2, "kickoff_2")?;
+    let key_set = transactions::get_workflow_key_set(kick_off_1_txid, user_data);
+    let bitcom_lock_scripts = transactions::load_bitcom_lock_scripts(&key_set).await?;
+    let connector_c = transactions::load_connector_c(&key_set).await?;
+    let fake_index = user_data.fake_index.map(|i| i as usize);
+    let (assert_txid, _, fee_info) = match workers::run(async move {
+        let bitcom_unlock_scripts = key_set.bitcom_unlock_scripts(fake_index);
+        let connector_c_addr = Some(connector_c.address.clone());
+        transactions::assert(&rpc, kick_off_2_txid, &bitcom_lock_scripts, &connector_c.tapscripts, bitcom_unlock_scripts, connector_c_addr).await
+    }).await {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to send assert tx: {}", e))
+    };
+    user_data.assert = Some(assert_txid);
+    user_data.status = sql::STATUS::ASSERT as u8;
+    Ok((assert_txid, fee_info))
+}
+
+pub async fn take_2(rpc: RpcClient, user_data: &mut UserData, user_address: Address) -> Result<Broadcast, String> {
+    let peg_in_txid = required(user_data.pegin, "pegin")?;
+    let kick_off_1_txid = required(user_data.kickoff_1, "kickoff_1")?;
+    let assert_txid = required(user_data.assert, "assert")?;
+    let key_set = transactions::get_workflow_key_set(kick_off_1_txid, user_data);
+    let connector_c = transactions::load_connector_c(&key_set).await?;
+    let receive_address = get_take_receive_address(user_data, user_address);
+    let res = match workers::run(async move {
+        let connector_c_addr = Some(connector_c.address.clone());
+        transactions::take_2(&rpc, peg_in_txid, assert_txid, &connector_c.tapscripts, connector_c_addr, &connector_c.spend_info, receive_address).await
+    }).await {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to send take2 tx: {}", e))
+    };
+    record_broadcast(user_data, "take2", res)?;
+    Ok(res)
+}
+
+pub async fn disprove(rpc: RpcClient, user_data: &mut UserData) -> Result<Broadcast, String> {
+    let kick_off_1_txid = required(user_data.kickoff_1, "kickoff_1")?;
+    let assert_txid = required(user_data.assert, "assert")?;
+    let key_set = transactions::get_workflow_key_set(kick_off_1_txid, user_data);
+    let connector_c = transactions::load_connector_c(&key_set).await?;
+    let res = match workers::run(async move {
+        let connector_c_addr = Some(connector_c.address.clone());
+        transactions::disprove(&rpc, assert_txid, &connector_c.tapscripts, connector_c_addr, &connector_c.spend_info, &key_set).await
+    }).await {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to send disprove tx: {}", e))
+    };
+    record_broadcast(user_data, "disprove", res)?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages() {
+        assert!(check_stage("peg-in", sql::STATUS::FAUCET as u8).is_ok());
+        assert!(check_stage("kickoff-1", sql::STATUS::PEGOUT as u8).is_ok());
+        assert!(check_stage("kickoff-2", sql::STATUS::START_TIME as u8).is_ok());
+        assert!(check_stage("take-1", sql::STATUS::KICKOFF2 as u8).is_ok());
+        assert_eq!(check_stage("take-1", sql::STATUS::CHALLENGE as u8), Err("not currently at kickoff2 stage".to_string()));
+        assert_eq!(check_stage("disprove", sql::STATUS::CHALLENGE as u8), Err("not currently at assert stage".to_string()));
+        assert!(check_stage("faucet", 0).is_err());
+    }
+}
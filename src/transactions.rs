@@ -1,19 +1,23 @@
 use bitcoin::{
-    Address, Amount, OutPoint, Transaction, Txid, taproot::TaprootSpendInfo,
-    XOnlyPublicKey, absolute, TxIn, TxOut, ScriptBuf, Witness, Sequence
+    Address, Amount, OutPoint, Transaction, Txid,
+    XOnlyPublicKey, absolute, TxIn, TxOut, ScriptBuf, Witness, Sequence,
+    PublicKey, EcdsaSighashType, TapSighashType, TapLeafHash, taproot::{self, LeafVersion, TaprootSpendInfo},
+    secp256k1::{Keypair, Message, Secp256k1}, sighash::{Prevouts, SighashCache},
 };
 use bitcoincore_rpc::Client;
 use bitvm::bridge::connectors::connector_0;
 use bitvm::bridge::contexts::operator;
+use bitvm::bridge::contexts::base::generate_keys_from_secret;
 use bitvm::bridge::transactions::pre_signed::PreSignedTransaction;
 use std::str::FromStr;  
 use bitvm::treepp::*;
 use bitvm::bridge::{
     connectors::{
-        revealer::Revealer, connector_c::ConnectorC, connector_0::Connector0, connector::TaprootConnector,
-        connector_1::Connector1, connector_2::Connector2, connector_a::ConnectorA,
+        revealer::Revealer, connector_c::ConnectorC, connector_0::Connector0, connector::{P2wshConnector, TaprootConnector},
+        connector_1::Connector1, connector_2::Connector2, connector_3::Connector3, connector_a::ConnectorA,
+        connector_z::ConnectorZ,
     }, 
-    graphs::base::{DUST_AMOUNT, FEE_AMOUNT},
+    graphs::base::DUST_AMOUNT,
     scripts::{generate_pay_to_pubkey_script_address, generate_pay_to_pubkey_script}, 
     groth16::{
         load_proof_from_file,
@@ -29,8 +33,12 @@ use bitvm::bridge::{
         base::{BaseTransaction, Input, InputWithScript}, 
         kick_off_1::KickOff1Transaction, 
         kick_off_2::KickOff2Transaction, 
+        kick_off_timeout::KickOffTimeoutTransaction,
+        start_time::StartTimeTransaction,
+        start_time_timeout::StartTimeTimeoutTransaction,
         peg_in_confirm::PegInConfirmTransaction, 
         peg_in_deposit::PegInDepositTransaction,
+        peg_in_refund::PegInRefundTransaction,
         challenge::ChallengeTransaction,
         take_1::Take1Transaction,
         take_2::Take2Transaction,
@@ -39,9 +47,24 @@ use bitvm::bridge::{
     }
 };
 use crate::utils::{wait, wait_tx};
-use crate::{config::{self, network}, utils};
-use once_cell::sync::Lazy;
-use log::info;
+use crate::{config::{self, network}, metrics, setup, sql::{self, ChallengeContribution, UserData}, utils, workers};
+use bitcoin_hashes::{sha256, Hash};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use once_cell::sync::{Lazy, OnceCell};
+use log::{info, warn};
+
+// witness of an input the user signs: items count + DER signature + compressed pubkey (p2wpkh)
+const USER_INPUT_WITNESS_WEIGHT: u64 = 1 + 73 + 34;
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
+pub struct FeeInfo {
+    pub fee: Amount,
+    pub vsize: u64,
+    pub fee_rate: u64, // in sat/vB, paid
+    pub target_fee_rate: u64, // in sat/vB
+    pub required_fee: Amount, // at target_fee_rate
+}
 
 pub static CONNECTOR_C_TAPSCRIPTS: Lazy<Vec<Script>> = Lazy::new(|| {
     info!("load connector_c_tapscripts");
@@ -64,8 +87,157 @@ pub static CONNECTOR_C_SPEND_INFO: Lazy<TaprootSpendInfo> = Lazy::new(|| {
     get_connector_c_spend_info()
 });
 
+// per-workflow caches, keyed by the key set's tapscript_dir
+// one cell per key set, the first request generates the entry while the others wait on the cell
+type CacheCell<T> = Arc<OnceCell<Arc<T>>>;
+static WORKFLOW_BITCOM_LOCK_SCRIPTS: Lazy<Mutex<HashMap<String, CacheCell<Vec<Script>>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static WORKFLOW_CONNECTOR_C: Lazy<Mutex<HashMap<String, CacheCell<ConnectorCData>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn cache_cell<T>(cache: &Mutex<HashMap<String, CacheCell<T>>>, key: &str) -> CacheCell<T> {
+    cache.lock().unwrap().entry(key.to_string()).or_default().clone()
+}
+
+// wots key material of one workflow (or the shared default one) and where its generated data lives
+#[derive(Clone, Debug)]
+pub struct WotsKeySet {
+    pub wots_secret: String,
+    pub proof_path: String,
+    pub compile_dir: String,
+    pub tapscript_dir: String,
+    pub signature_dir: String,
+    pub disprove_dir: String,
+}
+
+impl WotsKeySet {
+    pub fn default_set() -> Self {
+        WotsKeySet {
+            wots_secret: config::WOTS_SECRET.to_string(),
+            proof_path: config::PROOF_PATH.to_string(),
+            compile_dir: config::COMPILE_PATH.to_string(),
+            tapscript_dir: config::TAPSCRIPT_PATH.to_string(),
+            signature_dir: config::WOTS_SIGNATURE_PATH.to_string(),
+            disprove_dir: config::PRE_COMPUTED_DISPROVE_PATH.to_string(),
+        }
+    }
+
+    // wots secret = sha256(operator_secret || kick_off_1_txid), truncated to the length of config::WOTS_SECRET
+    pub fn for_workflow(kick_off_1_txid: Txid) -> Self {
+        let preimage = format!("{}{}", config::OPERATOR_SECRET, kick_off_1_txid);
+        let digest = sha256::Hash::hash(preimage.as_bytes());
+        let wots_secret = hex::encode(digest.to_byte_array())[..config::WOTS_SECRET.len()].to_string();
+        let workflow_dir = format!("{}/{}", config::WORKFLOW_DATA_PATH, kick_off_1_txid);
+        WotsKeySet {
+            wots_secret,
+            proof_path: config::PROOF_PATH.to_string(),
+            compile_dir: config::COMPILE_PATH.to_string(),
+            tapscript_dir: format!("{workflow_dir}/tapscripts"),
+            signature_dir: format!("{workflow_dir}/signed_assertions"),
+            disprove_dir: format!("{workflow_dir}/disprove"),
+        }
+    }
+
+    // use a registered vk & a proof submitted for the workflow instead of the dummy proof
+    pub fn with_proof(mut self, vk_id: &str, proof_path: &str) -> Self {
+        self.compile_dir = setup::vk_compile_dir(vk_id);
+        self.proof_path = proof_path.to_string();
+        self
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.tapscript_dir == config::TAPSCRIPT_PATH
+    }
+
+    pub fn wots_keys(&self) -> (WotsPublicKeys, WotsSecretKeys) {
+        generate_wots_keys_from_secrets(&self.wots_secret)
+    }
+
+    pub fn bitcom_lock_scripts(&self) -> Vec<Script> {
+        let (wots_pk, _) = self.wots_keys();
+        assert_bitcom_lock(&wots_pk)
+    }
+
+    pub fn bitcom_unlock_scripts(&self, corrupt_index: Option<usize>) -> Vec<Script> {
+        match corrupt_index {
+            Some(index) => {
+                let (_, wots_sk) = self.wots_keys();
+                assert_unlock_scripts_from_file(&self.signature_dir, Some(index), Some(wots_sk))
+            },
+            _ => assert_unlock_scripts_from_file(&self.signature_dir, None, None),
+        }
+    }
+
+    pub fn signed_assertions(&self) -> WotsSignatures {
+        load_all_signed_assertions_from_file(&self.signature_dir)
+    }
+
+    pub fn assert_tapscripts(&self) -> Vec<Script> {
+        load_all_assert_tapscripts_from_file(&self.tapscript_dir)
+    }
+}
+
+// connector_c depends on the tapscripts, which depend on the wots key
+pub struct ConnectorCData {
+    pub tapscripts: Vec<Script>,
+    pub address: Address,
+    pub spend_info: TaprootSpendInfo,
+}
+
+pub fn get_workflow_key_set(kick_off_1_txid: Txid, user_data: &UserData) -> WotsKeySet {
+    let key_set = WotsKeySet::for_workflow(kick_off_1_txid);
+    match (&user_data.vk_id, &user_data.proof_path) {
+        (Some(vk_id), Some(proof_path)) => key_set.with_proof(vk_id, proof_path),
+        _ => key_set,
+    }
+}
+
+pub fn get_cached_bitcom_lock_scripts(key_set: &WotsKeySet) -> Arc<Vec<Script>> {
+    let cell = cache_cell(&WORKFLOW_BITCOM_LOCK_SCRIPTS, &key_set.tapscript_dir);
+    cell.get_or_init(|| Arc::new(key_set.bitcom_lock_scripts())).clone()
+}
+
+// generate the workflow's tapscripts & signed assertions on first use (this may take serveral minutes)
+pub fn get_cached_connector_c(key_set: &WotsKeySet) -> Arc<ConnectorCData> {
+    let cell = cache_cell(&WORKFLOW_CONNECTOR_C, &key_set.tapscript_dir);
+    cell.get_or_init(|| Arc::new(generate_connector_c(key_set))).clone()
+}
+
+fn generate_connector_c(key_set: &WotsKeySet) -> ConnectorCData {
+    if key_set.is_default() {
+        return ConnectorCData {
+            tapscripts: borrow_assert_tapscripts().clone(),
+            address: get_precomputed_connector_c_address(),
+            spend_info: CONNECTOR_C_SPEND_INFO.clone(),
+        }
+    };
+    if !setup::check_workflow_setup(key_set) {
+        setup::setup_workflow(key_set);
+    };
+    let operator_context = config::get_operator_context();
+    let tapscripts = key_set.assert_tapscripts();
+    let mut connector_c = ConnectorC::new(network(), &operator_context.operator_taproot_public_key, &tapscripts);
+    let address = connector_c.gen_taproot_address();
+    let spend_info = connector_c.generate_taproot_spend_info();
+    ConnectorCData {
+        tapscripts,
+        address,
+        spend_info,
+    }
+}
+
+// same as get_cached_*, on the blocking pool so the http workers are not held up by the generation
+pub async fn load_bitcom_lock_scripts(key_set: &WotsKeySet) -> Result<Arc<Vec<Script>>, String> {
+    let key_set = key_set.clone();
+    workers::run_blocking(move || Ok(get_cached_bitcom_lock_scripts(&key_set))).await
+}
+
+pub async fn load_connector_c(key_set: &WotsKeySet) -> Result<Arc<ConnectorCData>, String> {
+    let key_set = key_set.clone();
+    workers::run_blocking(move || Ok(get_cached_connector_c(&key_set))).await
+}
+
 
 pub fn faucet(rpc: &Client, user_addr: &Address) -> Result<(OutPoint, OutPoint), String> {
+    let _timer = metrics::StepTimer::new("faucet");
     let faucet_1_amount = Amount::from_sat(config::PEGIN_AMOUNT);
     let faucet_2_amount = Amount::from_sat(config::KICKOFF_AMOUNT);
 
@@ -93,21 +265,543 @@ pub fn peg_in_prepare(rpc: &Client, faucet_1_txid: Txid, faucet_1_vout: u32) ->
         witness: Witness::default(),
     };
     
-    let total_output_amount = input_amount - Amount::from_sat(FEE_AMOUNT);
 
     let connector_0 = Connector0::new(network(), &verifier_contexts[0].n_of_n_taproot_public_key);
 
     let output_0 = TxOut {
-        value: total_output_amount,
+        value: input_amount,
         script_pubkey: connector_0.generate_taproot_address().script_pubkey(),
     };
 
-    Ok(Transaction {
+    let mut tx = Transaction {
         version: bitcoin::transaction::Version(2),
         lock_time: absolute::LockTime::ZERO,
         input: vec![input_0],
         output: vec![output_0],
-    })
+    };
+    // the fee is taken from the output holding the remaining value
+    let fee = estimate_user_tx_fee(rpc, &tx);
+    let change_amount = match tx.output[0].value.checked_sub(fee) {
+        Some(v) => v,
+        _ => return Err(format!("input amount {input_amount} can not cover fee {fee}"))
+    };
+    tx.output[0].value = change_amount;
+    Ok(tx)
+}
+
+// two-step peg-in: user funds -> connector_z, then either confirm (n-of-n) or refund (depositor, after timelock)
+// the depositor key is held by this service on behalf of the user, as in peg_in()
+pub fn peg_in_deposit_prepare(rpc: &Client, faucet_1_txid: Txid, faucet_1_vout: u32) -> Result<Transaction, String> {
+    let depositor_context = config::get_depositor_context();
+    let input_amount = match utils::get_utxo_value(rpc, faucet_1_txid, faucet_1_vout) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get faucet_1_outpoint value: {}", e))
+    };
+    let peg_in_deposit = PegInDepositTransaction::new(
+        &depositor_context,
+        config::DEPOSITOR_EVM_ADDRESS,
+        Input {
+            outpoint: OutPoint {
+                txid: faucet_1_txid,
+                vout: faucet_1_vout,
+            },
+            amount: input_amount,
+        },
+    );
+    // faucet_1 belongs to the user, who signs input 0 in place of the depositor
+    let mut tx = peg_in_deposit.finalize();
+    tx.input[0].witness = Witness::default();
+
+    // the fee is taken from the output holding the remaining value
+    let fee = estimate_user_tx_fee(rpc, &tx);
+    let change_amount = match input_amount.checked_sub(fee) {
+        Some(v) => v,
+        _ => return Err(format!("input amount {input_amount} can not cover fee {fee}"))
+    };
+    tx.output[0].value = change_amount;
+    Ok(tx)
+}
+
+pub fn peg_in_confirm_prepare(rpc: &Client, peg_in_deposit_txid: Txid) -> Result<Transaction, String> {
+    let depositor_context = config::get_depositor_context();
+    let verifier_contexts = config::get_verifier_contexts();
+    let connector_z_vout = 0;
+    let connector_z_amount = match utils::get_utxo_value(rpc, peg_in_deposit_txid, connector_z_vout) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get connector_z value: {}", e))
+    };
+    let mut peg_in_confirm = PegInConfirmTransaction::new(
+        &depositor_context,
+        config::DEPOSITOR_EVM_ADDRESS,
+        Input {
+            outpoint: OutPoint {
+                txid: peg_in_deposit_txid,
+                vout: connector_z_vout,
+            },
+            amount: connector_z_amount,
+        },
+    );
+
+    let secret_nonces_0 = peg_in_confirm.push_nonces(&verifier_contexts[0]);
+    let secret_nonces_1 = peg_in_confirm.push_nonces(&verifier_contexts[1]);
+
+    peg_in_confirm.pre_sign(&verifier_contexts[0], &secret_nonces_0);
+    peg_in_confirm.pre_sign(&verifier_contexts[1], &secret_nonces_1);
+
+    Ok(peg_in_confirm.finalize())
+}
+
+// PegInRefundTransaction pays the depositor key held by this service,
+// the refund goes back to `refund_address` (the workflow's user) instead
+pub fn peg_in_refund_prepare(rpc: &Client, peg_in_deposit_txid: Txid, refund_address: &Address) -> Result<Transaction, String> {
+    let depositor_context = config::get_depositor_context();
+    let connector_z_vout = 0;
+    let connector_z_amount = match utils::get_utxo_value(rpc, peg_in_deposit_txid, connector_z_vout) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get connector_z value: {}", e))
+    };
+    let peg_in_refund = PegInRefundTransaction::new(
+        &depositor_context,
+        config::DEPOSITOR_EVM_ADDRESS,
+        Input {
+            outpoint: OutPoint {
+                txid: peg_in_deposit_txid,
+                vout: connector_z_vout,
+            },
+            amount: connector_z_amount,
+        },
+    );
+    let mut tx = peg_in_refund.finalize();
+    tx.output[0].script_pubkey = refund_address.script_pubkey();
+
+    let connector_z = ConnectorZ::new(
+        network(),
+        config::DEPOSITOR_EVM_ADDRESS,
+        &depositor_context.depositor_taproot_public_key,
+        &depositor_context.n_of_n_taproot_public_key,
+    );
+    let prevout = TxOut {
+        value: connector_z_amount,
+        script_pubkey: connector_z.generate_taproot_address().script_pubkey(),
+    };
+    resign_taproot_leaf_input(&mut tx, 0, prevout, &depositor_context.depositor_keypair)?;
+    Ok(tx)
+}
+
+// replace the signature of a script-path input (witness: [sig, leaf script, control block])
+// after its outputs changed, the leaf & control block are kept
+fn resign_taproot_leaf_input(tx: &mut Transaction, input_index: usize, prevout: TxOut, keypair: &Keypair) -> Result<(), String> {
+    let witness: Vec<Vec<u8>> = tx.input[input_index].witness.iter().map(|w| w.to_vec()).collect();
+    if witness.len() != 3 {
+        return Err(format!("expect a single signature script-path witness, got {} elements", witness.len()))
+    };
+    let leaf_script = ScriptBuf::from_bytes(witness[1].clone());
+    let leaf_hash = TapLeafHash::from_script(&leaf_script, LeafVersion::TapScript);
+    let sighash_type = TapSighashType::All;
+    let sighash = match SighashCache::new(&*tx).taproot_script_spend_signature_hash(input_index, &Prevouts::All(&[prevout]), leaf_hash, sighash_type) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to compute sighash: {}", e))
+    };
+    let secp = Secp256k1::new();
+    let signature = taproot::Signature {
+        signature: secp.sign_schnorr(&Message::from_digest(sighash.to_byte_array()), keypair),
+        sighash_type,
+    };
+    let mut new_witness = Witness::new();
+    new_witness.push(signature.to_vec());
+    new_witness.push(&witness[1]);
+    new_witness.push(&witness[2]);
+    tx.input[input_index].witness = new_witness;
+    Ok(())
+}
+
+// return: peg_in_confirm_txid
+pub async fn peg_in_confirm(rpc: &Client, peg_in_deposit_txid: Txid) -> Result<Broadcast, String> {
+    let _timer = metrics::StepTimer::new("pegin-confirm");
+    let tx = match peg_in_confirm_prepare(rpc, peg_in_deposit_txid) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    broadcast_after_timelock(rpc, &tx, "peg_in_confirm").await
+}
+
+// return: peg_in_refund_txid
+pub async fn peg_in_refund(rpc: &Client, peg_in_deposit_txid: Txid, refund_address: &Address) -> Result<Broadcast, String> {
+    let _timer = metrics::StepTimer::new("pegin-refund");
+    let tx = match peg_in_refund_prepare(rpc, peg_in_deposit_txid, refund_address) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    broadcast_after_timelock(rpc, &tx, "peg_in_refund").await
+}
+
+// the workflow tx (None while not recorded) whose timelocked output the given tx kind spends
+pub fn get_timelock_parent(user_data: &UserData, tx_kind: &str) -> Result<Option<Txid>, String> {
+    match tx_kind {
+        "pegin-refund" => Ok(user_data.pegin_deposit),
+        "kickoff2" | "start-time-timeout" | "kickoff-timeout" => Ok(user_data.kickoff_1),
+        "take1" => Ok(user_data.kickoff_2),
+        "take2" | "disprove" => Ok(user_data.assert),
+        _ => Err(format!("unknown tx kind: {tx_kind}")),
+    }
+}
+
+// first block height the tx can be mined at, from its inputs' CSV & its CLTV
+pub fn get_spendable_height(rpc: &Client, tx: &Transaction) -> Result<u64, String> {
+    let tip = match utils::get_block_count(rpc) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    let mut spendable_height = tip + 1;
+    for i in 0..tx.input.len() {
+        let timelock = get_relative_timelock_blocks(tx, i) as u64;
+        if timelock == 0 {
+            continue
+        };
+        let prevout = tx.input[i].previous_output;
+        let confirmations = match utils::get_utxo_confirmations(rpc, prevout.txid, prevout.vout) {
+            Ok(v) => v as u64,
+            Err(e) => return Err(format!("fail to get {}:{} confirmations: {}", prevout.txid, prevout.vout, e))
+        };
+        // an unconfirmed prevout is mined at tip + 1 at the earliest
+        let prevout_height = if confirmations == 0 { tip + 1 } else { tip + 1 - confirmations };
+        spendable_height = spendable_height.max(prevout_height + timelock);
+    };
+    if tx.lock_time.is_block_height() {
+        // a block height lock_time allows mining at lock_time + 1
+        spendable_height = spendable_height.max(tx.lock_time.to_consensus_u32() as u64 + 1);
+    };
+    Ok(spendable_height)
+}
+
+// outcome of broadcast_after_timelock
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Broadcast {
+    Sent(Txid, FeeInfo),
+    // handed over to the scheduler, broadcast once the tip reaches the height
+    Scheduled(Txid, u64, FeeInfo),
+}
+
+impl Broadcast {
+    pub fn txid(&self) -> Txid {
+        match self {
+            Broadcast::Sent(txid, _) => *txid,
+            Broadcast::Scheduled(txid, _, _) => *txid,
+        }
+    }
+
+    pub fn fee_info(&self) -> FeeInfo {
+        match self {
+            Broadcast::Sent(_, fee_info) => *fee_info,
+            Broadcast::Scheduled(_, _, fee_info) => *fee_info,
+        }
+    }
+
+    pub fn scheduled_height(&self) -> Option<u64> {
+        match self {
+            Broadcast::Sent(..) => None,
+            Broadcast::Scheduled(_, height, _) => Some(*height),
+        }
+    }
+
+    // for callers that need the tx out now
+    pub fn sent(self) -> Result<Txid, String> {
+        match self {
+            Broadcast::Sent(txid, _) => Ok(txid),
+            Broadcast::Scheduled(txid, height, _) => Err(format!("{txid} is scheduled at height {height}, not broadcast yet")),
+        }
+    }
+}
+
+// broadcast once every timelock of the tx matures:
+// on regtest mint the blocks needed (if REGTEST_MINE_TO_MATURITY), otherwise hand it over to the scheduler
+pub async fn broadcast_after_timelock(rpc: &Client, tx: &Transaction, tx_name: &str) -> Result<Broadcast, String> {
+    let txid = tx.compute_txid();
+    let fee_info = match get_fee_info(rpc, tx) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get {tx_name} fee: {}", e))
+    };
+    let spendable_height = match get_spendable_height(rpc, tx) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get {tx_name} spendable height: {}", e))
+    };
+    let tip = match utils::get_block_count(rpc) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    if spendable_height > tip + 1 {
+        let remaining = spendable_height - tip - 1;
+        if network() == bitcoin::Network::Regtest && config::REGTEST_MINE_TO_MATURITY {
+            if let Err(e) = utils::mint_block(rpc, remaining) {
+                return Err(format!("fail to mint block: {}", e))
+            };
+        } else {
+            let db = match sql::open_db() {
+                Ok(v) => v,
+                Err(e) => return Err(format!("fail to connect db: {}", e))
+            };
+            if let Err(e) = sql::add_scheduled_tx(&db, &txid, tx_name, &utils::tx_wrapper(tx), spendable_height) {
+                return Err(e)
+            };
+            info!("{tx_name} tx {txid} scheduled at height {spendable_height}, {remaining} blocks remaining");
+            return Ok(Broadcast::Scheduled(txid, spendable_height, fee_info))
+        };
+    };
+    if let Err(e) = broadcast_at_target_fee(rpc, tx, tx_name, &fee_info) {
+        return Err(e)
+    };
+    if network() != bitcoin::Network::Regtest {
+        // no blocks to mint, let the scheduler watch the confirmation (& bump the fee if stuck)
+        return match watch_confirmation(rpc, tx, tx_name) {
+            Ok(_) => Ok(Broadcast::Sent(txid, fee_info)),
+            Err(e) => Err(e),
+        }
+    };
+    wait_tx().await;
+    if let Err(e) = utils::mint_block(rpc, 1) {
+        return Err(format!("fail to mint block: {}", e))
+    };
+    wait_tx().await;
+    match utils::validate_tx(rpc, txid) {
+        Ok(valid) => {
+            if !valid { 
+                return Err(format!("{tx_name} tx is gone?!"))
+            }
+        },
+        Err(e) => return Err(format!("fail to validate tx: {}", e))
+    };
+    Ok(Broadcast::Sent(txid, fee_info))
+}
+
+// pre-signed txs can't raise their own fee: one paying less than the current estimate goes out
+// along with a cpfp child, and is refused when it has no output to bump it with
+pub fn broadcast_at_target_fee(rpc: &Client, tx: &Transaction, tx_name: &str, fee_info: &FeeInfo) -> Result<(), String> {
+    let txid = tx.compute_txid();
+    if fee_info.fee >= fee_info.required_fee {
+        return match utils::broadcast_tx(rpc, tx) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("fail to broadcast {tx_name} tx: {}", e))
+        }
+    };
+    let child = match cpfp_prepare(rpc, tx, fee_info.target_fee_rate) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("{tx_name} tx {txid} pays {} sat/vB, below the estimate {} sat/vB, and can not be bumped: {}", fee_info.fee_rate, fee_info.target_fee_rate, e))
+    };
+    let child_txid = child.compute_txid();
+    if let Err(e) = utils::submit_package(rpc, &vec![tx.clone(), child]) {
+        return Err(format!("fail to submit {tx_name} cpfp package: {}", e))
+    };
+    info!("{tx_name} tx {txid} pays {} sat/vB, sent along with cpfp child {child_txid}", fee_info.fee_rate);
+    Ok(())
+}
+
+// record a broadcast tx for the scheduler, which bumps its fee if it stays unconfirmed
+pub fn watch_confirmation(rpc: &Client, tx: &Transaction, tx_name: &str) -> Result<bool, String> {
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to connect db: {}", e))
+    };
+    let height = match utils::get_block_count(rpc) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    sql::add_pending_tx(&db, &tx.compute_txid(), tx_name, &utils::tx_wrapper(tx), height)
+}
+
+// keys held by the service whose pay-to-pubkey outputs can anchor a CPFP child
+fn get_service_keys() -> Vec<(Keypair, PublicKey)> {
+    let operator_context = config::get_operator_context();
+    let depositor_context = config::get_depositor_context();
+    let mut keys = vec![
+        (operator_context.operator_keypair, operator_context.operator_public_key),
+        (depositor_context.depositor_keypair, depositor_context.depositor_public_key),
+    ];
+    for verifier_context in config::get_verifier_contexts() {
+        keys.push((verifier_context.verifier_keypair, verifier_context.verifier_public_key));
+    }
+    keys
+}
+
+// parent output a cpfp child can spend: locked to a service key, or to an address of the wallet
+// (e.g. take-1/take-2 paying the operator's or user's own wallet address)
+enum CpfpAnchor {
+    Service(Keypair, PublicKey),
+    Wallet,
+}
+
+fn find_cpfp_anchor(rpc: &Client, parent: &Transaction) -> Result<(u32, CpfpAnchor), String> {
+    for (vout, txout) in parent.output.iter().enumerate() {
+        for (keypair, public_key) in get_service_keys() {
+            if txout.script_pubkey == generate_pay_to_pubkey_script_address(network(), &public_key).script_pubkey() {
+                return Ok((vout as u32, CpfpAnchor::Service(keypair, public_key)))
+            };
+        }
+    };
+    for (vout, txout) in parent.output.iter().enumerate() {
+        if txout.value.to_sat() < DUST_AMOUNT {
+            continue
+        };
+        let address = match Address::from_script(&txout.script_pubkey, network()) {
+            Ok(v) => v,
+            Err(_) => continue
+        };
+        match utils::is_wallet_address(rpc, &address) {
+            Ok(true) => return Ok((vout as u32, CpfpAnchor::Wallet)),
+            Ok(false) => {},
+            Err(e) => return Err(e)
+        };
+    };
+    Err(format!("tx {} has no output the service or the wallet can spend for cpfp", parent.compute_txid()))
+}
+
+// child paying for `parent`: spends a parent output held by the service or the wallet plus a confirmed
+// wallet utxo (an unconfirmed one would be a parent outside the package), so that the package reaches target_fee_rate
+pub fn cpfp_prepare(rpc: &Client, parent: &Transaction, target_fee_rate: u64) -> Result<Transaction, String> {
+    let parent_txid = parent.compute_txid();
+    let (anchor_vout, anchor) = match find_cpfp_anchor(rpc, parent) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    let anchor_outpoint = OutPoint { txid: parent_txid, vout: anchor_vout };
+    let anchor_txout = parent.output[anchor_vout as usize].clone();
+
+    let parent_fee = match utils::get_tx_fee(rpc, parent) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    let parent_vsize = parent.vsize() as u64;
+    if parent_fee.to_sat() >= target_fee_rate * parent_vsize {
+        return Err(format!("tx {parent_txid} already pays {} sat/vB", parent_fee.to_sat() / parent_vsize))
+    };
+
+    // fund generously, the change goes back to the wallet
+    let min_funding_amount = Amount::from_sat(target_fee_rate * (parent_vsize + 500) + DUST_AMOUNT);
+    let (funding_outpoint, funding_txout) = match utils::get_confirmed_wallet_utxo(rpc, min_funding_amount) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to fund cpfp input: {}", e))
+    };
+    let change_address = match rpc.get_new_address(None, None) {
+        Ok(v) => match utils::address_from_str(&v.to_string()) {
+            Ok(v) => v,
+            Err(e) => return Err(e)
+        },
+        Err(e) => return Err(format!("fail to get change address: {}", e))
+    };
+    let input_amount = anchor_txout.value + funding_txout.value;
+    let new_input = |outpoint: OutPoint| TxIn {
+        previous_output: outpoint,
+        script_sig: ScriptBuf::new(),
+        sequence: Sequence::MAX,
+        witness: Witness::default(),
+    };
+    let mut child = Transaction {
+        version: bitcoin::transaction::Version(2),
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![
+            new_input(anchor_outpoint),
+            new_input(funding_outpoint),
+        ],
+        output: vec![TxOut {
+            value: input_amount,
+            script_pubkey: change_address.script_pubkey(),
+        }],
+    };
+    let sign = |child: &mut Transaction| -> Result<(), String> {
+        if let CpfpAnchor::Service(keypair, public_key) = &anchor {
+            utils::sign_p2wsh_p2pk_input(child, 0, keypair, public_key, anchor_txout.value, EcdsaSighashType::All);
+        };
+        // the wallet signs the funding input (& a wallet anchor), keeping the anchor signature above
+        *child = utils::sign_with_wallet(rpc, child, &[(anchor_outpoint, anchor_txout.clone())])?;
+        Ok(())
+    };
+
+    // sign once to learn the child's vsize, then settle the fee & sign again
+    sign(&mut child)?;
+    let package_fee = target_fee_rate * (parent_vsize + child.vsize() as u64);
+    let child_fee = Amount::from_sat(package_fee.saturating_sub(parent_fee.to_sat()));
+    child.output[0].value = match input_amount.checked_sub(child_fee) {
+        Some(v) if v >= Amount::from_sat(DUST_AMOUNT) => v,
+        _ => return Err(format!("cpfp inputs {input_amount} can not cover fee {child_fee}")),
+    };
+    sign(&mut child)?;
+    Ok(child)
+}
+
+// return: cpfp child txid
+pub async fn bump_fee(rpc: &Client, parent: &Transaction, tx_name: &str) -> Result<Txid, String> {
+    let _timer = metrics::StepTimer::new("cpfp");
+    let parent_txid = parent.compute_txid();
+    let child = match cpfp_prepare(rpc, parent, utils::get_fee_rate(rpc)) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    let child_txid = child.compute_txid();
+    if let Err(e) = utils::submit_package(rpc, &vec![parent.clone(), child]) {
+        return Err(format!("fail to submit {tx_name} cpfp package: {}", e))
+    };
+    info!("{tx_name} tx {parent_txid} bumped by cpfp child {child_txid}");
+    if network() == bitcoin::Network::Regtest {
+        wait_tx().await;
+        if let Err(e) = utils::mint_block(rpc, 1) {
+            return Err(format!("fail to mint block: {}", e))
+        };
+        wait_tx().await;
+        // the child confirms only along with its parent
+        match utils::validate_tx(rpc, child_txid) {
+            Ok(valid) => {
+                if !valid { 
+                    return Err(format!("{tx_name} cpfp package is gone?!"))
+                }
+            },
+            Err(e) => return Err(format!("fail to validate tx: {}", e))
+        };
+    };
+    Ok(child_txid)
+}
+
+// csv blocks required by the given input, 0 if it has no relative timelock
+pub fn get_relative_timelock_blocks(tx: &Transaction, input_index: usize) -> u16 {
+    match tx.input[input_index].sequence.to_relative_lock_time() {
+        Some(bitcoin::relative::LockTime::Blocks(height)) => height.value(),
+        _ => 0,
+    }
+}
+
+// vsize once every (still unsigned) input carries a user witness
+pub fn estimate_signed_vsize(tx: &Transaction) -> u64 {
+    let mut weight = tx.weight().to_wu();
+    if tx.input.iter().all(|input| input.witness.is_empty()) {
+        weight += 2; // segwit marker & flag
+    };
+    for input in tx.input.iter() {
+        if input.witness.is_empty() {
+            weight += USER_INPUT_WITNESS_WEIGHT;
+        };
+    };
+    (weight + 3) / 4
+}
+
+pub fn get_fee_info(rpc: &Client, tx: &Transaction) -> Result<FeeInfo, String> {
+    let fee = match utils::get_tx_fee(rpc, tx) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    Ok(new_fee_info(fee, estimate_signed_vsize(tx), utils::get_fee_rate(rpc)))
+}
+
+pub fn new_fee_info(fee: Amount, vsize: u64, target_fee_rate: u64) -> FeeInfo {
+    FeeInfo {
+        fee,
+        vsize,
+        fee_rate: fee.to_sat() / vsize.max(1),
+        target_fee_rate,
+        required_fee: Amount::from_sat(target_fee_rate * vsize),
+    }
+}
+
+// fee of a tx whose outputs sum to (input - fee), at the current fee rate
+fn estimate_user_tx_fee(rpc: &Client, tx_template: &Transaction) -> Amount {
+    Amount::from_sat(utils::get_fee_rate(rpc) * estimate_signed_vsize(tx_template))
 }
 
 pub fn kickoff_1_prepare(rpc: &Client, faucet_2_txid: Txid, faucet_2_vout: u32) -> Result<Transaction, String> {
@@ -126,8 +820,6 @@ pub fn kickoff_1_prepare(rpc: &Client, faucet_2_txid: Txid, faucet_2_vout: u32)
         witness: Witness::default(),
     };
 
-    let total_output_amount = input_amount - Amount::from_sat(FEE_AMOUNT);
-
     let connector_1 = Connector1::new(
         network(),
         &operator_context.operator_taproot_public_key,
@@ -150,7 +842,7 @@ pub fn kickoff_1_prepare(rpc: &Client, faucet_2_txid: Txid, faucet_2_vout: u32)
     };
 
     let output_1 = TxOut {
-        value: total_output_amount - Amount::from_sat(DUST_AMOUNT) * 2,
+        value: input_amount - Amount::from_sat(DUST_AMOUNT) * 2,
         script_pubkey: connector_1.generate_taproot_address().script_pubkey(),
     };
 
@@ -159,54 +851,239 @@ pub fn kickoff_1_prepare(rpc: &Client, faucet_2_txid: Txid, faucet_2_vout: u32)
         script_pubkey: connector_2.generate_taproot_address().script_pubkey(),
     };
 
-    Ok(Transaction {
+    let mut tx = Transaction {
         version: bitcoin::transaction::Version(2),
         lock_time: absolute::LockTime::ZERO,
         input: vec![input_0],
         output: vec![output_0, output_1, output_2],
-    })
+    };
+    // the fee is taken from the output holding the remaining value
+    let fee = estimate_user_tx_fee(rpc, &tx);
+    let change_amount = match tx.output[1].value.checked_sub(fee) {
+        Some(v) => v,
+        _ => return Err(format!("input amount {input_amount} can not cover fee {fee}"))
+    };
+    tx.output[1].value = change_amount;
+    Ok(tx)
+}
+
+pub fn kick_off_2_prepare(rpc: &Client, kick_off_1_txid: Txid, bitcom_lock_scripts: &Vec<Script>) -> Result<Transaction, String> {
+    let operator_context = config::get_operator_context();
+    let connector_1_vout = 1;
+    let connector_1_amount = match utils::get_utxo_value(rpc, kick_off_1_txid, connector_1_vout) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get connector_a value: {}", e))
+    };
+    let revealers = get_revealers(&operator_context.n_of_n_taproot_public_key, bitcom_lock_scripts);
+    let kick_off_2_tx = KickOff2Transaction::new(
+        &operator_context,
+        Input {
+            outpoint: OutPoint{
+                txid: kick_off_1_txid,
+                vout: connector_1_vout,
+            },
+            amount: connector_1_amount,
+        },
+        revealers,
+    );
+    Ok(kick_off_2_tx.finalize())
+}
+
+pub async fn kick_off_2(rpc: &Client, kick_off_1_txid: Txid, bitcom_lock_scripts: &Vec<Script>) -> Result<Broadcast, String> {
+    let _timer = metrics::StepTimer::new("kickoff2");
+    let tx = match kick_off_2_prepare(rpc, kick_off_1_txid, bitcom_lock_scripts) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    broadcast_after_timelock(rpc, &tx, "kick_off_2").await
+}
+
+// operator commits the start time, spends connector_2 of kickoff_1
+pub fn start_time_prepare(rpc: &Client, kick_off_1_txid: Txid) -> Result<Transaction, String> {
+    let operator_context = config::get_operator_context();
+    let connector_2_vout = 2;
+    let connector_2_amount = match utils::get_utxo_value(rpc, kick_off_1_txid, connector_2_vout) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get connector_2 value: {}", e))
+    };
+    let start_time_tx = StartTimeTransaction::new(
+        &operator_context,
+        Input {
+            outpoint: OutPoint {
+                txid: kick_off_1_txid,
+                vout: connector_2_vout,
+            },
+            amount: connector_2_amount,
+        },
+    );
+    Ok(start_time_tx.finalize())
+}
+
+// operator never committed a start time, burns connector_1 & connector_2 of kickoff_1
+pub fn start_time_timeout_prepare(rpc: &Client, kick_off_1_txid: Txid, reward_address: Option<Address>) -> Result<Transaction, String> {
+    let operator_context = config::get_operator_context();
+    let verifier_contexts = config::get_verifier_contexts();
+    let connector_1_vout = 1;
+    let connector_2_vout = 2;
+    let connector_1_amount = match utils::get_utxo_value(rpc, kick_off_1_txid, connector_1_vout) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get connector_1 value: {}", e))
+    };
+    let connector_2_amount = match utils::get_utxo_value(rpc, kick_off_1_txid, connector_2_vout) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get connector_2 value: {}", e))
+    };
+    let mut start_time_timeout = StartTimeTimeoutTransaction::new(
+        &operator_context,
+        Input {
+            outpoint: OutPoint {
+                txid: kick_off_1_txid,
+                vout: connector_2_vout,
+            },
+            amount: connector_2_amount,
+        },
+        Input {
+            outpoint: OutPoint {
+                txid: kick_off_1_txid,
+                vout: connector_1_vout,
+            },
+            amount: connector_1_amount,
+        },
+    );
+
+    let secret_nonces_0 = start_time_timeout.push_nonces(&verifier_contexts[0]);
+    let secret_nonces_1 = start_time_timeout.push_nonces(&verifier_contexts[1]);
+
+    start_time_timeout.pre_sign(&verifier_contexts[0], &secret_nonces_0);
+    start_time_timeout.pre_sign(&verifier_contexts[1], &secret_nonces_1);
+
+    // re-use verifier_0 as the reward receiver
+    let reward_address = match reward_address {
+        Some(addr) => addr,
+        _ => generate_pay_to_pubkey_script_address(
+            verifier_contexts[0].network,
+            &verifier_contexts[0].verifier_public_key,
+        ),
+    };
+    start_time_timeout.add_output(reward_address.script_pubkey());
+    Ok(start_time_timeout.finalize())
 }
 
-pub async fn kick_off_2(rpc: &Client, kick_off_1_txid: Txid, bitcom_lock_scripts: &Vec<Script>) -> Result<Txid, String> {
+// operator never sent kickoff_2, burns connector_1 of kickoff_1
+pub fn kick_off_timeout_prepare(rpc: &Client, kick_off_1_txid: Txid, reward_address: Option<Address>) -> Result<Transaction, String> {
     let operator_context = config::get_operator_context();
+    let verifier_contexts = config::get_verifier_contexts();
     let connector_1_vout = 1;
     let connector_1_amount = match utils::get_utxo_value(rpc, kick_off_1_txid, connector_1_vout) {
         Ok(v) => v,
-        Err(e) => return Err(format!("fail to get connector_a value: {}", e))
+        Err(e) => return Err(format!("fail to get connector_1 value: {}", e))
     };
-    let revealers = get_revealers(&operator_context.n_of_n_taproot_public_key, bitcom_lock_scripts);
-    let kick_off_2_tx = KickOff2Transaction::new(
+    let mut kick_off_timeout = KickOffTimeoutTransaction::new(
         &operator_context,
         Input {
-            outpoint: OutPoint{
+            outpoint: OutPoint {
                 txid: kick_off_1_txid,
                 vout: connector_1_vout,
             },
             amount: connector_1_amount,
         },
-        revealers,
     );
-    let tx = kick_off_2_tx.finalize();
-    let kick_off_2_txid = tx.compute_txid();
-    
-    if let Err(e) = utils::broadcast_tx(rpc, &tx) {
-        return Err(format!("fail to broadcast kickoff_2 tx: {}", e))
+
+    let secret_nonces_0 = kick_off_timeout.push_nonces(&verifier_contexts[0]);
+    let secret_nonces_1 = kick_off_timeout.push_nonces(&verifier_contexts[1]);
+
+    kick_off_timeout.pre_sign(&verifier_contexts[0], &secret_nonces_0);
+    kick_off_timeout.pre_sign(&verifier_contexts[1], &secret_nonces_1);
+
+    // re-use verifier_0 as the reward receiver
+    let reward_address = match reward_address {
+        Some(addr) => addr,
+        _ => generate_pay_to_pubkey_script_address(
+            verifier_contexts[0].network,
+            &verifier_contexts[0].verifier_public_key,
+        ),
     };
-    wait_tx().await;
-    if let Err(e) = utils::mint_block(rpc, 1) {
-        return Err(format!("fail to mint block: {}", e))
+    kick_off_timeout.add_output(reward_address.script_pubkey());
+    Ok(kick_off_timeout.finalize())
+}
+
+// return: start_time_txid
+pub async fn start_time(rpc: &Client, kick_off_1_txid: Txid) -> Result<Broadcast, String> {
+    let _timer = metrics::StepTimer::new("start-time");
+    let tx = match start_time_prepare(rpc, kick_off_1_txid) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
     };
-    wait_tx().await;
-    match utils::validate_tx(rpc, kick_off_1_txid) {
-        Ok(valid) => {
-            if !valid { 
-                return Err(format!("kickoff_2 tx is gone?!"))
-            }
-        },
-        Err(e) => return Err(format!("fail to validate tx: {}", e))
+    broadcast_after_timelock(rpc, &tx, "start_time").await
+}
+
+// return: start_time_timeout_txid
+pub async fn start_time_timeout(rpc: &Client, kick_off_1_txid: Txid, reward_address: Option<Address>) -> Result<Broadcast, String> {
+    let _timer = metrics::StepTimer::new("start-time-timeout");
+    let tx = match start_time_timeout_prepare(rpc, kick_off_1_txid, reward_address) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    broadcast_after_timelock(rpc, &tx, "start_time_timeout").await
+}
+
+// return: kick_off_timeout_txid
+pub async fn kick_off_timeout(rpc: &Client, kick_off_1_txid: Txid, reward_address: Option<Address>) -> Result<Broadcast, String> {
+    let _timer = metrics::StepTimer::new("kickoff-timeout");
+    let tx = match kick_off_timeout_prepare(rpc, kick_off_1_txid, reward_address) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    broadcast_after_timelock(rpc, &tx, "kick_off_timeout").await
+}
+
+// operator fronts the peg-in amount (less fee) to the withdrawer from its own funds,
+// take_1/take_2 reimburse it from the peg-in later
+pub fn peg_out_prepare(rpc: &Client, peg_in_txid: Txid, withdrawer_address: &Address) -> Result<Transaction, String> {
+    let operator_context = config::get_operator_context();
+    let peg_in_amount = match utils::get_utxo_value(rpc, peg_in_txid, 0) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get connector_0 value: {}", e))
     };
+    let operator_address = generate_pay_to_pubkey_script_address(network(), &operator_context.operator_public_key);
+    let funding_outpoint = match utils::fund_utxo(rpc, &operator_address, peg_in_amount) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to fund peg_out input: {}", e))
+    };
+    let mut tx = Transaction {
+        version: bitcoin::transaction::Version(2),
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: funding_outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::default(),
+        }],
+        output: vec![TxOut {
+            value: peg_in_amount,
+            script_pubkey: withdrawer_address.script_pubkey(),
+        }],
+    };
+
+    // sign once to learn the vsize, then settle the fee & sign again
+    utils::sign_p2wsh_p2pk_input(&mut tx, 0, &operator_context.operator_keypair, &operator_context.operator_public_key, peg_in_amount, EcdsaSighashType::All);
+    let fee = Amount::from_sat(utils::get_fee_rate(rpc) * tx.vsize() as u64);
+    tx.output[0].value = match peg_in_amount.checked_sub(fee) {
+        Some(v) => v,
+        _ => return Err(format!("peg-in amount {peg_in_amount} can not cover fee {fee}"))
+    };
+    utils::sign_p2wsh_p2pk_input(&mut tx, 0, &operator_context.operator_keypair, &operator_context.operator_public_key, peg_in_amount, EcdsaSighashType::All);
+    Ok(tx)
+}
 
-    Ok(kick_off_2_txid)
+// return: peg_out_txid
+pub async fn peg_out(rpc: &Client, peg_in_txid: Txid, withdrawer_address: &Address) -> Result<Broadcast, String> {
+    let _timer = metrics::StepTimer::new("pegout");
+    let tx = match peg_out_prepare(rpc, peg_in_txid, withdrawer_address) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    broadcast_after_timelock(rpc, &tx, "peg_out").await
 }
 
 // return: peg_in_txid
@@ -285,7 +1162,8 @@ pub fn kick_off_1(rpc: &Client) -> Txid {
 }
 
 // return: (take_1_txid, take_1_tx_weight)
-pub async fn take_1(rpc: &Client, peg_in_txid: Txid, kick_off_1_txid: Txid, kick_off_2_txid: Txid, receive_address: Address) -> Result<Txid, String> {
+// receive_address: None to reimburse the operator, who fronted the peg-out
+pub fn take_1_prepare(rpc: &Client, peg_in_txid: Txid, kick_off_1_txid: Txid, kick_off_2_txid: Txid, receive_address: Option<Address>) -> Result<Transaction, String> {
     let operator_context = config::get_operator_context();
     let verifier_contexts = config::get_verifier_contexts();
 
@@ -337,14 +1215,23 @@ pub async fn take_1(rpc: &Client, peg_in_txid: Txid, kick_off_1_txid: Txid, kick
         },
         amount: connector_b_amount,
     };
-    let mut take_1_tx = Take1Transaction::new_for_designated_receiver(
-        &operator_context,
-        take_1_input_0,
-        take_1_input_1,
-        take_1_input_2,
-        take_1_input_3,
-        receive_address,
-    );
+    let mut take_1_tx = match receive_address {
+        Some(addr) => Take1Transaction::new_for_designated_receiver(
+            &operator_context,
+            take_1_input_0,
+            take_1_input_1,
+            take_1_input_2,
+            take_1_input_3,
+            addr,
+        ),
+        _ => Take1Transaction::new(
+            &operator_context,
+            take_1_input_0,
+            take_1_input_1,
+            take_1_input_2,
+            take_1_input_3,
+        ),
+    };
 
     let secret_nonces_0 = take_1_tx.push_nonces(&verifier_contexts[0]);
     let secret_nonces_1 = take_1_tx.push_nonces(&verifier_contexts[1]);
@@ -352,30 +1239,30 @@ pub async fn take_1(rpc: &Client, peg_in_txid: Txid, kick_off_1_txid: Txid, kick
     take_1_tx.pre_sign(&verifier_contexts[0], &secret_nonces_0);
     take_1_tx.pre_sign(&verifier_contexts[1], &secret_nonces_1);
 
-    let tx = take_1_tx.finalize();
-    let take_1_txid = tx.compute_txid();
+    Ok(take_1_tx.finalize())
+}
 
-    if let Err(e) = utils::broadcast_tx(rpc, &tx) {
-        return Err(format!("fail to broadcast take_1 tx: {}", e))
-    };
-    wait_tx().await;
-    if let Err(e) = utils::mint_block(rpc, 1) {
-        return Err(format!("fail to mint block: {}", e))
-    };
-    wait_tx().await;
-    match utils::validate_tx(rpc, take_1_txid) {
-        Ok(valid) => {
-            if !valid { 
-                return Err(format!("take_1 tx is gone?!"))
-            }
-        },
-        Err(e) => return Err(format!("fail to validate tx: {}", e))
+pub async fn take_1(rpc: &Client, peg_in_txid: Txid, kick_off_1_txid: Txid, kick_off_2_txid: Txid, receive_address: Option<Address>) -> Result<Broadcast, String> {
+    let _timer = metrics::StepTimer::new("take1");
+    let tx = match take_1_prepare(rpc, peg_in_txid, kick_off_1_txid, kick_off_2_txid, receive_address) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
     };
-    Ok(take_1_txid)
+    broadcast_after_timelock(rpc, &tx, "take_1").await
 }
 
 // return: (challenge_txid, challenge_tx_weight)
-pub async fn challenge(rpc: &Client, kick_off_1_txid: Txid) -> Result<Txid, String> {
+pub async fn challenge(rpc: &Client, kick_off_1_txid: Txid) -> Result<Broadcast, String> {
+    let _timer = metrics::StepTimer::new("challenge");
+    let tx = match challenge_prepare(rpc, kick_off_1_txid) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    broadcast_after_timelock(rpc, &tx, "challenge").await
+}
+
+// fund (from a stub challenger) & sign the challenge tx without broadcasting it
+pub fn challenge_prepare(rpc: &Client, kick_off_1_txid: Txid) -> Result<Transaction, String> {
     let depositor_context = config::get_depositor_context();
     let operator_context = config::get_operator_context();
     let connector_a_vout = 0;
@@ -416,25 +1303,161 @@ pub async fn challenge(rpc: &Client, kick_off_1_txid: Txid) -> Result<Txid, Stri
         crowdfunding_keypair,
         refund_address.script_pubkey(),
     );
-    let tx = challenge_tx.finalize();
-    let challenge_txid = tx.compute_txid();
-    if let Err(e) = utils::broadcast_tx(rpc, &tx) {
-        return Err(format!("fail to broadcast challenge tx: {}", e))
-    };
-    wait_tx().await;
-    if let Err(e) = utils::mint_block(rpc, 1) {
-        return Err(format!("fail to mint block: {}", e))
+    Ok(challenge_tx.finalize())
+}
+
+// challenge tx spending connector_a, before any crowdfunding input is added
+fn challenge_crowdfund_base(rpc: &Client, kick_off_1_txid: Txid) -> Result<Transaction, String> {
+    let operator_context = config::get_operator_context();
+    let connector_a_vout = 0;
+    let connector_a_amount = match utils::get_utxo_value(rpc, kick_off_1_txid, connector_a_vout) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get connector_a value: {}", e))
     };
-    wait_tx().await;
-    match utils::validate_tx(rpc, challenge_txid) {
-        Ok(valid) => {
-            if !valid { 
-                return Err(format!("challenge tx is gone?!"))
-            }
+    let challenge_tx = ChallengeTransaction::new(
+        &operator_context,
+        Input {
+            outpoint: OutPoint{
+                txid: kick_off_1_txid,
+                vout: connector_a_vout
+            },
+            amount: connector_a_amount,
         },
-        Err(e) => return Err(format!("fail to validate tx: {}", e))
+        Amount::from_sat(config::CHALLENGE_AMOUNT),
+    );
+    Ok(challenge_tx.finalize())
+}
+
+// contributions appended after connector_a, each input at the same index as its refund output
+fn append_challenge_contributions(tx: &mut Transaction, contributions: &Vec<ChallengeContribution>) -> Result<(), String> {
+    for contribution in contributions.iter() {
+        let mut witness = Witness::new();
+        for item in contribution.witness.iter() {
+            match hex::decode(item) {
+                Ok(v) => witness.push(v),
+                Err(e) => return Err(format!("invalid witness of {}: {}", contribution.outpoint, e))
+            };
+        }
+        // keep input i & output i paired for SIGHASH_SINGLE
+        if tx.input.len() != tx.output.len() {
+            return Err("challenge tx inputs & outputs are not paired".to_string())
+        };
+        tx.input.push(TxIn {
+            previous_output: contribution.outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness,
+        });
+        tx.output.push(TxOut {
+            value: contribution.refund_amount,
+            script_pubkey: contribution.refund_script_pubkey.clone(),
+        });
+    }
+    Ok(())
+}
+
+// SIGHASH_SINGLE|ANYONECANPAY only commits to the contribution's input & refund output, so a trial tx of just
+// that pair (same version & lock_time as the challenge tx) checks its script, signature & amount
+pub fn verify_challenge_contribution(rpc: &Client, contribution: &ChallengeContribution) -> Result<(), String> {
+    let mut tx = Transaction {
+        version: bitcoin::transaction::Version(2),
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![],
+        output: vec![],
+    };
+    if let Err(e) = append_challenge_contributions(&mut tx, &vec![contribution.clone()]) {
+        return Err(e)
+    };
+    match utils::test_mempool_accept(rpc, &tx) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("contribution {} {}", contribution.outpoint, e))
+    }
+}
+
+// return: (collected, required), the challenge can be broadcast once collected >= required
+pub fn get_challenge_crowdfund_progress(rpc: &Client, kick_off_1_txid: Txid, contributions: &Vec<ChallengeContribution>) -> Result<(Amount, Amount), String> {
+    let mut tx = match challenge_crowdfund_base(rpc, kick_off_1_txid) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    let connector_a_amount = match utils::get_utxo_value(rpc, kick_off_1_txid, 0) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get connector_a value: {}", e))
+    };
+    let base_output_amount: Amount = tx.output.iter().map(|o| o.value).sum();
+    if let Err(e) = append_challenge_contributions(&mut tx, contributions) {
+        return Err(e)
+    };
+    let collected: Amount = contributions.iter().map(|c| c.amount - c.refund_amount).sum();
+    let fee = Amount::from_sat(utils::get_fee_rate(rpc) * tx.vsize() as u64);
+    let required = (base_output_amount + fee).checked_sub(connector_a_amount).unwrap_or(Amount::ZERO);
+    Ok((collected, required))
+}
+
+pub fn challenge_crowdfund_prepare(rpc: &Client, kick_off_1_txid: Txid, contributions: &Vec<ChallengeContribution>) -> Result<Transaction, String> {
+    let (collected, required) = match get_challenge_crowdfund_progress(rpc, kick_off_1_txid, contributions) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    if collected < required {
+        return Err(format!("challenge crowdfunding {collected} does not cover {required} yet"))
+    };
+    let mut tx = match challenge_crowdfund_base(rpc, kick_off_1_txid) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    if let Err(e) = append_challenge_contributions(&mut tx, contributions) {
+        return Err(e)
+    };
+    Ok(tx)
+}
+
+// return: challenge_txid
+pub async fn challenge_crowdfund(rpc: &Client, kick_off_1_txid: Txid, contributions: &Vec<ChallengeContribution>) -> Result<Broadcast, String> {
+    let _timer = metrics::StepTimer::new("challenge");
+    let tx = match challenge_crowdfund_prepare(rpc, kick_off_1_txid, contributions) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    broadcast_after_timelock(rpc, &tx, "challenge").await
+}
+
+// fund & sign a contribution from a throwaway key, stands in for a third-party challenger
+pub fn stub_challenge_contribution(rpc: &Client, contribution_amount: Amount) -> Result<ChallengeContribution, String> {
+    let (_, keypair, public_key) = generate_keys_from_secret(network(), &utils::random_hex(32));
+    let address = generate_pay_to_pubkey_script_address(network(), &public_key);
+    let amount = contribution_amount + Amount::from_sat(DUST_AMOUNT);
+    let outpoint = match utils::fund_utxo(rpc, &address, amount) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to fund challenge contribution: {}", e))
     };
-    Ok(challenge_txid)
+    let refund_script_pubkey = address.script_pubkey();
+    let refund_amount = Amount::from_sat(DUST_AMOUNT);
+
+    // SIGHASH_SINGLE|ANYONECANPAY only commits to this input & the output at its index,
+    // so a one-input one-output tx of the same version & lock_time yields the same signature
+    let mut tx = Transaction {
+        version: bitcoin::transaction::Version(2),
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::default(),
+        }],
+        output: vec![TxOut {
+            value: refund_amount,
+            script_pubkey: refund_script_pubkey.clone(),
+        }],
+    };
+    utils::sign_p2wsh_p2pk_input(&mut tx, 0, &keypair, &public_key, amount, EcdsaSighashType::SinglePlusAnyoneCanPay);
+    Ok(ChallengeContribution {
+        outpoint,
+        amount,
+        witness: tx.input[0].witness.iter().map(hex::encode).collect(),
+        refund_script_pubkey,
+        refund_amount,
+    })
 }
 
 // return: ((assert_txid, assert_tx_weight), connector_c_address)
@@ -443,9 +1466,10 @@ pub async fn assert(
     kick_off_2_txid: Txid, 
     bitcom_lock_scripts: &Vec<Script>,
     connector_c_tapscripts: &Vec<Script>,
-    corrupt_index: Option<u32>,
+    bitcom_unlock_scripts: Vec<Script>,
     connector_c_addr: Option<Address>
-) -> Result<(Txid, Address), String> {
+) -> Result<(Txid, Address, FeeInfo), String> {
+    let _timer = metrics::StepTimer::new("assert");
     let operator_context = config::get_operator_context();
     let connector_b_vout = 1; 
     let connector_b_amount = get_connector_b_amount();
@@ -470,10 +1494,6 @@ pub async fn assert(
     };
     
     let revealers = get_revealers(&operator_context.n_of_n_taproot_public_key, bitcom_lock_scripts);
-    let bitcom_unlock_scripts = match corrupt_index {
-        Some(index) => get_corrupt_bitcom_unlock_scripts(index as usize),
-        _ => borrow_bitcom_unlock_scripts().clone(),
-    };
     let bitcom_inputs = (0..bitcom_unlock_scripts.len())
         .map(|i| Input{
             outpoint: OutPoint {
@@ -494,7 +1514,13 @@ pub async fn assert(
     assert_tx.push_bitcommitments_witness(bitcom_unlock_scripts);
     let tx = assert_tx.finalize();
     let assert_txid = tx.compute_txid();
-    let _ = utils::broadcast_tx(rpc, &tx);
+    let fee_info = match get_fee_info(rpc, &tx) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get assert fee: {}", e))
+    };
+    if let Err(e) = broadcast_at_target_fee(rpc, &tx, "assert", &fee_info) {
+        return Err(e)
+    };
     wait_tx().await;
     let _ = utils::mint_block(rpc, 1);
     wait_tx().await;
@@ -507,18 +1533,20 @@ pub async fn assert(
         },
         Err(e) => return Err(format!("fail to validate tx: {}", e))
     };
-    Ok((assert_txid, connector_c_address))
+    Ok((assert_txid, connector_c_address, fee_info))
 }   
 
 // return: take_2_txid
-pub async fn take_2(
+// receive_address: None to reimburse the operator, who fronted the peg-out
+pub fn take_2_prepare(
     rpc: &Client, 
     peg_in_txid: Txid, 
     assert_txid: Txid, 
     connector_c_tapscripts: &Vec<Script>,
     connector_c_address: Option<Address>,
-    receive_address: Address,
-) -> Result<Txid, String> {
+    connector_c_spend_info: &TaprootSpendInfo,
+    receive_address: Option<Address>,
+) -> Result<Transaction, String> {
     let operator_context = config::get_operator_context();
     let verifier_contexts = config::get_verifier_contexts();
 
@@ -582,17 +1610,27 @@ pub async fn take_2(
         Some(addr) => connector_c.import_taproot_address(addr),
         _ => { connector_c.gen_taproot_address(); },
     };
-    connector_c.import_spend_info(&CONNECTOR_C_SPEND_INFO);
-
-    let mut take_2_tx = Take2Transaction::new_for_designated_receiver(
-        &operator_context,
-        connector_c,
-        take_2_input_0,
-        take_2_input_1,
-        take_2_input_2,
-        take_2_input_3,
-        receive_address,
-    );
+    connector_c.import_spend_info(connector_c_spend_info);
+
+    let mut take_2_tx = match receive_address {
+        Some(addr) => Take2Transaction::new_for_designated_receiver(
+            &operator_context,
+            connector_c,
+            take_2_input_0,
+            take_2_input_1,
+            take_2_input_2,
+            take_2_input_3,
+            addr,
+        ),
+        _ => Take2Transaction::new(
+            &operator_context,
+            connector_c,
+            take_2_input_0,
+            take_2_input_1,
+            take_2_input_2,
+            take_2_input_3,
+        ),
+    };
 
     let secret_nonces_0 = take_2_tx.push_nonces(&verifier_contexts[0]);
     let secret_nonces_1 = take_2_tx.push_nonces(&verifier_contexts[1]);
@@ -600,25 +1638,24 @@ pub async fn take_2(
     take_2_tx.pre_sign(&verifier_contexts[0], &secret_nonces_0);
     take_2_tx.pre_sign(&verifier_contexts[1], &secret_nonces_1);
 
-    let tx = take_2_tx.finalize();
-    let take_2_txid = tx.compute_txid();
-    if let Err(e) = utils::broadcast_tx(rpc, &tx) {
-        return Err(format!("fail to broadcast take_2 tx: {}", e))
-    };
-    wait_tx().await;
-    if let Err(e) = utils::mint_block(rpc, 1) {
-        return Err(format!("fail to mint block: {}", e))
-    };
-    wait_tx().await;
-    match utils::validate_tx(rpc, take_2_txid) {
-        Ok(valid) => {
-            if !valid { 
-                return Err(format!("take_2 tx is gone?!"))
-            }
-        },
-        Err(e) => return Err(format!("fail to validate tx: {}", e))
+    Ok(take_2_tx.finalize())
+}
+
+pub async fn take_2(
+    rpc: &Client, 
+    peg_in_txid: Txid, 
+    assert_txid: Txid, 
+    connector_c_tapscripts: &Vec<Script>,
+    connector_c_address: Option<Address>,
+    connector_c_spend_info: &TaprootSpendInfo,
+    receive_address: Option<Address>,
+) -> Result<Broadcast, String> {
+    let _timer = metrics::StepTimer::new("take2");
+    let tx = match take_2_prepare(rpc, peg_in_txid, assert_txid, connector_c_tapscripts, connector_c_address, connector_c_spend_info, receive_address) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
     };
-    Ok(take_2_txid)
+    broadcast_after_timelock(rpc, &tx, "take_2").await
 }
 
 // return (disprove_txid, disprove_tx_weight)
@@ -627,8 +1664,28 @@ pub async fn disprove(
     assert_txid: Txid, 
     connector_c_tapscripts: &Vec<Script>,
     connector_c_address: Option<Address>,
-    fake_index: Option<usize>,
-) -> Result<Txid, String> {
+    connector_c_spend_info: &TaprootSpendInfo,
+    key_set: &WotsKeySet,
+) -> Result<Broadcast, String> {
+    let _timer = metrics::StepTimer::new("disprove");
+    let tx = match disprove_prepare(rpc, assert_txid, connector_c_tapscripts, connector_c_address, connector_c_spend_info, key_set, None) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    broadcast_after_timelock(rpc, &tx, "disprove").await
+}
+
+// build & sign the disprove tx for the first invalid leaf of the assert tx without broadcasting it
+// challenger_reward_address: None to reward verifier_0
+pub fn disprove_prepare(
+    rpc: &Client, 
+    assert_txid: Txid, 
+    connector_c_tapscripts: &Vec<Script>,
+    connector_c_address: Option<Address>,
+    connector_c_spend_info: &TaprootSpendInfo,
+    key_set: &WotsKeySet,
+    challenger_reward_address: Option<Address>,
+) -> Result<Transaction, String> {
     let operator_context = config::get_operator_context();
     let verifier_contexts = config::get_verifier_contexts();
 
@@ -665,9 +1722,9 @@ pub async fn disprove(
         Some(addr) => connector_c.import_taproot_address(addr),
         _ => { connector_c.gen_taproot_address(); },
     };
-    connector_c.import_spend_info(&CONNECTOR_C_SPEND_INFO);
+    connector_c.import_spend_info(connector_c_spend_info);
 
-    let (leaf_index, hint_script) = match validate_assert_bitcom(rpc, assert_txid, fake_index) {
+    let (leaf_index, hint_script) = match validate_assert_bitcom(rpc, assert_txid, key_set) {
         Ok(res) => match res {
             Some(v) => v,
             _ => return Err(format!("bitcommitments in given assert_tx is completely valid, cannot disprove valid assertions")),
@@ -690,28 +1747,17 @@ pub async fn disprove(
     disprove_tx.pre_sign(&verifier_contexts[1], &secret_nonces_1);
 
     // re-use verifier_0 as challenger
-    let challenger_reward_address = generate_pay_to_pubkey_script_address(
-        verifier_contexts[0].network,
-        &verifier_contexts[0].verifier_public_key,
-    );
+    let challenger_reward_address = match challenger_reward_address {
+        Some(addr) => addr,
+        _ => generate_pay_to_pubkey_script_address(
+            verifier_contexts[0].network,
+            &verifier_contexts[0].verifier_public_key,
+        ),
+    };
     let challenger_reward_script = challenger_reward_address.script_pubkey(); 
     disprove_tx.add_input_output(leaf_index as u32, challenger_reward_script, hint_script);
 
-    let tx = disprove_tx.finalize();
-    let disprove_txid = tx.compute_txid();
-    let _ = utils::broadcast_tx(rpc, &tx);
-    wait_tx().await;
-    let _ = utils::mint_block(rpc, 1);
-    let _ = utils::mint_block(rpc, 1);
-    match utils::validate_tx(rpc, disprove_txid) {
-        Ok(valid) => {
-            if !valid { 
-                return Err(format!("disprove tx is gone?!"))
-            }
-        },
-        Err(e) => return Err(format!("fail to validate tx: {}", e))
-    };
-    Ok(disprove_txid)
+    Ok(disprove_tx.finalize())
 }
 
 
@@ -731,6 +1777,34 @@ pub fn get_connector_b_amount() -> Amount {
 pub fn get_connector_c_amount() -> Amount {
     Amount::from_sat(20000)
 }
+pub fn get_connector_a_address() -> Address {
+    let operator_context = config::get_operator_context();
+    ConnectorA::new(
+        network(),
+        &operator_context.operator_taproot_public_key,
+        &operator_context.n_of_n_taproot_public_key,
+    ).generate_taproot_address()
+}
+pub fn get_connector_1_address() -> Address {
+    let operator_context = config::get_operator_context();
+    Connector1::new(
+        network(),
+        &operator_context.operator_taproot_public_key,
+        &operator_context.n_of_n_taproot_public_key,
+    ).generate_taproot_address()
+}
+pub fn get_connector_2_address() -> Address {
+    let operator_context = config::get_operator_context();
+    Connector2::new(
+        network(),
+        &operator_context.operator_taproot_public_key,
+        &operator_context.n_of_n_taproot_public_key,
+    ).generate_taproot_address()
+}
+pub fn get_connector_3_address() -> Address {
+    let operator_context = config::get_operator_context();
+    Connector3::new(network(), &operator_context.operator_public_key).generate_address()
+}
 pub fn get_precomputed_connector_0_address() -> Address {
     Address::from_str(config::CONNECTOR_0_ADDRESS).unwrap().assume_checked()
 }
@@ -781,8 +1855,7 @@ pub fn borrow_bitcom_lock_scripts() -> &'static Vec<Script> {
 }
 
 pub fn get_bitcom_lock_scripts() -> Vec<Script> {
-    let (wots_pk, _) = get_wots_keys();
-    assert_bitcom_lock(&wots_pk)
+    WotsKeySet::default_set().bitcom_lock_scripts()
 }
 
 pub fn borrow_bitcom_unlock_scripts() -> &'static Vec<Script> {
@@ -790,12 +1863,11 @@ pub fn borrow_bitcom_unlock_scripts() -> &'static Vec<Script> {
 }
 
 pub fn get_bitcom_unlock_scripts() -> Vec<Script> {
-    assert_unlock_scripts_from_file(config::WOTS_SIGNATURE_PATH, None, None)
+    WotsKeySet::default_set().bitcom_unlock_scripts(None)
 }
 
 pub fn get_corrupt_bitcom_unlock_scripts(corrupt_index: usize) -> Vec<Script> {
-    let (_, wots_sk) = get_wots_keys();
-    assert_unlock_scripts_from_file(config::WOTS_SIGNATURE_PATH, Some(corrupt_index), Some(wots_sk))
+    WotsKeySet::default_set().bitcom_unlock_scripts(Some(corrupt_index))
 }
 
 pub fn borrow_assert_tapscripts() -> &'static Vec<Script> {
@@ -803,47 +1875,112 @@ pub fn borrow_assert_tapscripts() -> &'static Vec<Script> {
 }
 
 pub fn get_assert_tapscripts() -> Vec<Script> {
-    load_all_assert_tapscripts_from_file(config::TAPSCRIPT_PATH)
+    WotsKeySet::default_set().assert_tapscripts()
 }
 
 pub fn get_signed_assertions() -> WotsSignatures {
-    load_all_signed_assertions_from_file(config::WOTS_SIGNATURE_PATH)
+    WotsKeySet::default_set().signed_assertions()
 }   
 
-pub fn corrupt_assertions(signed_assertions: &mut WotsSignatures, index: usize) {
-    let (_, wots_sk) = get_wots_keys();
+pub fn corrupt_assertions(key_set: &WotsKeySet, signed_assertions: &mut WotsSignatures, index: usize) {
+    let (_, wots_sk) = key_set.wots_keys();
     corrupt_signed_assertions(&wots_sk, signed_assertions, index);
 }   
 
-pub fn validate_assert_bitcom(rpc: &Client, assert_txid: Txid, fake_index: Option<usize>) -> Result<Option<(usize, Script)>, String> {
-    fn validate(res: &mut Option<(usize, Script)>, vk: &VerifyingKey, signed_asserts: WotsSignatures, inpubkeys: WotsPublicKeys) {
-        *res = validate_assertions(&vk, signed_asserts, inpubkeys);
-    }
+// read the signed assertions from the assert tx on chain and look for a disprovable leaf
+pub fn validate_assert_bitcom(rpc: &Client, assert_txid: Txid, key_set: &WotsKeySet) -> Result<Option<(usize, Script)>, String> {
+    let cache_name = assert_txid.to_string();
+    if let Ok(v) = load_disprove_node_from_file(&key_set.disprove_dir, &cache_name) {
+        return Ok(Some(v))
+    };
 
-    if let Some(index) = fake_index {
-        if let Ok(v) = load_disprove_node_from_file(index) {
-            return Ok(Some(v))
+    let signed_assertions = match extract_signed_assertions(rpc, assert_txid) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to extract_signed_assertions: {}", e))
+    };
+    let res = verify_signed_assertions(key_set, signed_assertions);
+    if let Some((leaf_index, hint_script)) = &res {
+        if let Err(e) = save_disprove_node_to_file(&key_set.disprove_dir, &cache_name, *leaf_index, hint_script) {
+            warn!("fail to cache disprove node of {assert_txid}: {}", e);
         };
     };
+    Ok(res)
+}
 
-    let signed_assertions = match fake_index {
-        Some(index) => {
-            let mut ss = get_signed_assertions();
-            corrupt_assertions(&mut ss, index);
-            ss
-        },
-        _ => match extract_signed_assertions(rpc, assert_txid) {
-            Ok(v) => v,
-            Err(e) => return Err(format!("fail to extract_signed_assertions: {}", e))
-        },
+// assert spends kickoff_2:1, kickoff_2 spends kickoff_1:1; the wots key is derived from kickoff_1
+pub fn get_kick_off_1_txid_of_assert(rpc: &Client, assert_txid: Txid) -> Result<Txid, String> {
+    let assert_tx = match utils::get_raw_tx(rpc, assert_txid) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get assert tx: {}", e))
     };
-    let (vk, _, _) = load_proof_from_file(config::PROOF_PATH);
-    let (wots_pk, _) = get_wots_keys();
+    let kick_off_2_txid = match assert_tx.input.get(0) {
+        Some(input) => input.previous_output.txid,
+        _ => return Err(format!("{assert_txid} has no input")),
+    };
+    let kick_off_2_tx = match utils::get_raw_tx(rpc, kick_off_2_txid) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get kickoff_2 tx: {}", e))
+    };
+    match kick_off_2_tx.input.get(0) {
+        Some(input) => Ok(input.previous_output.txid),
+        _ => Err(format!("{kick_off_2_txid} has no input")),
+    }
+}
+
+// return: None if all assertions are valid, otherwise (failing tapleaf index, hint script)
+pub fn verify_signed_assertions(key_set: &WotsKeySet, signed_assertions: WotsSignatures) -> Option<(usize, Script)> {
+    fn validate(res: &mut Option<(usize, Script)>, vk: &VerifyingKey, signed_asserts: WotsSignatures, inpubkeys: WotsPublicKeys) {
+        *res = validate_assertions(&vk, signed_asserts, inpubkeys);
+    }
+
+    let (vk, _, _) = load_proof_from_file(&key_set.proof_path);
+    let (wots_pk, _) = key_set.wots_keys();
     let mut res = None;
     utils::suppress_output(||{
         validate(&mut res, &vk, signed_assertions, wots_pk);
     });
-    Ok(res)
+    res
+}
+
+// sign the proof in `proof_path` with the default wots key into `scratch_dir` and validate it, nothing is broadcast
+pub fn verify_proof(proof_path: &str, scratch_dir: &str) -> Result<Option<(usize, Script)>, String> {
+    let mut key_set = WotsKeySet::default_set();
+    key_set.proof_path = proof_path.to_string();
+    key_set.signature_dir = format!("{scratch_dir}/signed_assertions");
+    setup::generate_signed_assertions_for(&key_set.signature_dir, &key_set.proof_path, &key_set.wots_secret);
+    let signed_assertions = key_set.signed_assertions();
+    Ok(verify_signed_assertions(&key_set, signed_assertions))
+}
+
+// validate assertions given as the contents of signed_assertion_{i}.json, nothing is broadcast
+pub fn verify_signed_assertion_files(key_set: &WotsKeySet, scratch_dir: &str, files: &Vec<serde_json::Value>) -> Result<Option<(usize, Script)>, String> {
+    if files.len() != config::N_ASSERTIONS {
+        return Err(format!("expect {} signed assertions, got {}", config::N_ASSERTIONS, files.len()))
+    };
+    for i in 0..files.len() {
+        if let Err(e) = std::fs::write(format!("{scratch_dir}/signed_assertion_{i}.json"), files[i].to_string()) {
+            return Err(format!("fail to write signed_assertion_{i}: {}", e))
+        };
+    }
+    let mut key_set = key_set.clone();
+    key_set.signature_dir = scratch_dir.to_string();
+    let signed_assertions = key_set.signed_assertions();
+    Ok(verify_signed_assertions(&key_set, signed_assertions))
+}
+
+// validate the key set's own signed assertions (corrupted at `fake_index`), they are signed
+// into `scratch_dir` if the workflow has not generated them yet
+pub fn verify_stored_assertions(key_set: &WotsKeySet, scratch_dir: &str, fake_index: Option<usize>) -> Result<Option<(usize, Script)>, String> {
+    let mut key_set = key_set.clone();
+    if !setup::is_signed_assertions_generated(&key_set.signature_dir) {
+        key_set.signature_dir = format!("{scratch_dir}/signed_assertions");
+        setup::generate_signed_assertions_for(&key_set.signature_dir, &key_set.proof_path, &key_set.wots_secret);
+    };
+    let mut signed_assertions = key_set.signed_assertions();
+    if let Some(index) = fake_index {
+        corrupt_assertions(&key_set, &mut signed_assertions, index);
+    };
+    Ok(verify_signed_assertions(&key_set, signed_assertions))
 }
 
 pub fn extract_signed_assertions(rpc: &Client, assert_txid: Txid) -> Result<WotsSignatures, String> {
@@ -853,16 +1990,17 @@ pub fn extract_signed_assertions(rpc: &Client, assert_txid: Txid) -> Result<Wots
     }
 }
 
-fn load_disprove_node_from_file(fake_index: usize) -> Result<(usize, Script), String> {
+#[derive(serde::Serialize, serde::Deserialize)]
+struct DisproveInput {
+    leaf_index: usize,
+    hint_script: Vec<u8>,   
+}
+
+fn load_disprove_node_from_file(disprove_dir: &str, name: &str) -> Result<(usize, Script), String> {
     use std::fs::OpenOptions;
-    use std::io::{Write, BufReader, BufRead};
-    #[derive(serde::Serialize, serde::Deserialize)]
-    struct DisproveInput {
-        leaf_index: usize,
-        hint_script: Vec<u8>,   
-    }
+    use std::io::BufReader;
 
-    let res_file_name = &format!("{}/disprove_{fake_index}.json", config::PRE_COMPUTED_DISPROVE_PATH);
+    let res_file_name = &format!("{}/disprove_{name}.json", disprove_dir);
     let file = match OpenOptions::new().read(true).open(res_file_name) {
         Ok(f) => f,
         Err(e) => return Err(format!("fail to open {res_file_name}: {}",e)),
@@ -878,8 +2016,19 @@ fn load_disprove_node_from_file(fake_index: usize) -> Result<(usize, Script), St
     Ok((res.leaf_index, hint_script))
 }
 
-fn get_wots_keys() -> (WotsPublicKeys, WotsSecretKeys) {
-    generate_wots_keys_from_secrets(config::WOTS_SECRET)
+fn save_disprove_node_to_file(disprove_dir: &str, name: &str, leaf_index: usize, hint_script: &Script) -> Result<(), String> {
+    if let Err(e) = std::fs::create_dir_all(disprove_dir) {
+        return Err(format!("fail to create {disprove_dir}: {}",e))
+    };
+    let res_file_name = &format!("{}/disprove_{name}.json", disprove_dir);
+    let res = DisproveInput {
+        leaf_index,
+        hint_script: hint_script.clone().compile().to_bytes(),
+    };
+    match std::fs::write(res_file_name, serde_json::to_string(&res).unwrap()) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("fail to write {res_file_name}: {}",e)),
+    }
 }
 
 fn get_revealers<'a>(n_of_n_taproot_public_key: &XOnlyPublicKey, bitcom_lock_scripts: &'a Vec<Script>) -> Vec<Revealer<'a>> {
@@ -891,6 +2040,28 @@ fn get_revealers<'a>(n_of_n_taproot_public_key: &XOnlyPublicKey, bitcom_lock_scr
     revealers
 }
 
+// connector_c address of the key set if already known, generating it may take minutes
+pub fn peek_connector_c_address(key_set: &WotsKeySet) -> Option<Address> {
+    if key_set.is_default() {
+        return Some(get_precomputed_connector_c_address())
+    };
+    let cell = WORKFLOW_CONNECTOR_C.lock().unwrap().get(&key_set.tapscript_dir).cloned()?;
+    cell.get().map(|c| c.address.clone())
+}
+
+// revealer addresses of the key set (outputs of kick_off_2) if its lock scripts are already known,
+// generating them may take minutes
+pub fn peek_workflow_revealers_address(key_set: &WotsKeySet) -> Option<Vec<Address>> {
+    if key_set.is_default() {
+        return Some(REVEALERS_ADDRESS.clone())
+    };
+    let cell = WORKFLOW_BITCOM_LOCK_SCRIPTS.lock().unwrap().get(&key_set.tapscript_dir).cloned()?;
+    let bitcom_lock_scripts = cell.get()?;
+    let operator_context = config::get_operator_context();
+    let revealers = get_revealers(&operator_context.n_of_n_taproot_public_key, bitcom_lock_scripts);
+    Some(revealers.into_iter().map(|r| r.generate_taproot_address()).collect())
+}
+
 fn get_revealers_address() -> Vec<Address> {
     let operator_context = config::get_operator_context();
     let revealers = get_revealers(&operator_context.n_of_n_taproot_public_key, borrow_bitcom_lock_scripts());
@@ -898,3 +2069,56 @@ fn get_revealers_address() -> Vec<Address> {
         .map(|r| r.generate_taproot_address())
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn cache_cell_generates_once_per_key() {
+        let cache: Mutex<HashMap<String, CacheCell<usize>>> = Mutex::new(HashMap::new());
+        let calls = AtomicUsize::new(0);
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    let cell = cache_cell(&cache, "workflow");
+                    let v = cell.get_or_init(|| Arc::new(calls.fetch_add(1, Ordering::SeqCst)));
+                    assert_eq!(**v, 0);
+                });
+            }
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(cache_cell(&cache, "other").get().is_none());
+        assert_eq!(cache.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn signed_assertion_files_count_checked() {
+        let scratch_dir = tempfile::tempdir().unwrap();
+        let dir = scratch_dir.path().to_string_lossy().to_string();
+        let files = vec![serde_json::json!({}); config::N_ASSERTIONS - 1];
+        assert!(verify_signed_assertion_files(&WotsKeySet::default_set(), &dir, &files).is_err());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    }
+    #[test]
+    fn scheduled_broadcast_not_sent() {
+        let txid = Txid::from_str(&"11".repeat(32)).unwrap();
+        let fee_info = new_fee_info(Amount::from_sat(300), 150, 2);
+        assert_eq!((fee_info.fee_rate, fee_info.required_fee), (2, Amount::from_sat(300)));
+        assert!(new_fee_info(Amount::from_sat(299), 150, 2).fee < new_fee_info(Amount::from_sat(299), 150, 2).required_fee);
+        assert_eq!(Broadcast::Sent(txid, fee_info).sent(), Ok(txid));
+        assert_eq!(Broadcast::Sent(txid, fee_info).scheduled_height(), None);
+        assert!(Broadcast::Scheduled(txid, 120, fee_info).sent().is_err());
+        assert_eq!(Broadcast::Scheduled(txid, 120, fee_info).scheduled_height(), Some(120));
+        assert_eq!(Broadcast::Scheduled(txid, 120, fee_info).fee_info(), fee_info);
+
+        let mut user_data: UserData = serde_json::from_str(r#"{"status":0}"#).unwrap();
+        assert!(user_data.scheduled.is_none());
+        assert_eq!(get_timelock_parent(&user_data, "take2"), Ok(None));
+        user_data.record_tx("take2", txid).unwrap();
+        assert_eq!(user_data.take_2, Some(txid));
+        assert_eq!(user_data.status, sql::STATUS::TAKE2 as u8);
+        assert!(user_data.record_tx("assert", txid).is_err());
+    }
+}
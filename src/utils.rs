@@ -7,19 +7,20 @@ use actix_web::Error;
 use shh::stdout as shh_stdout;
 use tokio::time::{sleep, Duration};
 use core::str::FromStr;
+use std::collections::{hash_map::Entry, HashMap};
 use bitcoin_hashes::Hash;
 use bitcoin_hashes_origin::hex::{FromHex, ToHex};
-use bitcoincore_rpc::{Client, Auth, RpcApi};
+use bitcoincore_rpc::{Client, RpcApi};
 use bitcoin::consensus::{encode, deserialize};
 use bitcoin::{
     Address, Amount, OutPoint, Transaction, TxIn, 
     TxOut, Txid, ScriptBuf, Witness, Sequence,
-    EcdsaSighashType, secp256k1::{Message, SecretKey, rand}, sighash::SighashCache,
+    EcdsaSighashType, PublicKey, secp256k1::{Keypair, Message, Secp256k1, SecretKey, rand}, sighash::SighashCache,
 };
 use bitvm::treepp::*;
 use bitvm::bridge::scripts;
 use bitvm::bridge::contexts::base;
-use crate::config;
+use crate::{config, metrics, rpc::{self, RpcClient}};
 use log::{info, error};
 
 pub fn address_from_str(addr_str: &str) -> Result<Address, String> {
@@ -52,22 +53,33 @@ pub async fn check_rpc() -> bool {
             return false
         },
     };
-    match mint_block(&rpc, 1) {
+    // read-only: the node must answer & the faucet wallet must be loaded
+    if let Err(e) = get_block_count(&rpc) {
+        error!("fail to get chain tip: {}",e);
+        return false
+    };
+    match get_wallet_balance(&rpc) {
         Ok(_) => true,
         Err(e) => { 
-            error!("fail to mint block: {}",e);
+            error!("fail to get faucet wallet: {}",e);
             false
         },
     }
 }
 
-pub async fn new_rpc_client() -> Result<Client, String> {
-    match Client::new(config::RPC_URL, Auth::UserPass(config::RPCUSER.to_string(), config::RPCPASSWORD.to_string())) {
-        Ok(v) => Ok(v),
+// a client of the shared pool, for code running outside the http handlers
+pub async fn new_rpc_client() -> Result<RpcClient, String> {
+    match rpc::shared() {
+        Ok(pool) => Ok(pool.get()),
         Err(e) => Err(format!("fail to connect to node: {}",e)),
     }
 }
 
+pub fn random_hex(n_bytes: usize) -> String {
+    let bytes: Vec<u8> = (0..n_bytes).map(|_| rand::random::<u8>()).collect();
+    hex::encode(bytes)
+}
+
 pub fn dead_address() -> Address {
     Address::p2sh(&script!{OP_RETURN}.compile(), config::network()).unwrap()
 } 
@@ -84,6 +96,17 @@ pub fn tx_wrapper(tx: &Transaction) -> String {
     encode::serialize_hex(tx)
 }
 
+pub fn tx_from_hex(tx_hex: &str) -> Result<Transaction, String> {
+    let tx_bytes = match hex::decode(tx_hex) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to decode tx hex: {}",e))
+    };
+    match deserialize(&tx_bytes) {
+        Ok(v) => Ok(v),
+        Err(e) => Err(format!("fail to deserialize tx: {}",e))
+    }
+}
+
 pub fn tx_unwrapper(tx: &bitcoin_origin::Transaction) -> Transaction {
     let tx_bytes = bitcoin_origin::consensus::serialize(tx);
     deserialize(&tx_bytes).unwrap()
@@ -114,8 +137,15 @@ pub fn txid_unwrapper(txid: bitcoin_origin::Txid) -> Txid {
 
 pub fn broadcast_tx(rpc: &Client, tx: &Transaction) -> Result<bool, String> {
     match rpc.send_raw_transaction(tx_wrapper(tx)) {
-        Ok(_) => Ok(true),
-        Err(e) => Err(format!("fail to broadcast_tx: {}",e)),
+        Ok(_) => {
+            metrics::inc_counter(metrics::BROADCAST_TOTAL, &[("result", "success"), ("reason", "")], 1.0);
+            Ok(true)
+        },
+        Err(e) => {
+            let e = e.to_string();
+            metrics::inc_counter(metrics::BROADCAST_TOTAL, &[("result", "failure"), ("reason", metrics::broadcast_failure_reason(&e))], 1.0);
+            Err(format!("fail to broadcast_tx: {}",e))
+        },
     }
 }
 
@@ -142,6 +172,24 @@ pub fn validate_tx(rpc: &Client, txid: Txid) -> Result<bool, String> {
     }
 }
 
+// 0 while the tx is still in the mempool
+pub fn get_tx_confirmations(rpc: &Client, txid: Txid) -> Result<u32, String> {
+    match rpc.get_raw_transaction_info(&txid_wrapper(txid), None) {
+        Ok(info) => Ok(info.confirmations.unwrap_or(0)),
+        Err(e) => Err(format!("fail to get tx info: {}",e))
+    }
+}
+
+pub fn get_utxo_confirmations(rpc: &Client, txid: Txid, vout: u32) -> Result<u32, String> {
+    match rpc.get_tx_out(&txid_wrapper(txid), vout, Some(true)) {
+        Ok(res_option) => match res_option {
+            Some(res) => Ok(res.confirmations),
+            _ => Err("fail when get_utxo_confirmations: no such outpoint".to_string()),
+        },
+        Err(e) => Err(format!("fail to get tx out: {}",e))
+    }
+}
+
 pub fn get_utxo_value(rpc: &Client, txid: Txid, vout: u32) -> Result<Amount, String> {
     match rpc.get_tx_out(&txid_wrapper(txid), vout, Some(true)) {
         Ok(res_option) => match res_option {
@@ -170,6 +218,87 @@ pub fn get_raw_tx(rpc: &Client, txid: Txid) -> Result<Transaction, String> {
     Ok(tx_unwrapper(&tx))
 }
 
+// txs fetched while serving one request, the inputs of a tx often spend several outputs of the same tx
+#[derive(Default)]
+pub struct TxCache {
+    txs: HashMap<Txid, Transaction>,
+}
+
+impl TxCache {
+    pub fn get_raw_tx(&mut self, rpc: &Client, txid: Txid) -> Result<&Transaction, String> {
+        match self.txs.entry(txid) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(get_raw_tx(rpc, txid)?)),
+        }
+    }
+}
+
+pub fn get_block_count(rpc: &Client) -> Result<u64, String> {
+    match rpc.get_block_count() {
+        Ok(v) => Ok(v),
+        Err(e) => Err(format!("fail to get block count: {}",e))
+    }
+}
+
+pub fn get_block_txs(rpc: &Client, height: u64) -> Result<Vec<Transaction>, String> {
+    let block_hash = match rpc.get_block_hash(height) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to get block hash at {height}: {}",e))
+    };
+    match rpc.get_block(&block_hash) {
+        Ok(block) => Ok(block.txdata.iter().map(|tx| tx_unwrapper(tx)).collect()),
+        Err(e) => Err(format!("fail to get block at {height}: {}",e))
+    }
+}
+
+// value of an outpoint, spent or not (gettxout misses outputs already spent in the mempool)
+pub fn get_prevout_value(rpc: &Client, outpoint: OutPoint) -> Result<Amount, String> {
+    if let Ok(v) = get_utxo_value(rpc, outpoint.txid, outpoint.vout) {
+        return Ok(v)
+    };
+    let prev_tx = match get_raw_tx(rpc, outpoint.txid) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    match prev_tx.output.get(outpoint.vout as usize) {
+        Some(txout) => Ok(txout.value),
+        _ => Err(format!("no such outpoint: {}:{}", outpoint.txid, outpoint.vout)),
+    }
+}
+
+pub fn get_tx_fee(rpc: &Client, tx: &Transaction) -> Result<Amount, String> {
+    let mut input_amount = Amount::ZERO;
+    for input in tx.input.iter() {
+        match get_prevout_value(rpc, input.previous_output) {
+            Ok(v) => input_amount += v,
+            Err(e) => return Err(format!("fail to get input value: {}",e))
+        };
+    }
+    let output_amount: Amount = tx.output.iter().map(|o| o.value).sum();
+    match input_amount.checked_sub(output_amount) {
+        Some(v) => Ok(v),
+        _ => Err("outputs exceed inputs".to_string()),
+    }
+}
+
+// in sat/vB: the configured FEE_RATE, else bitcoind's estimate, else FALLBACK_FEE_RATE
+pub fn get_fee_rate(rpc: &Client) -> u64 {
+    if config::FEE_RATE > 0 {
+        return config::FEE_RATE
+    };
+    match rpc.estimate_smart_fee(config::FEE_CONF_TARGET, None) {
+        Ok(res) => match res.fee_rate {
+            // BTC/kvB -> sat/vB, rounded up
+            Some(rate) => ((rate.to_sat() + 999) / 1000).max(1),
+            _ => config::FALLBACK_FEE_RATE,
+        },
+        Err(e) => {
+            error!("fail to estimate fee rate, fallback to {} sat/vB: {}", config::FALLBACK_FEE_RATE, e);
+            config::FALLBACK_FEE_RATE
+        }
+    }
+}
+
 pub fn decode_txid(txid_hex: &str) -> Result<Txid, String> {
     let mut txid_bytes = match hex::decode(txid_hex) {
         Ok(v) => v,
@@ -193,7 +322,7 @@ pub fn generate_stub_outpoint_batch(
     let total_amount = input_values.iter().map(|amount| amount.to_sat()).sum::<u64>() + 1_000_000;
     let network = config::network();
     let tmp_secret = SecretKey::new(&mut rand::thread_rng()).secret_bytes().to_hex();
-    let (secp, keypair, public_key) = base::generate_keys_from_secret(network, &tmp_secret);
+    let (_, keypair, public_key) = base::generate_keys_from_secret(network, &tmp_secret);
     let bulk_address = scripts::generate_pay_to_pubkey_script_address(network, &public_key);
     let fund_outpoint = match fund_utxo(rpc, &bulk_address, Amount::from_sat(total_amount)) {
         Ok(v) => v,
@@ -221,20 +350,7 @@ pub fn generate_stub_outpoint_batch(
     };
 
     // sign 
-    let scr = scripts::generate_pay_to_pubkey_script(&public_key);
-    let value = Amount::from_sat(total_amount);
-    let sighash_type = EcdsaSighashType::All;
-    let mut sighash_cache = SighashCache::new(&mut bulk_tx);
-    let sighash = sighash_cache
-        .p2wsh_signature_hash(0, &scr, value, sighash_type)
-        .expect("Failed to construct sighash");
-    let signature = secp.sign_ecdsa(&Message::from(sighash), &keypair.secret_key());
-    let signature = bitcoin::ecdsa::Signature {
-        signature,
-        sighash_type,
-    };
-    bulk_tx.input[0].witness.push_ecdsa_signature(&signature);
-    bulk_tx.input[0].witness.push(scr);
+    sign_p2wsh_p2pk_input(&mut bulk_tx, 0, &keypair, &public_key, Amount::from_sat(total_amount), EcdsaSighashType::All);
 
     // broadcast
     if let Err(e) = mint_block(rpc, 1) {
@@ -247,6 +363,96 @@ pub fn generate_stub_outpoint_batch(
     Ok((0..res_num).map(|vout| OutPoint{txid,vout}).collect())
 }
 
+// sign an input locked by `scripts::generate_pay_to_pubkey_script_address`
+pub fn sign_p2wsh_p2pk_input(tx: &mut Transaction, input_index: usize, keypair: &Keypair, public_key: &PublicKey, value: Amount, sighash_type: EcdsaSighashType) {
+    let secp = Secp256k1::new();
+    let scr = scripts::generate_pay_to_pubkey_script(public_key);
+    let mut sighash_cache = SighashCache::new(&mut *tx);
+    let sighash = sighash_cache
+        .p2wsh_signature_hash(input_index, &scr, value, sighash_type)
+        .expect("Failed to construct sighash");
+    let signature = secp.sign_ecdsa(&Message::from(sighash), &keypair.secret_key());
+    let signature = bitcoin::ecdsa::Signature {
+        signature,
+        sighash_type,
+    };
+    tx.input[input_index].witness = Witness::default();
+    tx.input[input_index].witness.push_ecdsa_signature(&signature);
+    tx.input[input_index].witness.push(scr);
+}
+
+// whether bitcoind would accept the tx (scripts, signatures, amounts), without broadcasting it
+pub fn test_mempool_accept(rpc: &Client, tx: &Transaction) -> Result<(), String> {
+    // maxfeerate 0: a trial tx may burn its whole input as fee
+    let res: serde_json::Value = match rpc.call("testmempoolaccept", &[serde_json::json!([tx_wrapper(tx)]), serde_json::json!(0)]) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to test mempool accept: {}",e))
+    };
+    match res.get(0) {
+        Some(v) if v.get("allowed").and_then(|a| a.as_bool()) == Some(true) => Ok(()),
+        Some(v) => Err(format!("rejected: {}", v.get("reject-reason").and_then(|r| r.as_str()).unwrap_or("unknown"))),
+        _ => Err(format!("unexpected testmempoolaccept result: {}", res)),
+    }
+}
+
+// submit parent(s) & child together so the child's fee counts for the whole package
+pub fn submit_package(rpc: &Client, txs: &Vec<Transaction>) -> Result<bool, String> {
+    let txs_hex: Vec<String> = txs.iter().map(tx_wrapper).collect();
+    let res: serde_json::Value = match rpc.call("submitpackage", &[serde_json::json!(txs_hex)]) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to submit package: {}",e))
+    };
+    match res.get("package_msg").and_then(|msg| msg.as_str()) {
+        Some("success") => Ok(true),
+        _ => Err(format!("package rejected: {}", res)),
+    }
+}
+
+// a confirmed utxo of the wallet worth at least `min_amount`, a child spending it has no other unconfirmed parent
+pub fn get_confirmed_wallet_utxo(rpc: &Client, min_amount: Amount) -> Result<(OutPoint, TxOut), String> {
+    let utxos = match rpc.list_unspent(Some(1), None, None, Some(false), None) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to list wallet utxos: {}",e))
+    };
+    match utxos.iter().filter(|u| u.spendable && amount_unwrapper(u.amount) >= min_amount).min_by_key(|u| u.amount) {
+        Some(u) => Ok((
+            OutPoint { txid: txid_unwrapper(u.txid), vout: u.vout },
+            TxOut { value: amount_unwrapper(u.amount), script_pubkey: ScriptBuf::from_bytes(u.script_pub_key.to_bytes()) },
+        )),
+        _ => Err(format!("no confirmed wallet utxo of at least {min_amount}")),
+    }
+}
+
+pub fn is_wallet_address(rpc: &Client, address: &Address) -> Result<bool, String> {
+    match rpc.get_address_info(&address_wrapper(address)) {
+        Ok(info) => Ok(info.is_mine.unwrap_or(false)),
+        Err(e) => Err(format!("fail to get address info: {}",e))
+    }
+}
+
+// sign the inputs the wallet holds keys for, `prevouts` describes the ones it can't look up (e.g. of an unbroadcast parent)
+pub fn sign_with_wallet(rpc: &Client, tx: &Transaction, prevouts: &[(OutPoint, TxOut)]) -> Result<Transaction, String> {
+    let prevtxs: Vec<serde_json::Value> = prevouts.iter()
+        .map(|(outpoint, txout)| serde_json::json!({
+            "txid": outpoint.txid.to_string(),
+            "vout": outpoint.vout,
+            "scriptPubKey": hex::encode(txout.script_pubkey.as_bytes()),
+            "amount": txout.value.to_btc(),
+        }))
+        .collect();
+    let res: serde_json::Value = match rpc.call("signrawtransactionwithwallet", &[serde_json::json!(tx_wrapper(tx)), serde_json::json!(prevtxs)]) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to sign with wallet: {}",e))
+    };
+    if res.get("complete").and_then(|v| v.as_bool()) != Some(true) {
+        return Err(format!("wallet can not sign every input: {}", res))
+    };
+    match res.get("hex").and_then(|v| v.as_str()) {
+        Some(tx_hex) => tx_from_hex(tx_hex),
+        _ => Err(format!("unexpected signrawtransactionwithwallet result: {}", res)),
+    }
+}
+
 pub fn generate_stub_outpoint(
     rpc: &Client,
     funding_utxo_address: &Address,
@@ -270,6 +476,25 @@ pub fn fund_utxo(rpc: &Client, address: &Address, amount: Amount) -> Result<OutP
     })
 }
 
+pub fn get_wallet_balance(rpc: &Client) -> Result<Amount, String> {
+    match rpc.get_balance(None, None) {
+        Ok(v) => Ok(amount_unwrapper(v)),
+        Err(e) => Err(format!("fail to get wallet balance: {}", e)),
+    }
+}
+
+// send `amount` (default: the whole balance) out of the faucet wallet, the fee is paid from the amount
+pub fn drain_wallet(rpc: &Client, address: &Address, amount: Option<Amount>) -> Result<Txid, String> {
+    let amount = match amount {
+        Some(v) => v,
+        _ => get_wallet_balance(rpc)?,
+    };
+    match rpc.send_to_address(&address_wrapper(address), amount_wrapper(amount), None, None, Some(true), None, None, None) {
+        Ok(v) => Ok(txid_unwrapper(v)),
+        Err(e) => Err(format!("fail to drain faucet wallet: {}", e)),
+    }
+}
+
 pub fn suppress_output<F>(f: F)
 where
     F: FnOnce(),
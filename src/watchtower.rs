@@ -0,0 +1,308 @@
+use bitcoin::{Address, Amount, Network, Transaction, Txid};
+use bitcoincore_rpc::Client;
+use rusqlite::Connection;
+use log::{info, warn, error};
+use crate::{config, sql, transactions, utils};
+
+enum WatchedTx {
+    KickOff1,
+    KickOff2,
+    Assert,
+    Other,
+}
+
+// run the watchtower on its own thread & runtime, the rpc client is blocking
+pub fn start() {
+    let res = std::thread::Builder::new()
+        .name("watchtower".to_string())
+        .spawn(|| {
+            let rt = tokio::runtime::Runtime::new().expect("fail to create watchtower runtime");
+            rt.block_on(run());
+        });
+    if let Err(e) = res {
+        error!("fail to start watchtower: {}", e);
+    };
+}
+
+pub async fn run() {
+    info!("watchtower: started, scanning every {}s", config::WATCHTOWER_INTERVAL);
+    loop {
+        if let Err(e) = scan().await {
+            error!("watchtower: {}", e);
+        };
+        utils::wait(config::WATCHTOWER_INTERVAL).await;
+    }
+}
+
+// scan blocks since the last scanned height, start from the current tip on first run
+async fn scan() -> Result<(), String> {
+    let db = match sql::open_db() {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to connect db: {}", e))
+    };
+    let rpc = match utils::new_rpc_client().await {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to connect bitcoind: {}", e))
+    };
+    let tip = match utils::get_block_count(&rpc) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    let start = match sql::get_watchtower_height(&db) {
+        Ok(Some(h)) => h + 1,
+        Ok(None) => tip,
+        Err(e) => return Err(e)
+    };
+    for height in start..=tip {
+        let txs = match utils::get_block_txs(&rpc, height) {
+            Ok(v) => v,
+            Err(e) => return Err(e)
+        };
+        for tx in txs.iter() {
+            if let Err(e) = handle_tx(&db, &rpc, tx).await {
+                // retry this block on the next round
+                return Err(format!("block {height}: {}", e))
+            };
+        }
+        if let Err(e) = sql::set_watchtower_height(&db, height) {
+            return Err(e)
+        };
+    }
+    Ok(())
+}
+
+fn classify(tx: &Transaction) -> WatchedTx {
+    let connector_a_script = transactions::get_connector_a_address().script_pubkey();
+    let connector_b_script = transactions::get_precomputed_connector_b_address().script_pubkey();
+    let connector_4_script = transactions::get_precomputed_connector_4_address().script_pubkey();
+    if tx.output.len() == 3 && tx.output[0].script_pubkey == connector_a_script {
+        WatchedTx::KickOff1
+    } else if tx.output.len() > 2 && tx.output[1].script_pubkey == connector_b_script {
+        WatchedTx::KickOff2
+    } else if tx.output.len() == 3 && tx.output[0].script_pubkey == connector_4_script {
+        WatchedTx::Assert
+    } else {
+        WatchedTx::Other
+    }
+}
+
+// Err: the block should be scanned again later, other failures are only logged
+async fn handle_tx(db: &Connection, rpc: &Client, tx: &Transaction) -> Result<(), String> {
+    let txid = tx.compute_txid();
+    match classify(tx) {
+        WatchedTx::KickOff1 => {
+            // only kickoffs of the bridge's own peg-ins, one not recorded yet is the operator's in flight
+            let workflow_id = match sql::get_workflow_id_by_txid(db, "kickoff_1", &txid) {
+                Ok(Some(v)) => v,
+                Ok(None) => {
+                    info!("watchtower: kickoff_1 {txid} of no known peg-in, skipped");
+                    return Ok(())
+                },
+                Err(e) => return Err(e),
+            };
+            // held until the challenge is recorded, the handlers update the same workflow
+            if let Err(e) = sql::lock_workflow(db, workflow_id) {
+                return Err(format!("workflow {workflow_id}: {}", e))
+            };
+            let res = challenge_workflow(db, rpc, workflow_id, txid).await;
+            if let Err(e) = sql::unlock_workflow(db, workflow_id) {
+                error!("watchtower: fail to unlock workflow {workflow_id}: {}", e);
+            };
+            res
+        },
+        WatchedTx::KickOff2 => {
+            // the kickoff_1 it spends was judged when it was mined
+            let kick_off_1_txid = tx.input[0].previous_output.txid;
+            info!("watchtower: kickoff_2 {txid} of kickoff_1 {kick_off_1_txid} observed");
+            Ok(())
+        },
+        WatchedTx::Assert => {
+            let kick_off_1_txid = match transactions::get_kick_off_1_txid_of_assert(rpc, txid) {
+                Ok(v) => v,
+                Err(e) => {
+                    error!("watchtower: fail to trace assert {txid}: {}", e);
+                    return Ok(())
+                }
+            };
+            let workflow_id = match sql::get_workflow_id_by_txid(db, "kickoff_1", &kick_off_1_txid) {
+                Ok(v) => v,
+                Err(e) => return Err(e),
+            };
+            let workflow_id = match workflow_id {
+                Some(v) => v,
+                None => return disprove_assert(db, rpc, None, kick_off_1_txid, txid),
+            };
+            // held until the disprove is recorded, the handlers update the same workflow
+            if let Err(e) = sql::lock_workflow(db, workflow_id) {
+                return Err(format!("workflow {workflow_id}: {}", e))
+            };
+            let res = disprove_assert(db, rpc, Some(workflow_id), kick_off_1_txid, txid);
+            if let Err(e) = sql::unlock_workflow(db, workflow_id) {
+                error!("watchtower: fail to unlock workflow {workflow_id}: {}", e);
+            };
+            res
+        },
+        WatchedTx::Other => Ok(()),
+    }
+}
+
+// the caller holds the workflow lock
+async fn challenge_workflow(db: &Connection, rpc: &Client, workflow_id: i32, txid: Txid) -> Result<(), String> {
+    let mut user_data = match sql::get_user_data(db, workflow_id) {
+        Ok(Some(v)) => v,
+        Ok(None) => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if !should_challenge(&user_data) {
+        info!("watchtower: kickoff_1 {txid} matches a recorded peg-out or is already challenged");
+        return Ok(())
+    };
+
+    warn!("watchtower: kickoff_1 {txid} has no matching withdrawal, challenging it");
+    let challenge_txid = match challenge(rpc, txid).await {
+        Ok(v) => v,
+        Err(e) => {
+            error!("watchtower: fail to challenge {txid}: {}", e);
+            return Ok(())
+        }
+    };
+    user_data.status = sql::STATUS::CHALLENGE as u8;
+    user_data.challenge = Some(challenge_txid);
+    if let Err(e) = sql::update_user_data(db, workflow_id, &user_data) {
+        error!("watchtower: fail to update workflow {workflow_id}: {}", e);
+    };
+    Ok(())
+}
+
+// the caller holds the lock of `workflow_id`, None: an assert of no known workflow
+fn disprove_assert(db: &Connection, rpc: &Client, workflow_id: Option<i32>, kick_off_1_txid: Txid, txid: Txid) -> Result<(), String> {
+    let user_data = match workflow_id {
+        Some(workflow_id) => match sql::get_user_data(db, workflow_id) {
+            Ok(v) => v,
+            Err(e) => return Err(e),
+        },
+        None => None,
+    };
+    if let Some(data) = &user_data {
+        if data.disprove.is_some() {
+            return Ok(())
+        };
+    };
+
+    let key_set = match &user_data {
+        Some(data) => transactions::get_workflow_key_set(kick_off_1_txid, data),
+        _ => transactions::WotsKeySet::for_workflow(kick_off_1_txid),
+    };
+    let disprove_txid = match disprove(rpc, txid, &key_set) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            info!("watchtower: assert {txid} is valid");
+            return Ok(())
+        },
+        Err(e) => {
+            error!("watchtower: fail to disprove assert {txid}: {}", e);
+            return Ok(())
+        }
+    };
+    warn!("watchtower: assert {txid} disproved by {disprove_txid}");
+
+    if let (Some(workflow_id), Some(mut data)) = (workflow_id, user_data) {
+        data.status = sql::STATUS::DISPROVE as u8;
+        data.assert = Some(txid);
+        data.disprove = Some(disprove_txid);
+        if let Err(e) = sql::update_user_data(db, workflow_id, &data) {
+            error!("watchtower: fail to update workflow {workflow_id}: {}", e);
+        };
+    };
+    Ok(())
+}
+
+// a kickoff_1 is only honest once the operator fronted the peg-out of its workflow
+fn should_challenge(user_data: &sql::UserData) -> bool {
+    user_data.peg_out.is_none() && user_data.challenge.is_none()
+}
+
+async fn challenge(rpc: &Client, kick_off_1_txid: Txid) -> Result<Txid, String> {
+    let tx = match transactions::challenge_prepare(rpc, kick_off_1_txid) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    broadcast_within_fee(rpc, &tx, "challenge")
+}
+
+// return: None if the assertions are valid
+fn disprove(rpc: &Client, assert_txid: Txid, key_set: &transactions::WotsKeySet) -> Result<Option<Txid>, String> {
+    match transactions::validate_assert_bitcom(rpc, assert_txid, key_set) {
+        Ok(Some(_)) => {},
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let connector_c = transactions::get_cached_connector_c(key_set);
+    let challenger_reward_address = match get_challenger_reward_address() {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    let tx = match transactions::disprove_prepare(
+        rpc,
+        assert_txid,
+        &connector_c.tapscripts,
+        Some(connector_c.address.clone()),
+        &connector_c.spend_info,
+        key_set,
+        challenger_reward_address,
+    ) {
+        Ok(v) => v,
+        Err(e) => return Err(e)
+    };
+    match broadcast_within_fee(rpc, &tx, "disprove") {
+        Ok(v) => Ok(Some(v)),
+        Err(e) => Err(e),
+    }
+}
+
+fn broadcast_within_fee(rpc: &Client, tx: &Transaction, tx_name: &str) -> Result<Txid, String> {
+    let fee = match utils::get_tx_fee(rpc, tx) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("fail to calc {tx_name} fee: {}", e))
+    };
+    let max_fee = Amount::from_sat(config::WATCHTOWER_MAX_FEE);
+    if fee > max_fee {
+        return Err(format!("{tx_name} fee {fee} exceeds max fee {max_fee}"))
+    };
+    if let Err(e) = utils::broadcast_tx(rpc, tx) {
+        return Err(format!("fail to broadcast {tx_name} tx: {}", e))
+    };
+    if config::network() == Network::Regtest {
+        if let Err(e) = utils::mint_block(rpc, 1) {
+            return Err(format!("fail to mint block: {}", e))
+        };
+    };
+    Ok(tx.compute_txid())
+}
+
+fn get_challenger_reward_address() -> Result<Option<Address>, String> {
+    if config::WATCHTOWER_CHALLENGER_REWARD_ADDRESS.is_empty() {
+        return Ok(None)
+    };
+    match utils::address_from_str(config::WATCHTOWER_CHALLENGER_REWARD_ADDRESS) {
+        Ok(v) => Ok(Some(v)),
+        Err(e) => Err(format!("invalid challenger reward address: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_data(json: &str) -> sql::UserData {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn challenge_kickoffs_without_peg_out() {
+        let txid = "0000000000000000000000000000000000000000000000000000000000000001";
+        assert!(should_challenge(&user_data(r#"{"status":3}"#)));
+        assert!(!should_challenge(&user_data(&format!(r#"{{"status":3,"peg_out":"{txid}"}}"#))));
+        assert!(!should_challenge(&user_data(&format!(r#"{{"status":5,"challenge":"{txid}"}}"#))));
+    }
+}
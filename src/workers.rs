@@ -0,0 +1,95 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use once_cell::sync::Lazy;
+use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use crate::config;
+
+// the rpc client & the transactions steps block (sync bitcoind calls, signing, script building),
+// they run here so the actix workers keep answering other requests meanwhile
+static WORKER_RUNTIME: Lazy<Runtime> = Lazy::new(|| {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(config::WORKER_THREADS)
+        .thread_name("worker")
+        .enable_all()
+        .build()
+        .expect("fail to create worker runtime")
+});
+
+// assertion/proof validation takes minutes of cpu, at most VALIDATION_WORKERS run at once
+static VALIDATION_PERMITS: Lazy<Arc<Semaphore>> = Lazy::new(|| Arc::new(Semaphore::new(config::VALIDATION_WORKERS)));
+static VALIDATION_PENDING: AtomicUsize = AtomicUsize::new(0);
+
+pub fn init() {
+    let _ = &*WORKER_RUNTIME;
+    let _ = &*VALIDATION_PERMITS;
+}
+
+// drop-in for tokio::spawn, the task runs on the worker runtime
+pub fn spawn<F>(fut: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    WORKER_RUNTIME.spawn(fut)
+}
+
+// run a fallible step on the worker runtime & wait for it, a panic is reported as an error
+pub async fn run<F, T>(fut: F) -> Result<T, String>
+where
+    F: Future<Output = Result<T, String>> + Send + 'static,
+    T: Send + 'static,
+{
+    match spawn(fut).await {
+        Ok(res) => res,
+        Err(e) => Err(format!("worker task failed: {}", e)),
+    }
+}
+
+// same as `run` for sync calls (e.g. bitcoind rpc)
+pub async fn run_blocking<F, T>(f: F) -> Result<T, String>
+where
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    match WORKER_RUNTIME.spawn_blocking(f).await {
+        Ok(res) => res,
+        Err(e) => Err(format!("worker task failed: {}", e)),
+    }
+}
+
+pub fn validation_pending() -> usize {
+    VALIDATION_PENDING.load(Ordering::SeqCst)
+}
+
+struct PendingGuard;
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        VALIDATION_PENDING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// queue a validation job, Err when VALIDATION_WORKERS jobs run & VALIDATION_QUEUE_SIZE more are waiting
+pub fn spawn_validation<F, T>(f: F) -> Result<JoinHandle<T>, String>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let limit = config::VALIDATION_WORKERS + config::VALIDATION_QUEUE_SIZE;
+    let reserved = VALIDATION_PENDING.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+        if n < limit { Some(n + 1) } else { None }
+    });
+    if reserved.is_err() {
+        return Err(format!("validation queue is full ({limit} jobs), please retry later"))
+    };
+    let guard = PendingGuard;
+    let permits = VALIDATION_PERMITS.clone();
+    Ok(WORKER_RUNTIME.spawn(async move {
+        let _guard = guard;
+        let _permit = permits.acquire_owned().await.expect("validation semaphore closed");
+        tokio::task::block_in_place(f)
+    }))
+}